@@ -1,4 +1,7 @@
 use erl_ast::ast;
+use num::BigInt;
+use num::ToPrimitive;
+use num::Zero;
 use ty;
 
 pub trait FromAst {
@@ -24,29 +27,37 @@ impl FromAst for ty::Type {
         use erl_ast::ast::ty::Type as AstType;
         match *ty {
             AstType::Atom(ref x) => ty::atom(&x.value),
-            AstType::Integer(ref x) => {
-                use ::num::traits::ToPrimitive;
-                if let Some(v) = x.value.to_i64() {
-                    From::from(ty::integer().value(v))
-                } else {
-                    From::from(ty::integer())
-                }
-            }
+            AstType::Integer(ref x) => From::from(ty::integer().value(x.value.clone())),
             AstType::Var(ref x) => From::from(ty::Var::new(&x.name)),
             AstType::Annotated(ref x) => {
                 From::from(ty::Var::with_value(&x.name.name, FromAst::from_ast(&x.ty)))
             }
             AstType::UnaryOp(ref x) => {
-                assert_eq!("-", x.operator);
                 let operand = if let ty::Type::Integer(operand) = FromAst::from_ast(&x.operand) {
-                    operand.get_single_value().unwrap()
+                    operand.get_single_value()
                 } else {
                     panic!("{:?}", x.operand);
                 };
-                From::from(ty::integer().value(-operand))
+                let value = operand.and_then(|v| integer_unary_op(&x.operator, v));
+                value.map_or_else(ty::integer, |v| ty::integer().value(v)).into()
+            }
+            AstType::BinaryOp(ref x) => {
+                let operand = |t: &AstType| if let ty::Type::Integer(t) = FromAst::from_ast(t) {
+                    t.get_single_value()
+                } else {
+                    None
+                };
+                let (l, r) = (operand(&x.left_operand), operand(&x.right_operand));
+                let value = l.and_then(|l| r.and_then(|r| integer_binary_op(&x.operator, l, r)));
+                value.map_or_else(ty::integer, |v| ty::integer().value(v)).into()
+            }
+            AstType::BitString(ref x) => {
+                // NOTE: `<<_:M, _:_*N>>` (`0` means "absent")
+                From::from(ty::BitstringType {
+                    bits: x.bytes as usize,
+                    align: x.tail_bits as usize,
+                })
             }
-            AstType::BinaryOp(_) => panic!("{:?}", ty),
-            AstType::BitString(_) => panic!("{:?}", ty),
             AstType::Nil(_) => From::from(ty::NilType),
             AstType::AnyFun(_) => From::from(ty::FunType::any()),
             AstType::Function(ref x) => {
@@ -82,6 +93,7 @@ impl FromAst for ty::Type {
                         .iter()
                         .map(|p| {
                             ty::MapPair {
+                                is_assoc: p.is_assoc,
                                 key: FromAst::from_ast(&p.key),
                                 value: FromAst::from_ast(&p.value),
                             }
@@ -90,11 +102,11 @@ impl FromAst for ty::Type {
                 })
             }
             AstType::BuiltIn(ref x) => {
-                From::from(ty::builtin(&x.name,
-                                       &x.args
-                                           .iter()
-                                           .map(FromAst::from_ast)
-                                           .collect::<Vec<_>>()))
+                ty::builtin(&x.name,
+                            &x.args
+                                .iter()
+                                .map(FromAst::from_ast)
+                                .collect::<Vec<_>>())
             }
             AstType::Record(ref x) => {
                 From::from(ty::RecordType {
@@ -134,3 +146,36 @@ impl FromAst for ty::Type {
         }
     }
 }
+
+pub fn integer_unary_op(operator: &str, v: BigInt) -> Option<BigInt> {
+    match operator {
+        "-" => Some(-v),
+        "+" => Some(v),
+        "bnot" => Some(!v),
+        _ => None,
+    }
+}
+
+pub fn integer_binary_op(operator: &str, l: BigInt, r: BigInt) -> Option<BigInt> {
+    match operator {
+        "+" => Some(l + r),
+        "-" => Some(l - r),
+        "*" => Some(l * r),
+        // NOTE: `div` and `rem` truncate toward zero like `/` and `%` of `BigInt`
+        "div" if !r.is_zero() => Some(l / r),
+        "rem" if !r.is_zero() => Some(l % r),
+        "band" => Some(l & r),
+        "bor" => Some(l | r),
+        "bxor" => Some(l ^ r),
+        "bsl" | "bsr" => {
+            let shift = r.to_i64().filter(|r| r.abs() <= ty::MAX_SHIFT)?;
+            let shift = if operator == "bsl" { shift } else { -shift };
+            if shift >= 0 {
+                Some(l << shift as usize)
+            } else {
+                Some(l >> (-shift) as usize)
+            }
+        }
+        _ => None,
+    }
+}
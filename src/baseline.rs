@@ -0,0 +1,121 @@
+//! Known findings which are not reported until they change.
+//!
+//! The baseline is a JSON document of the following form:
+//!
+//! ```text
+//! {"format": "diagnoser-baseline",
+//!  "version": 1,
+//!  "findings": [{"id": "no-return",
+//!                "mfa": "foo:bar/1",
+//!                "message": "Function bar/1 has no local return",
+//!                "fingerprint": "<see `Diagnostic::fingerprint` (in hex)>"}]}
+//! ```
+//!
+//! Only the fingerprints are used for matching; the other fields are for humans.
+//! Since fingerprints do not depend on line numbers, findings remain hidden
+//! when unrelated code moves them around.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use diagnostic::Diagnostic;
+use json::Json;
+use module::Result;
+
+pub const FORMAT: &str = "diagnoser-baseline";
+pub const VERSION: i64 = 1;
+
+#[derive(Debug, Default)]
+pub struct Baseline {
+    findings: BTreeMap<u64, Finding>,
+}
+impl Baseline {
+    pub fn new() -> Self {
+        Baseline::default()
+    }
+    pub fn from_diagnostics(diagnostics: &[Diagnostic]) -> Self {
+        let findings = diagnostics.iter()
+            .map(|d| {
+                let finding = Finding {
+                    id: d.kind.id().to_string(),
+                    mfa: format!("{}:{}/{}", d.function.module, d.function.name, d.function.arity),
+                    message: d.message.clone(),
+                };
+                (d.fingerprint(), finding)
+            })
+            .collect();
+        Baseline { findings }
+    }
+
+    pub fn len(&self) -> usize {
+        self.findings.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+    pub fn contains(&self, diagnostic: &Diagnostic) -> bool {
+        self.findings.contains_key(&diagnostic.fingerprint())
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let json = Json::parse(&text)?;
+        Baseline::from_json(&json)
+            .map_err(|e| format!("Invalid baseline: {}: {}", path.display(), e).into())
+    }
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, format!("{:#}\n", self.to_json()))?;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Json {
+        let findings = self.findings
+            .iter()
+            .map(|(fingerprint, f)| {
+                Json::object(vec![("id", Json::string(&f.id)),
+                                  ("mfa", Json::string(&f.mfa)),
+                                  ("message", Json::string(&f.message)),
+                                  ("fingerprint", Json::string(format!("{:016x}", fingerprint)))])
+            })
+            .collect();
+        Json::object(vec![("format", Json::from(FORMAT)),
+                          ("version", Json::Integer(VERSION)),
+                          ("findings", Json::Array(findings))])
+    }
+    pub fn from_json(json: &Json) -> Result<Self> {
+        if json.get("format").and_then(Json::as_str) != Some(FORMAT) {
+            return Err("Unknown format".into());
+        }
+        let version = json.get("version").and_then(Json::as_i64);
+        if version != Some(VERSION) {
+            return Err(format!("Unsupported version: {:?}", version).into());
+        }
+        let findings = json.get("findings").and_then(Json::as_array).ok_or("Missing `findings`")?;
+        let mut baseline = Baseline::new();
+        for f in findings {
+            let field = |key: &str| {
+                f.get(key)
+                    .and_then(Json::as_str)
+                    .map(|s| s.to_string())
+                    .ok_or_else(|| format!("Missing `{}`", key))
+            };
+            let fingerprint = field("fingerprint")?;
+            let fingerprint = u64::from_str_radix(&fingerprint, 16)
+                .map_err(|_| format!("Invalid fingerprint: {}", fingerprint))?;
+            let finding = Finding {
+                id: field("id")?,
+                mfa: field("mfa")?,
+                message: field("message")?,
+            };
+            baseline.findings.insert(fingerprint, finding);
+        }
+        Ok(baseline)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Finding {
+    id: String,
+    mfa: String,
+    message: String,
+}
@@ -1,29 +1,61 @@
 use std::path::Path;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use erl_ast::AST;
 use erl_ast::ast;
+use erl_ast::error::FromBeamError;
 use erl_ast::result::FromBeamResult;
 
 #[derive(Debug)]
 pub struct Module {
+    pub name: String,
     pub dependent_modules: HashSet<String>,
-    pub client_modules: HashSet<String>,
+    pub client_modules: HashSet<String>, // See `resolve_client_modules`
     pub ast: AST,
 }
 
 impl Module {
+    #[allow(clippy::result_large_err)]
     pub fn from_beam_file<P: AsRef<Path>>(beam_file: P) -> FromBeamResult<Self> {
-        let ast = try!(AST::from_beam_file(beam_file));
-        let dependent_modules = collect_dependent_modules_from_ast(&ast);
+        let ast = AST::from_beam_file(beam_file)?;
+        let name = ast.module
+            .forms
+            .iter()
+            .filter_map(|f| if let ast::form::Form::Module(ref m) = *f {
+                Some(m.name.clone())
+            } else {
+                None
+            })
+            .nth(0)
+            .ok_or(FromBeamError::NoModuleAttribute)?;
+        let mut dependent_modules = collect_dependent_modules_from_ast(&ast);
+        dependent_modules.remove(&name);
         Ok(Module {
-            ast: ast,
-            dependent_modules: dependent_modules,
+            name,
+            ast,
+            dependent_modules,
             client_modules: HashSet::new(),
         })
     }
 }
 
-fn collect_dependent_modules_from_ast(ast: &AST) -> HashSet<String> {
+/// Sets `client_modules` of each module to the modules (in `modules`) which depend on it.
+///
+/// The dependencies are reversed in one pass over `modules`.
+pub fn resolve_client_modules(modules: &mut [Module]) {
+    let mut clients: HashMap<String, HashSet<String>> = HashMap::new();
+    for m in modules.iter() {
+        for d in &m.dependent_modules {
+            clients.entry(d.clone()).or_default().insert(m.name.clone());
+        }
+    }
+    for m in modules.iter_mut() {
+        m.client_modules = clients.remove(&m.name).unwrap_or_default();
+    }
+}
+
+/// Collects the modules referred by imports and remote calls in `ast`.
+pub fn collect_dependent_modules_from_ast(ast: &AST) -> HashSet<String> {
     let mut modules = HashSet::new();
     for f in &ast.module.forms {
         collect_dependent_modules_from_form(f, &mut modules);
@@ -132,7 +164,7 @@ fn collect_dependent_modules_from_expr(expr: &ast::expr::Expression,
                         collect_dependent_modules_from_expr(&g.expr, modules);
                     }
                     ast::expr::Qualifier::Filter(ref f) => {
-                        collect_dependent_modules_from_expr(&f, modules);
+                        collect_dependent_modules_from_expr(f, modules);
                     }
                 }
             }
@@ -0,0 +1,11 @@
+-module(cli).
+-export([f/0, g/1, h/0]).
+-spec g(prov:t()) -> any().
+f() ->
+    Q = prov:new(),
+    element(1, Q).
+g({prov, _}) -> ok;
+g(X) when is_tuple(X) -> ok.
+h() ->
+    Q = prov:new(),
+    prov:peek(Q).
@@ -0,0 +1,8 @@
+-module(prov).
+-export([new/0, peek/1]).
+-export_type([t/0]).
+-opaque t() :: {prov, list()}.
+-spec new() -> t().
+-spec peek(t()) -> any().
+new() -> {prov, []}.
+peek({prov, L}) -> element(2, L).
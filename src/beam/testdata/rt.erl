@@ -0,0 +1,13 @@
+-module(rt).
+-export([size/1, insert/2]).
+
+-type tree() :: leaf | {node, integer(), tree(), tree()}.
+-type bad() :: bad().
+
+-spec size(tree()) -> non_neg_integer().
+size(leaf) -> 0;
+size({node, _, L, R}) -> size(L) + size(R) + 1.
+
+-spec insert(integer(), tree()) -> tree().
+insert(X, leaf) -> {node, X, leaf, leaf};
+insert(X, {node, Y, L, R}) -> {node, Y, insert(X, L), R}.
@@ -0,0 +1,249 @@
+//! Signatures of built-in functions and operators.
+//!
+//! The `erlang` module is usually not analyzable (its functions are implemented natively),
+//! so the signatures of commonly used BIFs are defined here.
+use ast;
+use lattice;
+use module::Remote;
+use resolve;
+use ty;
+use ty::Type;
+
+/// Returns the signature of the BIF `fun`.
+///
+/// Operators are regarded as functions of the `erlang` module (e.g., `erlang:'+'/2`);
+/// `andalso` and `orelse` are included for convenience.
+pub fn signature(fun: &Remote) -> Option<ty::FunSpec> {
+    if fun.module != "erlang" {
+        return None;
+    }
+    let (args, ret): (&[&str], &str) = match (fun.name.as_str(), fun.arity) {
+        ("is_atom", 1) |
+        ("is_binary", 1) |
+        ("is_bitstring", 1) |
+        ("is_boolean", 1) |
+        ("is_float", 1) |
+        ("is_function", 1) |
+        ("is_integer", 1) |
+        ("is_list", 1) |
+        ("is_map", 1) |
+        ("is_number", 1) |
+        ("is_pid", 1) |
+        ("is_port", 1) |
+        ("is_reference", 1) |
+        ("is_tuple", 1) => (&["any"], "boolean"),
+        ("is_function", 2) => (&["any", "arity"], "boolean"),
+        ("is_record", 2) => (&["any", "atom"], "boolean"),
+        ("is_record", 3) => (&["any", "atom", "non_neg_integer"], "boolean"),
+        ("==", 2) | ("/=", 2) | ("=:=", 2) | ("=/=", 2) | ("<", 2) | (">", 2) | ("=<", 2) |
+        (">=", 2) => (&["any", "any"], "boolean"),
+        ("+", 2) | ("-", 2) | ("*", 2) => (&["number", "number"], "number"),
+        ("/", 2) => (&["number", "number"], "float"),
+        ("div", 2) | ("rem", 2) | ("band", 2) | ("bor", 2) | ("bxor", 2) | ("bsl", 2) |
+        ("bsr", 2) => (&["integer", "integer"], "integer"),
+        ("+", 1) | ("-", 1) | ("abs", 1) => (&["number"], "number"),
+        ("bnot", 1) => (&["integer"], "integer"),
+        ("not", 1) => (&["boolean"], "boolean"),
+        ("and", 2) | ("or", 2) | ("xor", 2) => (&["boolean", "boolean"], "boolean"),
+        ("andalso", 2) | ("orelse", 2) => (&["boolean", "any"], "any"),
+        ("++", 2) => (&["list", "any"], "any"),
+        ("--", 2) => (&["list", "list"], "list"),
+        ("!", 2) | ("send", 2) => (&["pid|port|atom|tuple", "any"], "any"),
+        ("length", 1) => (&["list"], "non_neg_integer"),
+        ("hd", 1) | ("tl", 1) => (&["nonempty_maybe_improper_list"], "any"),
+        ("element", 2) => (&["pos_integer", "tuple"], "any"),
+        ("setelement", 3) => (&["pos_integer", "tuple", "any"], "tuple"),
+        ("tuple_size", 1) => (&["tuple"], "non_neg_integer"),
+        ("byte_size", 1) | ("bit_size", 1) => (&["bitstring"], "non_neg_integer"),
+        ("map_size", 1) => (&["map"], "non_neg_integer"),
+        ("float", 1) => (&["number"], "float"),
+        ("round", 1) | ("trunc", 1) | ("ceil", 1) | ("floor", 1) => (&["number"], "integer"),
+        ("max", 2) | ("min", 2) => (&["any", "any"], "any"),
+        ("self", 0) => (&[], "pid"),
+        ("node", 0) => (&[], "node"),
+        ("make_ref", 0) => (&[], "reference"),
+        ("atom_to_list", 1) => (&["atom"], "string"),
+        ("list_to_atom", 1) => (&["string"], "atom"),
+        ("atom_to_binary", 2) => (&["atom", "atom"], "binary"),
+        ("binary_to_atom", 2) => (&["binary", "atom"], "atom"),
+        ("integer_to_list", 1) => (&["integer"], "string"),
+        ("list_to_integer", 1) => (&["string"], "integer"),
+        ("integer_to_binary", 1) => (&["integer"], "binary"),
+        ("binary_to_integer", 1) => (&["binary"], "integer"),
+        ("binary_to_list", 1) => (&["binary"], "list"),
+        ("list_to_binary", 1) | ("iolist_to_binary", 1) => (&["iodata"], "binary"),
+        ("tuple_to_list", 1) => (&["tuple"], "list"),
+        ("list_to_tuple", 1) => (&["list"], "tuple"),
+        ("spawn", 1) | ("spawn_link", 1) => (&["function"], "pid"),
+        ("spawn", 3) | ("spawn_link", 3) => (&["module", "atom", "list"], "pid"),
+        ("register", 2) => (&["atom", "pid|port"], "'true'"),
+        ("whereis", 1) => (&["atom"], "pid|port|'undefined'"),
+        ("link", 1) | ("unlink", 1) => (&["pid|port"], "'true'"),
+        ("is_process_alive", 1) => (&["pid"], "boolean"),
+        ("apply", 2) => (&["function|tuple", "list"], "any"),
+        ("apply", 3) => (&["module|tuple", "atom", "list"], "any"),
+        ("throw", 1) | ("exit", 1) | ("error", 1) => (&["any"], "none"),
+        ("error", 2) => (&["any", "any"], "none"),
+        _ => return None,
+    };
+    Some(ty::FunSpec {
+        args: Some(args.iter().map(|a| parse_union(a)).collect()),
+        return_type: parse_union(ret),
+    })
+}
+
+/// Returns the result type of applying the BIF `fun` to `args`
+/// if it can be more precise than the return type of the signature.
+pub fn apply(fun: &Remote, args: &[&Type]) -> Option<Type> {
+    if fun.module != "erlang" {
+        return None;
+    }
+    let integer = || From::from(ty::integer());
+    let float = || From::from(ty::FloatType);
+    let parts = args.iter().map(|a| lattice::numeric_parts(a)).collect::<Vec<_>>();
+    let ranges = parts.iter().map(|p| p.0.as_ref()).collect::<Option<Vec<_>>>();
+    let true_ = ty::atom("true");
+    let false_ = ty::atom("false");
+    match (fun.name.as_str(), args.len()) {
+        ("+", 2) | ("-", 2) | ("*", 2) | ("+", 1) | ("-", 1) | ("abs", 1) => {
+            // NOTE: The result is an integer if all the operands are, and a float otherwise
+            let mut result = Vec::new();
+            if let Some(ref ranges) = ranges {
+                result.push(integer_op(&fun.name, ranges).unwrap_or_else(integer));
+            }
+            let numeric = parts.iter().all(|p| p.0.is_some() || p.1);
+            if numeric && parts.iter().any(|p| p.1) {
+                result.push(float());
+            }
+            Some(lattice::join(&result))
+        }
+        ("div", 2) | ("rem", 2) | ("band", 2) | ("bor", 2) | ("bxor", 2) | ("bsl", 2) |
+        ("bsr", 2) => {
+            let result = ranges.map(|r| integer_op(&fun.name, &r).unwrap_or_else(integer));
+            Some(result.unwrap_or_else(lattice::none))
+        }
+        ("round", 1) | ("trunc", 1) | ("ceil", 1) | ("floor", 1) => {
+            // NOTE: Integers are returned as they are
+            let (ref range, float) = parts[0];
+            let mut result = range.iter().cloned().map(From::from).collect::<Vec<_>>();
+            if float {
+                result.push(integer());
+            }
+            Some(lattice::join(&result))
+        }
+        ("=:=", 2) | ("=/=", 2) | ("==", 2) | ("/=", 2) => {
+            // NOTE: `==` and `/=` compare integers and floats by their values (`1 == 1.0`)
+            let exact = fun.name == "=:=" || fun.name == "=/=";
+            let (x, y) = (args[0], args[1]);
+            let coerced = (parts[0].0.is_some() && parts[1].1) ||
+                          (parts[0].1 && parts[1].0.is_some());
+            let may_equal = !lattice::is_none(&lattice::inf(x, y)) || (!exact && coerced);
+            let must_equal = is_singleton(x) && x == y;
+            let equal = fun.name == "=:=" || fun.name == "==";
+            Some(if must_equal {
+                if equal { true_ } else { false_ }
+            } else if !may_equal {
+                if equal { false_ } else { true_ }
+            } else {
+                lattice::sup(&true_, &false_)
+            })
+        }
+        ("andalso", 2) | ("orelse", 2) => {
+            // NOTE: The right operand is evaluated only if the left one does not decide
+            let (decided, undecided) = if fun.name == "andalso" {
+                (false_, true_)
+            } else {
+                (true_, false_)
+            };
+            let mut result = Vec::new();
+            if !lattice::is_none(&lattice::inf(args[0], &decided)) {
+                result.push(decided);
+            }
+            if !lattice::is_none(&lattice::inf(args[0], &undecided)) {
+                result.push(args[1].clone());
+            }
+            Some(lattice::join(&result))
+        }
+        ("!", 2) | ("send", 2) => Some(args[1].clone()),
+        ("++", 2) if lattice::is_subtype(args[0], &From::from(ty::NilType)) => {
+            Some(args[1].clone())
+        }
+        ("hd", 1) => Some(lattice::uncons(args[0]).0),
+        ("tl", 1) => Some(lattice::uncons(args[0]).1),
+        _ => None,
+    }
+}
+
+// Range arithmetic of the integer operators (see `ty::IntegerType::add` and so on)
+fn integer_op(operator: &str, args: &[&ty::IntegerType]) -> Option<Type> {
+    let (x, y) = match *args {
+        [x] if operator == "-" => return Some(From::from(x.neg())),
+        [x] if operator == "+" => return Some(From::from(x.clone())),
+        [x] if operator == "abs" => return Some(From::from(x.abs())),
+        [x, y] => (x, y),
+        _ => return None,
+    };
+    // NOTE: Operations on single values are evaluated exactly (if possible)
+    if let (Some(a), Some(b)) = (x.get_single_value(), y.get_single_value()) {
+        if let Some(v) = ast::integer_binary_op(operator, a, b) {
+            return Some(From::from(ty::integer().value(v)));
+        }
+    }
+    let result = match operator {
+        "+" => Some(x.add(y)),
+        "-" => Some(x.sub(y)),
+        "*" => Some(x.mul(y)),
+        "div" => x.div(y),
+        "rem" => x.rem(y),
+        "band" => Some(x.band(y)),
+        "bor" => Some(x.bor(y)),
+        "bsl" => Some(x.bsl(y)),
+        "bsr" => Some(x.bsr(y)),
+        _ => return None,
+    };
+    Some(result.map_or_else(lattice::none, From::from))
+}
+
+// Returns `true` if `t` has only one value
+fn is_singleton(t: &Type) -> bool {
+    match *t {
+        Type::Integer(ref x) => x.get_single_value().is_some(),
+        Type::Atom(ref x) => x.value.is_some(),
+        Type::Nil(_) => true,
+        _ => false,
+    }
+}
+
+/// Returns the type of the values for which the guard BIF `name/1` (e.g., `is_atom`) holds.
+pub fn type_test(name: &str) -> Option<Type> {
+    let t = match name {
+        "is_atom" => "atom",
+        "is_binary" => "binary",
+        "is_bitstring" => "bitstring",
+        "is_boolean" => "boolean",
+        "is_float" => "float",
+        "is_function" => "function",
+        "is_integer" => "integer",
+        "is_list" => "maybe_improper_list",
+        "is_map" => "map",
+        "is_number" => "number",
+        "is_pid" => "pid",
+        "is_port" => "port",
+        "is_reference" => "reference",
+        "is_tuple" => "tuple",
+        _ => return None,
+    };
+    Some(parse_union(t))
+}
+
+// NOTE: `s` is a `|`-separated list of built-in type names and quoted atoms
+fn parse_union(s: &str) -> Type {
+    let types = s.split('|')
+        .map(|t| if t.starts_with('\'') {
+            ty::atom(t.trim_matches('\''))
+        } else {
+            resolve::builtin_type(t, &[]).unwrap_or_else(|| panic!("Unknown type: {}", t))
+        })
+        .collect::<Vec<_>>();
+    lattice::join(&types)
+}
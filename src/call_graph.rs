@@ -0,0 +1,96 @@
+//! Function-level call graph.
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use env::Env;
+use graph::Content;
+use graph::Graph;
+use graph::NodeId;
+use module::Arity;
+use module::Local;
+use module::Module;
+use module::Remote;
+use scc;
+use ty::Type;
+
+#[derive(Debug)]
+pub struct CallGraph {
+    /// Function => the functions it calls (including undefined and external ones)
+    pub callees: BTreeMap<Remote, BTreeSet<Remote>>,
+}
+impl CallGraph {
+    pub fn new(env: &Env) -> Self {
+        let mut callees = BTreeMap::new();
+        for m in env.modules.values() {
+            for (f, fun) in &m.functions {
+                let caller = Remote::new(&m.name, &f.name, f.arity);
+                callees.insert(caller, collect_callees(m, &fun.graph));
+            }
+        }
+        CallGraph { callees }
+    }
+
+    /// Returns the strongly connected components in the analysis order (callees first).
+    ///
+    /// The functions which are not defined in the loaded modules are excluded.
+    pub fn sccs(&self) -> Vec<Vec<Remote>> {
+        scc::strongly_connected_components(&self.callees)
+            .into_iter()
+            .map(|c| c.into_iter().filter(|f| self.callees.contains_key(f)).collect::<Vec<_>>())
+            .filter(|c| !c.is_empty())
+            .collect()
+    }
+}
+
+/// Returns the functions statically called from `graph` of a function in `module`.
+pub fn collect_callees(module: &Module, graph: &Graph) -> BTreeSet<Remote> {
+    graph.nodes
+        .values()
+        .filter_map(|n| resolve_callee(module, graph, &n.content))
+        .collect()
+}
+
+/// Returns the function called by the call node `content`.
+///
+/// `None` is returned if the node is not a call, the callee is not statically known
+/// or the call is a pseudo call (e.g., `__tuple`) made by `meta::GraphBuilder`.
+pub fn resolve_callee(module: &Module, graph: &Graph, content: &Content) -> Option<Remote> {
+    match *content {
+        Content::LocalCall(ref x) if !x.is_pattern => {
+            let name = literal_atom(graph, x.fun)?;
+            if is_pseudo_call(&name) {
+                return None;
+            }
+            let local = Local::new(&name, x.args.len() as Arity);
+            if module.functions.contains_key(&local) {
+                return Some(Remote::new(&module.name, &name, local.arity));
+            }
+            let import = module.imports.iter().find(|i| i.to_local() == local);
+            // NOTE: Calls to undefined local functions are auto-imported BIFs
+            Some(import.cloned().unwrap_or_else(|| Remote::new("erlang", &name, local.arity)))
+        }
+        Content::RemoteCall(ref x) => {
+            let module = literal_atom(graph, x.module)?;
+            let name = literal_atom(graph, x.fun)?;
+            Some(Remote::new(&module, &name, x.args.len() as Arity))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the name if `node` is an atom literal.
+pub fn literal_atom(graph: &Graph, node: NodeId) -> Option<String> {
+    match graph.nodes[&node].content {
+        Content::Val(ref v) => {
+            match *v.producible_type.get() {
+                Type::Atom(ref a) => a.value.clone(),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns `true` if `name` is the name of a pseudo function (e.g., `__tuple`).
+pub fn is_pseudo_call(name: &str) -> bool {
+    name.starts_with("__")
+}
@@ -0,0 +1,185 @@
+//! Discovery of `.beam` files in Erlang code paths.
+//!
+//! See: [Code Path](http://erlang.org/doc/man/code.html#code-path)
+use std::fs;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use config;
+use module::Result;
+use term;
+
+/// The sources of the modules to load (see `Env::load_code_path`).
+#[derive(Debug, Clone, Default)]
+pub struct CodePath {
+    /// Directories searched recursively for `ebin/*.beam` files
+    pub dirs: Vec<PathBuf>,
+
+    /// Application resource files whose modules are loaded like the given beam files
+    pub apps: Vec<PathBuf>,
+
+    /// Colon-separated list of library directories (see `find_beams_in_erl_libs`)
+    pub erl_libs: Option<String>,
+
+    /// OTP library root directory (e.g., `/usr/lib/erlang/lib`)
+    pub otp_root: Option<PathBuf>,
+
+    /// Module name globs to load (all modules if empty) and not to load
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+
+    /// Only the modules which the roots depend on are loaded (up to `depth` hops)
+    pub lazy: bool,
+    pub depth: Option<usize>,
+}
+impl CodePath {
+    /// Returns the beam files of `dirs`, `erl_libs` and `otp_root`.
+    ///
+    /// Earlier entries shadow later ones, so OTP modules come last.
+    pub fn search_path(&self) -> Result<Vec<PathBuf>> {
+        let mut beams = Vec::new();
+        for dir in &self.dirs {
+            beams.extend(find_beams_in_dir(dir)?);
+        }
+        if let Some(ref erl_libs) = self.erl_libs {
+            beams.extend(find_beams_in_erl_libs(erl_libs)?);
+        }
+        if let Some(ref otp_root) = self.otp_root {
+            beams.extend(find_beams_in_lib_dir(otp_root)?);
+        }
+        Ok(beams.into_iter().filter(|b| self.is_selected(b)).collect())
+    }
+
+    /// Returns `true` if the module of `beam_file` is selected by `include` and `exclude`.
+    pub fn is_selected(&self, beam_file: &Path) -> bool {
+        let name = beam_file.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        config::is_module_selected(&self.include, &self.exclude, name)
+    }
+}
+
+/// Recursively finds `*.beam` files located in `ebin` directories under `dir`.
+///
+/// If `dir` itself is an `ebin` directory, the beams in it are also returned.
+pub fn find_beams_in_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
+    let mut beams = Vec::new();
+    let mut visited = HashSet::new();
+    walk_dir(dir.as_ref(), &mut visited, &mut beams)?;
+    Ok(beams)
+}
+
+/// Finds `*.beam` files in the library directories listed in `erl_libs`.
+///
+/// `erl_libs` is a colon-separated list like the `ERL_LIBS` environment variable.
+pub fn find_beams_in_erl_libs(erl_libs: &str) -> Result<Vec<PathBuf>> {
+    let mut beams = Vec::new();
+    for lib_dir in erl_libs.split(':').filter(|d| !d.is_empty()) {
+        beams.extend(find_beams_in_lib_dir(lib_dir)?);
+    }
+    Ok(beams)
+}
+
+/// Finds `*.beam` files in `lib_dir/APP[-VSN]/ebin/` directories.
+///
+/// An OTP root (e.g., `/usr/lib/erlang/lib`) is a library directory.
+/// If there are multiple versions of an application, only the latest one is used.
+pub fn find_beams_in_lib_dir<P: AsRef<Path>>(lib_dir: P) -> Result<Vec<PathBuf>> {
+    let mut apps: HashMap<String, (Vec<u64>, PathBuf)> = HashMap::new();
+    for entry in read_dir_sorted(lib_dir.as_ref())? {
+        let ebin = entry.join("ebin");
+        if !ebin.is_dir() {
+            continue;
+        }
+        let dir_name = entry.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let (app, vsn) = split_app_vsn(&dir_name);
+        let newer = apps.get(app).is_none_or(|(v, _)| vsn > *v);
+        if newer {
+            apps.insert(app.to_string(), (vsn, ebin));
+        }
+    }
+    let mut ebins = apps.into_iter().map(|(_, (_, ebin))| ebin).collect::<Vec<_>>();
+    ebins.sort();
+
+    let mut beams = Vec::new();
+    for ebin in ebins {
+        beams.extend(find_beams_in_ebin(&ebin)?);
+    }
+    Ok(beams)
+}
+
+/// Finds the `*.beam` files of the modules listed in an application resource file.
+///
+/// The beams are looked up in the directory containing `app_file`.
+///
+/// See: [Application Resource File](http://erlang.org/doc/man/app.html)
+pub fn find_beams_in_app_file<P: AsRef<Path>>(app_file: P) -> Result<Vec<PathBuf>> {
+    let app_file = app_file.as_ref();
+    let terms = term::consult(app_file)?;
+    let props = terms.first()
+        .and_then(|t| t.as_tuple())
+        .filter(|t| t.len() == 3 && t[0].as_atom() == Some("application"))
+        .and_then(|t| t[2].as_list())
+        .ok_or_else(|| format!("Not an application resource file: {}", app_file.display()))?;
+    let modules = props.iter()
+        .filter_map(|p| p.as_tuple())
+        .find(|p| p.len() == 2 && p[0].as_atom() == Some("modules"))
+        .and_then(|p| p[1].as_list())
+        .unwrap_or(&[]);
+
+    let dir = app_file.parent().unwrap_or_else(|| Path::new("."));
+    let mut beams = Vec::with_capacity(modules.len());
+    for m in modules {
+        let name = m.as_atom().ok_or_else(|| format!("Invalid module name: {:?}", m))?;
+        let beam = dir.join(format!("{}.beam", name));
+        if !beam.is_file() {
+            return Err(format!("No such beam file: {}", beam.display()).into());
+        }
+        beams.push(beam);
+    }
+    Ok(beams)
+}
+
+fn find_beams_in_ebin(ebin: &Path) -> Result<Vec<PathBuf>> {
+    Ok(read_dir_sorted(ebin)?
+        .into_iter()
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "beam"))
+        .collect())
+}
+
+fn walk_dir(dir: &Path, visited: &mut HashSet<PathBuf>, beams: &mut Vec<PathBuf>) -> Result<()> {
+    // NOTE: Canonicalizing prevents infinite loops caused by symbolic links
+    if !visited.insert(fs::canonicalize(dir)?) {
+        return Ok(());
+    }
+    if dir.file_name().is_some_and(|n| n == "ebin") {
+        beams.extend(find_beams_in_ebin(dir)?);
+    }
+    for entry in read_dir_sorted(dir)? {
+        if entry.is_dir() {
+            walk_dir(&entry, visited, beams)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_dir_sorted(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        entries.push(entry?.path());
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+// e.g., "stdlib-2.8" => ("stdlib", [2, 8])
+fn split_app_vsn(dir_name: &str) -> (&str, Vec<u64>) {
+    if let Some(i) = dir_name.rfind('-') {
+        let vsn = dir_name[i + 1..].split('.').map(|n| n.parse()).collect();
+        if let Ok(vsn) = vsn {
+            return (&dir_name[..i], vsn);
+        }
+    }
+    (dir_name, Vec::new())
+}
@@ -0,0 +1,152 @@
+//! Project configuration file (`diagnoser.config`).
+//!
+//! The file consists of dot-terminated `{Key, Value}` terms (like `rebar.config`):
+//!
+//! ```erlang
+//! {dirs, ["_build/default/lib"]}.          % see `--dir`
+//! {apps, ["ebin/my_app.app"]}.             % see `--app`
+//! {erl_libs, "deps:/opt/lib"}.             % see `--erl-libs`
+//! {otp_root, "/usr/lib/erlang/lib"}.       % see `--otp-root`
+//! {include, ["my_*"]}.                     % module name globs (`*` and `?`)
+//! {exclude, ["*_SUITE", "*_tests"]}.
+//! {enable, [all]}.                         % diagnostic IDs (see `diagnostic::Kind::id`)
+//! {disable, ['no-return']}.
+//! {atom_set_limit, 20}.                    % see `lattice::set_atom_set_limit`
+//! {plt, "_build/diagnoser.plt"}.
+//! {format, sarif}.
+//! ```
+//!
+//! Every key is optional. Relative paths are relative to the directory of the file.
+//! Strings may be written as strings, binaries or atoms.
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use module::Result;
+use term;
+use term::Term;
+
+pub const FILE_NAME: &str = "diagnoser.config";
+
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    pub dirs: Vec<PathBuf>,
+    pub apps: Vec<PathBuf>,
+    pub erl_libs: Option<String>,
+    pub otp_root: Option<PathBuf>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub enable: Vec<String>,
+    pub disable: Vec<String>,
+    pub atom_set_limit: Option<usize>,
+    pub plt: Option<PathBuf>,
+    pub format: Option<String>,
+}
+impl Config {
+    pub fn new() -> Self {
+        Config::default()
+    }
+
+    /// Finds `diagnoser.config` in `dir` or its ancestors.
+    pub fn find<P: AsRef<Path>>(dir: P) -> Option<PathBuf> {
+        dir.as_ref().ancestors().map(|d| d.join(FILE_NAME)).find(|f| f.is_file())
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+        term::parse_terms(&text)
+            .and_then(|terms| Config::from_terms(&terms, base))
+            .map_err(|e| format!("Invalid config: {}: {}", path.display(), e).into())
+    }
+
+    /// Makes a configuration from `{Key, Value}` terms (relative paths are joined to `base`).
+    pub fn from_terms(terms: &[Term], base: &Path) -> Result<Self> {
+        let mut config = Config::new();
+        for t in terms {
+            let (key, value) = match t.as_tuple() {
+                Some([Term::Atom(key), value]) => (key.as_str(), value),
+                _ => return Err(format!("Expected `{{Key, Value}}`, but got {:?}", t).into()),
+            };
+            match key {
+                "dirs" => config.dirs = strings(value)?.iter().map(|d| base.join(d)).collect(),
+                "apps" => config.apps = strings(value)?.iter().map(|a| base.join(a)).collect(),
+                "erl_libs" => {
+                    let dirs = string(value)?
+                        .split(':')
+                        .filter(|d| !d.is_empty())
+                        .map(|d| base.join(d).to_string_lossy().into_owned())
+                        .collect::<Vec<_>>();
+                    config.erl_libs = Some(dirs.join(":"));
+                }
+                "otp_root" => config.otp_root = Some(base.join(string(value)?)),
+                "include" => config.include = strings(value)?,
+                "exclude" => config.exclude = strings(value)?,
+                "enable" => config.enable = strings(value)?,
+                "disable" => config.disable = strings(value)?,
+                "atom_set_limit" => {
+                    config.atom_set_limit = match *value {
+                        Term::Integer(n) if n >= 0 => Some(n as usize),
+                        _ => return Err(format!("Invalid atom_set_limit: {:?}", value).into()),
+                    }
+                }
+                "plt" => config.plt = Some(base.join(string(value)?)),
+                "format" => config.format = Some(string(value)?),
+                _ => return Err(format!("Unknown key: {}", key).into()),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Returns `true` if `module` matches one of `include` (if any) and none of `exclude`.
+pub fn is_module_selected(include: &[String], exclude: &[String], module: &str) -> bool {
+    (include.is_empty() || include.iter().any(|p| matches_glob(p, module))) &&
+    !exclude.iter().any(|p| matches_glob(p, module))
+}
+
+/// Matches `name` against `pattern` where `*` matches any string and `?` matches any character.
+pub fn matches_glob(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None; // The position of the last `*` and the position of `name` then
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => {
+                match backtrack {
+                    Some((star, matched)) => {
+                        // NOTE: Let the `*` consume one more character
+                        p = star + 1;
+                        n = matched + 1;
+                        backtrack = Some((star, matched + 1));
+                    }
+                    None => return false,
+                }
+            }
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn string(value: &Term) -> Result<String> {
+    match *value {
+        Term::String(ref x) | Term::Binary(ref x) | Term::Atom(ref x) => Ok(x.clone()),
+        _ => Err(format!("Expected a string, but got {:?}", value).into()),
+    }
+}
+
+fn strings(value: &Term) -> Result<Vec<String>> {
+    match *value {
+        Term::List(ref x) => x.iter().map(string).collect(),
+        _ => Err(format!("Expected a list, but got {:?}", value).into()),
+    }
+}
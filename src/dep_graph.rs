@@ -0,0 +1,88 @@
+//! Inter-module dependency graph.
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io;
+use std::io::Write;
+use env::Env;
+use json::Json;
+use scc;
+
+#[derive(Debug)]
+pub struct ModuleGraph {
+    /// Module name => the modules it calls (including external ones)
+    pub callees: BTreeMap<String, BTreeSet<String>>,
+    /// Module name => the loaded modules which call it
+    pub callers: BTreeMap<String, BTreeSet<String>>,
+    pub externals: BTreeSet<String>,
+}
+impl ModuleGraph {
+    pub fn new(env: &Env) -> Self {
+        let mut callees = BTreeMap::new();
+        let mut callers = BTreeMap::new();
+        for m in env.modules.values() {
+            callees.insert(m.name.clone(), m.dependent_modules.iter().cloned().collect());
+            callers.insert(m.name.clone(), m.client_modules.iter().cloned().collect());
+        }
+        let externals: BTreeSet<String> =
+            env.external_modules().into_iter().map(|m| m.to_string()).collect();
+        for m in &externals {
+            callees.insert(m.clone(), BTreeSet::new());
+            callers.insert(m.clone(), env.client_modules(m).cloned().collect());
+        }
+        ModuleGraph {
+            callees,
+            callers,
+            externals,
+        }
+    }
+
+    /// Returns the strongly connected components in the analysis order (callees first).
+    pub fn sccs(&self) -> Vec<Vec<String>> {
+        scc::strongly_connected_components(&self.callees)
+    }
+
+    /// Returns the components consisting of two or more mutually dependent modules.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        self.sccs().into_iter().filter(|c| c.len() > 1).collect()
+    }
+
+    pub fn write_as_dot<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let in_cycle = self.cycles().into_iter().flatten().collect::<BTreeSet<_>>();
+        writeln!(w, "digraph deps {{")?;
+        for m in self.callees.keys() {
+            let mut attrs = format!("label={:?}", m);
+            if self.externals.contains(m) {
+                attrs.push_str(",style=dashed");
+            }
+            if in_cycle.contains(m) {
+                attrs.push_str(",color=red");
+            }
+            writeln!(w, "{:?} [{}];", m, attrs)?;
+        }
+        for (from, tos) in &self.callees {
+            for to in tos {
+                if in_cycle.contains(from) && in_cycle.contains(to) {
+                    writeln!(w, "{:?} -> {:?} [color=red];", from, to)?;
+                } else {
+                    writeln!(w, "{:?} -> {:?};", from, to)?;
+                }
+            }
+        }
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Json {
+        let modules = self.callees
+            .iter()
+            .map(|(m, callees)| {
+                Json::object(vec![("name", Json::string(m)),
+                                  ("external", Json::Bool(self.externals.contains(m))),
+                                  ("callees", Json::strings(callees)),
+                                  ("callers", Json::strings(&self.callers[m]))])
+            })
+            .collect();
+        let sccs = self.sccs().into_iter().map(Json::strings).collect();
+        Json::object(vec![("modules", Json::Array(modules)), ("sccs", Json::Array(sccs))])
+    }
+}
@@ -0,0 +1,667 @@
+//! Findings of the analysis (e.g., functions which never return).
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use env::Env;
+use graph::Content;
+use graph::EdgeKind;
+use graph::Graph;
+use graph::NodeId;
+use intern::TypeId;
+use lattice;
+use module::Arity;
+use module::Module;
+use module::Remote;
+use module::Result;
+use module::Spec;
+use plt;
+use pretty::Aliases;
+use resolve::OpaqueType;
+use resolve::Resolver;
+use solver;
+use solver::Solver;
+use ty;
+use ty::FunSpec;
+use ty::Type;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    /// The function never returns normally
+    NoReturn,
+
+    /// A function clause can never match
+    UnmatchedClause,
+
+    /// A call can never succeed because its arguments do not match the callee
+    CallFails,
+
+    /// The inferred return type has nothing in common with the spec
+    SpecMismatch,
+
+    /// An opaque type is looked into outside of the defining module
+    OpaqueViolation,
+
+    /// A recursive type never becomes productive
+    MalformedType,
+}
+impl Kind {
+    pub fn all() -> &'static [Kind] {
+        &[Kind::NoReturn,
+          Kind::UnmatchedClause,
+          Kind::CallFails,
+          Kind::SpecMismatch,
+          Kind::OpaqueViolation,
+          Kind::MalformedType]
+    }
+    /// Returns the stable identifier of the kind (e.g., `no-return`).
+    pub fn id(&self) -> &'static str {
+        match *self {
+            Kind::NoReturn => "no-return",
+            Kind::UnmatchedClause => "unmatched-clause",
+            Kind::CallFails => "call-fails",
+            Kind::SpecMismatch => "spec-mismatch",
+            Kind::OpaqueViolation => "opaque-violation",
+            Kind::MalformedType => "malformed-type",
+        }
+    }
+    pub fn from_id(id: &str) -> Option<Kind> {
+        Kind::all().iter().find(|k| k.id() == id).cloned()
+    }
+
+    /// Returns the option of `-dialyzer(...)` attributes which suppresses the kind.
+    pub fn dialyzer_option(&self) -> &'static str {
+        match *self {
+            Kind::NoReturn => "no_return",
+            Kind::UnmatchedClause => "no_match",
+            Kind::CallFails => "no_fail_call",
+            Kind::SpecMismatch => "no_contracts",
+            Kind::OpaqueViolation => "no_opaque",
+            Kind::MalformedType => "no_contracts",
+        }
+    }
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Kind::NoReturn => "The function never returns normally",
+            Kind::UnmatchedClause => "A function clause can never match",
+            Kind::CallFails => "A call can never succeed",
+            Kind::SpecMismatch => "The inferred return type has nothing in common with the spec",
+            Kind::OpaqueViolation => "An opaque type is looked into outside of its module",
+            Kind::MalformedType => "A recursive type never becomes productive",
+        }
+    }
+    pub fn severity(&self) -> Severity {
+        match *self {
+            Kind::CallFails | Kind::SpecMismatch | Kind::MalformedType => Severity::Error,
+            Kind::NoReturn | Kind::UnmatchedClause | Kind::OpaqueViolation => Severity::Warning,
+        }
+    }
+}
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+/// Returns the enabled kinds.
+///
+/// `enable` and `disable` are lists of kind identifiers (or `all`).
+/// `all` is applied before the identifiers, and `disable` takes precedence over `enable`
+/// (e.g., `--disable all --enable call-fails` enables only `call-fails`).
+/// All the kinds are enabled by default.
+pub fn select_kinds(enable: &[String], disable: &[String]) -> Result<BTreeSet<Kind>> {
+    let mut kinds = Kind::all().iter().cloned().collect::<BTreeSet<_>>();
+    if disable.iter().any(|k| k == "all") {
+        kinds.clear();
+    }
+    if enable.iter().any(|k| k == "all") {
+        kinds.extend(Kind::all());
+    }
+    for id in enable.iter().filter(|k| *k != "all") {
+        kinds.insert(Kind::from_id(id).ok_or_else(|| format!("Unknown diagnostic: {}", id))?);
+    }
+    for id in disable.iter().filter(|k| *k != "all") {
+        kinds.remove(&Kind::from_id(id).ok_or_else(|| format!("Unknown diagnostic: {}", id))?);
+    }
+    Ok(kinds)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub file: String, // The source file (or the beam file if it is unknown)
+    pub line: i32,
+}
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub kind: Kind,
+    pub severity: Severity,
+    pub function: Remote,
+    pub location: Location,
+    pub message: String,
+
+    /// The types involved in the finding (e.g., `("argument 1", integer())`)
+    pub types: Vec<(String, Type)>,
+}
+impl Diagnostic {
+    /// Returns a hash which identifies the finding regardless of its line number
+    /// (see `baseline::Baseline`).
+    pub fn fingerprint(&self) -> u64 {
+        let f = &self.function;
+        let mut text =
+            format!("{}\n{}:{}/{}\n{}", self.kind, f.module, f.name, f.arity, self.message);
+        for (role, t) in &self.types {
+            text.push_str(&format!("\n{}: {}", role, t));
+        }
+        plt::hash_bytes(text.as_bytes())
+    }
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{}: {}: {} [{}]",
+               self.location,
+               self.severity,
+               self.message,
+               self.kind)
+    }
+}
+
+/// Checks the loaded modules with the inferred `signatures` (see `Solver::solve`).
+///
+/// Findings suppressed by `-dialyzer(...)` attributes are excluded.
+/// The result is sorted by location.
+pub fn check(env: &Env, signatures: &BTreeMap<Remote, FunSpec>) -> Vec<Diagnostic> {
+    let modules = env.modules.keys().cloned().collect();
+    check_modules(env, signatures, &modules)
+}
+
+/// Checks the functions of `modules` (see `check`).
+pub fn check_modules(env: &Env,
+                     signatures: &BTreeMap<Remote, FunSpec>,
+                     modules: &BTreeSet<String>)
+                     -> Vec<Diagnostic> {
+    let solver = Solver::with_signatures(env, signatures.clone());
+    let aliases = Aliases::new(env);
+    let mut diagnostics = Vec::new();
+    let signatures = signatures.iter().filter(|(f, _)| modules.contains(&f.module));
+    for (f, signature) in signatures {
+        let module = &env.modules[&f.module];
+        let graph = &module.functions[&f.to_local()].graph;
+        let types = solver.node_types(f).unwrap_or_default();
+        let checker = Checker {
+            env,
+            solver: &solver,
+            module,
+            graph,
+            fun: f,
+            types,
+            aliases: &aliases,
+            diagnostics: &mut diagnostics,
+        };
+        checker.check(signature);
+    }
+    for module in modules.iter().filter_map(|m| env.modules.get(m)) {
+        check_types(env, module, &mut diagnostics);
+    }
+    diagnostics.sort_by(|a, b| {
+        (&a.location, &a.function, a.kind).cmp(&(&b.location, &b.function, b.kind))
+    });
+    diagnostics
+}
+
+/// Reports the malformed types declared in `module` (once for each declaration).
+fn check_types(env: &Env, module: &Module, diagnostics: &mut Vec<Diagnostic>) {
+    if module.dialyzer_options.module.contains(Kind::MalformedType.dialyzer_option()) {
+        return;
+    }
+    let resolver = Resolver::new(env, &module.name);
+    for t in module.types.keys() {
+        resolver.resolve(&ty::local(&t.name, &vec![lattice::any(); t.arity as usize]));
+    }
+    let file = source_file(module);
+    for (_, t) in resolver.malformed_types().into_iter().filter(|(m, _)| *m == module.name) {
+        let line = module.type_lines.get(&t).cloned().unwrap_or(0);
+        diagnostics.push(Diagnostic {
+            kind: Kind::MalformedType,
+            severity: Kind::MalformedType.severity(),
+            // NOTE: A finding on a type is attributed to the type instead of a function
+            function: Remote::new(&module.name, &t.name, t.arity),
+            location: Location { file: file.clone(), line },
+            message: format!("The type {}/{} never becomes productive", t.name, t.arity),
+            types: Vec::new(),
+        });
+    }
+}
+
+// NOTE: The beam file is used if the source file is unknown
+fn source_file(module: &Module) -> String {
+    module.source_file
+        .clone()
+        .or_else(|| module.beam_file.as_ref().map(|f| f.display().to_string()))
+        .unwrap_or_default()
+}
+
+/// The functions which look into the structure of their arguments
+/// (e.g., `element/2` and the type tests).
+const STRUCTURAL_BIFS: &[(&str, Arity)] = &[("bit_size", 1),
+                                             ("byte_size", 1),
+                                             ("element", 2),
+                                             ("hd", 1),
+                                             ("is_atom", 1),
+                                             ("is_binary", 1),
+                                             ("is_bitstring", 1),
+                                             ("is_boolean", 1),
+                                             ("is_float", 1),
+                                             ("is_function", 1),
+                                             ("is_function", 2),
+                                             ("is_integer", 1),
+                                             ("is_list", 1),
+                                             ("is_map", 1),
+                                             ("is_map_key", 2),
+                                             ("is_number", 1),
+                                             ("is_pid", 1),
+                                             ("is_port", 1),
+                                             ("is_record", 2),
+                                             ("is_record", 3),
+                                             ("is_reference", 1),
+                                             ("is_tuple", 1),
+                                             ("length", 1),
+                                             ("map_get", 2),
+                                             ("map_size", 1),
+                                             ("setelement", 3),
+                                             ("size", 1),
+                                             ("tl", 1),
+                                             ("tuple_size", 1),
+                                             ("tuple_to_list", 1)];
+
+struct Checker<'a, 'b: 'a> {
+    env: &'a Env,
+    solver: &'a Solver<'b>,
+    module: &'a Module,
+    graph: &'a Graph,
+    fun: &'a Remote,
+    types: HashMap<NodeId, Type>,
+    aliases: &'a Aliases,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+impl<'a, 'b: 'a> Checker<'a, 'b> {
+    fn check(mut self, signature: &FunSpec) {
+        let line = self.graph.clauses.first().map_or(0, |c| c.line);
+        let spec = self.module.specs.get(&self.fun.to_local());
+        let args = signature.args.clone().unwrap_or_default();
+        let args = args.into_iter()
+            .enumerate()
+            .map(|(i, t)| (format!("argument {}", i + 1), t))
+            .collect::<Vec<_>>();
+
+        let specs = spec.map(|s| Resolver::new(self.env, &self.module.name).resolve_spec(s));
+
+        if lattice::is_none(&signature.return_type) {
+            let message =
+                format!("Function {}/{} has no local return", self.fun.name, self.fun.arity);
+            self.report(Kind::NoReturn, line, message, args.clone());
+        } else if let Some(specs) = specs {
+            let spec_ret = lattice::join(&specs.iter()
+                .map(|s| s.return_type.clone())
+                .collect::<Vec<_>>());
+            if lattice::is_none(&lattice::inf(&signature.return_type, &spec_ret)) {
+                let message = format!("The return type {} of {}/{} has nothing in common with \
+                                       the spec {}",
+                                      self.fold(&signature.return_type),
+                                      self.fun.name,
+                                      self.fun.arity,
+                                      self.fold(&spec_ret));
+                let types = vec![("inferred return".to_string(), signature.return_type.clone()),
+                                 ("spec return".to_string(), spec_ret)];
+                self.report(Kind::SpecMismatch, line, message, types);
+            }
+        }
+
+        let clauses = self.graph.clauses.iter().filter(|c| c.parent.is_none());
+        for (index, clause) in clauses.enumerate() {
+            let unmatched = clause.patterns.iter().any(|p| lattice::is_none(&self.type_of(*p)));
+            if unmatched {
+                let mut types = args.clone();
+                for (i, p) in clause.patterns.iter().enumerate() {
+                    if let Content::Val(ref x) = self.graph.nodes[p].content {
+                        types.push((format!("pattern {}", i + 1), x.producible_type.to_type()));
+                    }
+                }
+                // NOTE: The message does not include the line to keep the fingerprint stable
+                let message = format!("The clause {} of {}/{} can never match",
+                                      index + 1,
+                                      self.fun.name,
+                                      self.fun.arity);
+                self.report(Kind::UnmatchedClause, clause.line, message, types);
+            }
+        }
+
+        let mut nodes = self.graph.nodes.values().collect::<Vec<_>>();
+        nodes.sort_by_key(|n| n.id);
+        for node in nodes {
+            let args = match node.content {
+                Content::LocalCall(ref x) if !x.is_pattern => &x.args,
+                Content::RemoteCall(ref x) => &x.args,
+                _ => continue,
+            };
+            if !self.is_reachable(node.clause) {
+                continue;
+            }
+            let callee = match solver::resolve_callee(self.module, self.graph, &node.content) {
+                Some(callee) => callee,
+                None => continue,
+            };
+            let arg_types = args.iter().map(|a| self.type_of(*a)).collect::<Vec<_>>();
+            if arg_types.iter().any(lattice::is_none) {
+                continue;
+            }
+            let clauses = self.solver.lookup(&callee);
+            let matches = |c: &FunSpec| match c.args {
+                Some(ref params) => {
+                    params.iter()
+                        .zip(arg_types.iter())
+                        .all(|(p, a)| !lattice::is_none(&lattice::inf(p, a)))
+                }
+                None => true,
+            };
+            if clauses.is_empty() || clauses.iter().any(matches) {
+                continue;
+            }
+            let mut types = arg_types.into_iter()
+                .enumerate()
+                .map(|(i, t)| (format!("argument {}", i + 1), t))
+                .collect::<Vec<_>>();
+            for (i, c) in clauses.into_iter().enumerate() {
+                let t = From::from(ty::FunType { clauses: vec![c] });
+                types.push((format!("callee clause {}", i + 1), t));
+            }
+            let line = node.clause.map_or(line, |c| self.graph.clauses[c].line);
+            let message = format!("The call {}:{}/{} will never succeed",
+                                  callee.module,
+                                  callee.name,
+                                  callee.arity);
+            self.report(Kind::CallFails, line, message, types);
+        }
+
+        self.check_opacity(line);
+    }
+
+    // NOTE: Only the opacity declared in specs (including the cached ones, see `plt`) is tracked
+    fn check_opacity(&mut self, line: i32) {
+        let patterns = self.graph
+            .nodes
+            .values()
+            .filter_map(|n| match n.content {
+                Content::LocalCall(ref x) if x.is_pattern => Some(x.return_value),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        let is_structural = |id: NodeId| {
+            patterns.contains(&id) ||
+            match self.graph.nodes[&id].content {
+                Content::Val(ref x) => x.consumable_type != TypeId::any(),
+                _ => false,
+            }
+        };
+        let mut matches = self.graph
+            .edges
+            .values()
+            .filter(|e| matches!(e.kind, EdgeKind::Match))
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|e| e.id);
+
+        // NOTE: Opaque values are propagated to the variables bound to them
+        let mut opaques = self.opaque_values();
+        loop {
+            let bindings = matches.iter()
+                .filter(|e| !is_structural(e.consumer) && !opaques.contains_key(&e.consumer))
+                .filter_map(|e| opaques.get(&e.producer).map(|o| (e.consumer, o.clone())))
+                .collect::<Vec<_>>();
+            if bindings.is_empty() {
+                break;
+            }
+            opaques.extend(bindings);
+        }
+
+        let mut violations = Vec::new();
+        for e in &matches {
+            let consumer = &self.graph.nodes[&e.consumer];
+            if !is_structural(e.consumer) || !self.is_reachable(consumer.clause) {
+                continue;
+            }
+            if let Some(opaque) = opaques.get(&e.producer) {
+                let message = format!("A pattern looks into the opaque type {}/{} of module {}",
+                                      opaque.name,
+                                      opaque.arity,
+                                      opaque.module);
+                let mut types = vec![("matched value".to_string(), self.type_of(e.producer))];
+                match consumer.content {
+                    Content::Val(ref x) if !patterns.contains(&e.consumer) => {
+                        types.push(("pattern".to_string(), x.producible_type.to_type()));
+                    }
+                    _ => {}
+                }
+                violations.push((consumer.clause, message, types));
+            }
+        }
+
+        let mut nodes = self.graph.nodes.values().collect::<Vec<_>>();
+        nodes.sort_by_key(|n| n.id);
+        for node in nodes {
+            let args = match node.content {
+                Content::LocalCall(ref x) if !x.is_pattern => &x.args,
+                Content::RemoteCall(ref x) => &x.args,
+                _ => continue,
+            };
+            if !self.is_reachable(node.clause) {
+                continue;
+            }
+            let callee = match solver::resolve_callee(self.module, self.graph, &node.content) {
+                Some(callee) => callee,
+                None => continue,
+            };
+            let is_structural_bif = callee.module == "erlang" &&
+                                    STRUCTURAL_BIFS.contains(&(callee.name.as_str(),
+                                                               callee.arity));
+            if !is_structural_bif {
+                continue;
+            }
+            for (i, a) in args.iter().enumerate() {
+                if let Some(opaque) = opaques.get(a) {
+                    let message = format!("The call {}:{}/{} looks into the opaque type {}/{} \
+                                           of module {}",
+                                          callee.module,
+                                          callee.name,
+                                          callee.arity,
+                                          opaque.name,
+                                          opaque.arity,
+                                          opaque.module);
+                    let types = vec![(format!("argument {}", i + 1), self.type_of(*a))];
+                    violations.push((node.clause, message, types));
+                }
+            }
+        }
+
+        for (clause, message, types) in violations {
+            let line = clause.map_or(line, |c| self.graph.clauses[c].line);
+            self.report(Kind::OpaqueViolation, line, message, types);
+        }
+    }
+
+    /// Returns the values of which the types are opaque types of the other modules
+    /// (i.e., the arguments and the call results declared so by specs).
+    fn opaque_values(&self) -> HashMap<NodeId, OpaqueType> {
+        let mut opaques = HashMap::new();
+        let resolver = Resolver::new(self.env, &self.module.name);
+        if let Some(spec) = self.module.specs.get(&self.fun.to_local()) {
+            let args = self.graph.nodes.values().find_map(|n| match n.content {
+                Content::Fun(ref x) => Some(&x.args),
+                _ => None,
+            });
+            let (params, _) = opaque_signature(&resolver, spec);
+            for (a, o) in args.into_iter().flatten().zip(params) {
+                if let Some(o) = o {
+                    opaques.insert(*a, o);
+                }
+            }
+        }
+        for node in self.graph.nodes.values() {
+            let return_value = match node.content {
+                Content::LocalCall(ref x) if !x.is_pattern => x.return_value,
+                Content::RemoteCall(ref x) => x.return_value,
+                _ => continue,
+            };
+            let callee = match solver::resolve_callee(self.module, self.graph, &node.content) {
+                Some(callee) => callee,
+                None => continue,
+            };
+            if let Some(spec) = self.env.remote_spec(&callee) {
+                let resolver = Resolver::new(self.env, &callee.module);
+                if let (_, Some(o)) = opaque_signature(&resolver, &spec) {
+                    opaques.insert(return_value, o);
+                }
+            }
+        }
+        opaques.retain(|_, o| o.module != self.module.name);
+        opaques
+    }
+
+    fn type_of(&self, id: NodeId) -> Type {
+        self.types.get(&id).cloned().unwrap_or_else(lattice::none)
+    }
+
+    // NOTE: Nodes in dead clauses are not reported (the clauses themselves are)
+    fn is_reachable(&self, clause: Option<usize>) -> bool {
+        let mut current = clause;
+        while let Some(c) = current {
+            if !solver::is_clause_alive(self.graph, &self.types, c) {
+                return false;
+            }
+            current = self.graph.clauses[c].parent;
+        }
+        true
+    }
+
+    fn report(&mut self, kind: Kind, line: i32, message: String, types: Vec<(String, Type)>) {
+        let options = &self.module.dialyzer_options;
+        if options.is_set(&self.fun.to_local(), kind.dialyzer_option()) {
+            return;
+        }
+        let file = source_file(self.module);
+        self.diagnostics.push(Diagnostic {
+            kind,
+            severity: kind.severity(),
+            function: self.fun.clone(),
+            location: Location { file, line },
+            message,
+            types: types.into_iter().map(|(role, t)| (role, self.fold(&t))).collect(),
+        });
+    }
+
+    // NOTE: Expanded types are shown with the names of known types (see `Aliases::fold`)
+    fn fold(&self, t: &Type) -> Type {
+        self.aliases.fold(t, &self.module.name)
+    }
+}
+
+// NOTE: A type is opaque only if all the clauses of `spec` agree
+fn opaque_signature(resolver: &Resolver,
+                    spec: &Spec)
+                    -> (Vec<Option<OpaqueType>>, Option<OpaqueType>) {
+    let mut clauses = spec.clauses.iter().map(|c| resolver.opaque_spec_clause(c));
+    let (mut args, mut ret) = match clauses.next() {
+        Some(c) => c,
+        None => return (Vec::new(), None),
+    };
+    for (a, r) in clauses {
+        for (x, y) in args.iter_mut().zip(a) {
+            if *x != y {
+                *x = None;
+            }
+        }
+        if ret != r {
+            ret = None;
+        }
+    }
+    (args, ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use plt::Plt;
+    use super::*;
+
+    const PROV_BEAM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/beam/testdata/prov.beam");
+    const CLI_BEAM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/beam/testdata/cli.beam");
+    const RT_BEAM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/beam/testdata/rt.beam");
+
+    fn opaque_violations(env: &Env) -> Vec<(String, i32)> {
+        let signatures = Solver::new(env).solve();
+        check(env, &signatures)
+            .into_iter()
+            .filter(|d| d.kind == Kind::OpaqueViolation && d.function.module == "cli")
+            .map(|d| (d.function.name, d.location.line))
+            .collect()
+    }
+
+    #[test]
+    fn opaque_types_of_cached_modules_are_checked() {
+        let mut env = Env::new();
+        env.add_module(Module::from_beam_file(PROV_BEAM).unwrap());
+        let signatures = Solver::new(&env).solve();
+        let plt = Plt::from_env(&env, &signatures).unwrap();
+        let plt = Plt::from_json(&plt.to_json()).unwrap();
+
+        let mut loaded = Env::new();
+        loaded.add_module(Module::from_beam_file(PROV_BEAM).unwrap());
+        loaded.add_module(Module::from_beam_file(CLI_BEAM).unwrap());
+        let mut cached = Env::new();
+        assert!(cached.use_plt(plt).is_empty());
+        cached.add_module(Module::from_beam_file(CLI_BEAM).unwrap());
+
+        // NOTE: `f/0` takes the opaque value returned by `prov:new/0` apart
+        let expected = [("f", 4), ("g", 7), ("g", 8)];
+        let expected = expected.iter().map(|&(f, l)| (f.to_string(), l)).collect::<Vec<_>>();
+        assert_eq!(opaque_violations(&loaded), expected);
+        assert_eq!(opaque_violations(&cached), expected);
+    }
+
+    #[test]
+    fn malformed_types_are_reported_at_their_declarations() {
+        let mut env = Env::new();
+        env.add_module(Module::from_beam_file(RT_BEAM).unwrap());
+        let signatures = Solver::new(&env).solve();
+        let malformed = check(&env, &signatures)
+            .into_iter()
+            .filter(|d| d.kind == Kind::MalformedType)
+            .map(|d| (d.function.name, d.location.line))
+            .collect::<Vec<_>>();
+        assert_eq!(malformed, [("bad".to_string(), 5)]);
+    }
+}
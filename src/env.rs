@@ -1,16 +1,412 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use code_path;
+use code_path::CodePath;
+use module::Local;
 use module::Module;
+use module::Remote;
+use module::Result;
+use module::Spec;
+use plt::CachedModule;
+use plt::Plt;
+use pool;
+use ty;
 
 #[derive(Debug)]
 pub struct Env {
     pub modules: HashMap<String, Module>,
+
+    /// Analysis results of the modules which are not loaded (see `Env::use_plt`)
+    pub cached_modules: HashMap<String, CachedModule>,
+
+    /// Number of threads used to load modules
+    pub jobs: usize,
+
+    /// Module name => the loaded modules which depend on it (including external ones)
+    clients: HashMap<String, HashSet<String>>,
+}
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl Env {
     pub fn new() -> Self {
-        Env { modules: HashMap::new() }
+        Env {
+            modules: HashMap::new(),
+            cached_modules: HashMap::new(),
+            jobs: 1,
+            clients: HashMap::new(),
+        }
     }
-    pub fn add_module(&mut self, module: Module) {
+    pub fn add_module(&mut self, mut module: Module) {
         assert!(!self.modules.contains_key(&module.name));
+        self.cached_modules.remove(&module.name);
+        for d in &module.dependent_modules {
+            self.clients.entry(d.clone()).or_default().insert(module.name.clone());
+            if let Some(m) = self.modules.get_mut(d) {
+                m.client_modules.insert(module.name.clone());
+            }
+        }
+        module.client_modules = self.clients.get(&module.name).cloned().unwrap_or_default();
         self.modules.insert(module.name.clone(), module);
     }
+
+    /// Removes the module `name`; its clients regard it as an external module afterwards.
+    pub fn remove_module(&mut self, name: &str) -> Option<Module> {
+        let module = self.modules.remove(name)?;
+        for d in &module.dependent_modules {
+            if let Some(clients) = self.clients.get_mut(d) {
+                clients.remove(name);
+                if clients.is_empty() {
+                    self.clients.remove(d);
+                }
+            }
+            if let Some(m) = self.modules.get_mut(d) {
+                m.client_modules.remove(name);
+            }
+        }
+        Some(module)
+    }
+
+    /// Returns the loaded modules which depend on the module `name` (which may not be loaded).
+    pub fn client_modules<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a String> + 'a {
+        self.clients.get(name).into_iter().flatten()
+    }
+
+    /// Loads `beam_file` again, replacing the module of the same name.
+    ///
+    /// Returns the name of the module.
+    pub fn reload_beam_file<P: AsRef<Path>>(&mut self, beam_file: P) -> Result<String> {
+        let beam_file = beam_file.as_ref();
+        let module = Module::from_beam_file_with_jobs(beam_file, self.jobs)
+            .map_err(|e| format!("Can't parse file: {}: {}", beam_file.display(), e))?;
+        let name = module.name.clone();
+        self.remove_module(&name);
+        self.add_module(module);
+        Ok(name)
+    }
+
+    /// Loads `beam_file` unless a module of the same name has already been loaded.
+    ///
+    /// Like the code path of the Erlang runtime, the first loaded module shadows the others.
+    /// Returns `false` if the module is shadowed.
+    pub fn load_beam_file<P: AsRef<Path>>(&mut self, beam_file: P) -> Result<bool> {
+        let beam_file = beam_file.as_ref().to_path_buf();
+        let loaded = self.load_beam_files(&[beam_file])?;
+        Ok(!loaded.is_empty())
+    }
+
+    /// Loads `beam_files` in order (see `Env::load_beam_file`).
+    ///
+    /// The files are parsed on `self.jobs` threads, but the result is the same as loading
+    /// them one by one. Returns the newly loaded beam files.
+    pub fn load_beam_files(&mut self, beam_files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        // NOTE: Files whose names show that they are shadowed are not parsed
+        let mut names = HashSet::new();
+        let candidates = beam_files.iter()
+            .filter(|b| match b.file_stem().and_then(|s| s.to_str()) {
+                Some(name) => !self.modules.contains_key(name) && names.insert(name),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        let function_jobs = (self.jobs / candidates.len().max(1)).max(1);
+        let results = pool::map(self.jobs, candidates.clone(), |beam_file| {
+            Module::from_beam_file_with_jobs(beam_file, function_jobs)
+                .map_err(|e| format!("Can't parse file: {}: {}", beam_file.display(), e))
+        });
+
+        let mut loaded = Vec::new();
+        for (beam_file, result) in candidates.into_iter().zip(results) {
+            let module = result?;
+            if !self.modules.contains_key(&module.name) {
+                self.add_module(module);
+                loaded.push(beam_file.clone());
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Loads the beam files in the `ebin` directories under `dir` (see `Env::load_beam_files`).
+    pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<Vec<PathBuf>> {
+        self.load_beam_files(&code_path::find_beams_in_dir(dir)?)
+    }
+    /// Loads the beam files in the library directories of `erl_libs` (e.g., `ERL_LIBS`).
+    pub fn load_erl_libs(&mut self, erl_libs: &str) -> Result<Vec<PathBuf>> {
+        self.load_beam_files(&code_path::find_beams_in_erl_libs(erl_libs)?)
+    }
+    /// Loads the latest versions of the applications under `otp_root`.
+    pub fn load_otp_root<P: AsRef<Path>>(&mut self, otp_root: P) -> Result<Vec<PathBuf>> {
+        self.load_beam_files(&code_path::find_beams_in_lib_dir(otp_root)?)
+    }
+    /// Loads the modules listed in the application resource file `app_file`.
+    pub fn load_app_file<P: AsRef<Path>>(&mut self, app_file: P) -> Result<Vec<PathBuf>> {
+        self.load_beam_files(&code_path::find_beams_in_app_file(app_file)?)
+    }
+
+    /// Loads `beam_files` and the modules of `code_path`.
+    ///
+    /// The given beam files and the modules of `code_path.apps` shadow the ones in the
+    /// search path (see `CodePath::search_path`). In the lazy mode, only the modules which they
+    /// depend on are loaded from the search path (see `Env::load_lazily`).
+    /// Cached modules (see `Env::use_plt`) are loaded from the search path only if they are
+    /// given as `beam_files`.
+    ///
+    /// Returns the newly loaded beam files.
+    pub fn load_code_path(&mut self,
+                          beam_files: &[PathBuf],
+                          code_path: &CodePath)
+                          -> Result<Vec<PathBuf>> {
+        let mut roots = beam_files.to_vec();
+        for app in &code_path.apps {
+            roots.extend(code_path::find_beams_in_app_file(app)?);
+        }
+        roots.retain(|b| code_path.is_selected(b));
+        let search_path = code_path.search_path()?;
+        if code_path.lazy {
+            return self.load_lazily(&roots, &search_path, code_path.depth);
+        }
+        roots.extend(search_path.into_iter().filter(|b| {
+            let name = b.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+            !self.cached_modules.contains_key(name)
+        }));
+        self.load_beam_files(&roots)
+    }
+
+    /// Loads `roots` and then the modules they depend on, on demand.
+    ///
+    /// Dependencies are looked up in `search_path` (a list of beam files, earlier ones shadow
+    /// later ones) and followed up to `max_depth` hops from the roots (`None` means unlimited).
+    /// Modules which are not loaded are treated as externals (see `Env::remote_spec`).
+    ///
+    /// Returns the newly loaded beam files.
+    pub fn load_lazily(&mut self,
+                       roots: &[PathBuf],
+                       search_path: &[PathBuf],
+                       max_depth: Option<usize>)
+                       -> Result<Vec<PathBuf>> {
+        let mut index = HashMap::new();
+        for beam in search_path {
+            if let Some(name) = beam.file_stem().and_then(|s| s.to_str()) {
+                index.entry(name.to_string()).or_insert_with(|| beam.clone());
+            }
+        }
+
+        // NOTE: The dependencies are loaded level by level (in breadth-first order)
+        let mut loaded = self.load_beam_files(roots)?;
+        let mut visited = HashSet::new();
+        let mut frontier = roots.iter()
+            .filter_map(|r| r.file_stem().and_then(|s| s.to_str()))
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let mut depth = 0;
+        while !frontier.is_empty() && max_depth.is_none_or(|d| depth < d) {
+            let mut next = Vec::new();
+            for name in frontier {
+                if !visited.insert(name.clone()) {
+                    continue;
+                }
+                let mut dependencies = match self.modules.get(&name) {
+                    Some(module) => module.dependent_modules.iter().cloned().collect::<Vec<_>>(),
+                    None => continue,
+                };
+                dependencies.sort();
+                next.extend(dependencies.into_iter()
+                    .filter(|d| !self.cached_modules.contains_key(d) && index.contains_key(d)));
+            }
+            let beams = next.iter().map(|d| index[d].clone()).collect::<Vec<_>>();
+            loaded.extend(self.load_beam_files(&beams)?);
+            frontier = next;
+            depth += 1;
+        }
+        Ok(loaded)
+    }
+
+    /// Returns the modules which are referred by loaded modules but not loaded themselves.
+    pub fn external_modules(&self) -> HashSet<&str> {
+        self.modules
+            .values()
+            .flat_map(|m| m.dependent_modules.iter())
+            .filter(|m| !self.modules.contains_key(*m) && !self.cached_modules.contains_key(*m))
+            .map(|m| m.as_str())
+            .collect()
+    }
+    pub fn is_external(&self, module: &str) -> bool {
+        !self.modules.contains_key(module) && !self.cached_modules.contains_key(module)
+    }
+
+    /// Uses the analysis results in `plt` for the modules which are not loaded.
+    ///
+    /// Stale entries (see `Plt::stale_modules`) are discarded.
+    /// Returns the beam files of them which still exist, so that they can be re-analyzed.
+    pub fn use_plt(&mut self, plt: Plt) -> Vec<PathBuf> {
+        let stale = plt.stale_modules();
+        let mut beam_files = Vec::new();
+        for (name, m) in plt.modules {
+            if stale.contains(&name) {
+                if m.beam_file.exists() {
+                    beam_files.push(m.beam_file);
+                }
+            } else if !self.modules.contains_key(&name) {
+                self.cached_modules.insert(name, m);
+            }
+        }
+        beam_files
+    }
+
+    /// Returns the cached signature of `fun` if its module is not loaded.
+    pub fn cached_signature(&self, fun: &Remote) -> Option<&ty::FunSpec> {
+        self.cached_modules.get(&fun.module).and_then(|m| m.signatures.get(&fun.to_local()))
+    }
+
+    /// Returns the cached definition of the exported type `module:name/arity`.
+    pub fn cached_type(&self, module: &str, key: &Local) -> Option<&ty::UserDefinedClass> {
+        self.cached_modules.get(module).and_then(|m| m.exported_types.get(key))
+    }
+
+    /// Returns the spec of `fun`.
+    ///
+    /// The specs of cached modules are taken from the PLT (see `Env::use_plt`), and functions
+    /// of the other external modules are typed as `(any(), ...) -> any()`.
+    /// `None` means that `fun` is defined in a loaded module but has no spec.
+    pub fn remote_spec(&self, fun: &Remote) -> Option<Spec> {
+        match self.modules.get(&fun.module) {
+            Some(module) => module.specs.get(&fun.to_local()).cloned(),
+            None => {
+                let spec = self.cached_modules
+                    .get(&fun.module)
+                    .and_then(|m| m.specs.get(&fun.to_local()).cloned());
+                Some(spec.unwrap_or_else(|| Spec::any(fun.arity)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::process;
+    use std::slice;
+    use code_path::CodePath;
+    use super::*;
+
+    const HELLO_BEAM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/beam/testdata/hello.beam");
+
+    // NOTE: Each test uses its own directory because tests run in parallel
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = ::std::env::temp_dir().join(format!("diagnoser-env-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn put_beam(ebin: &Path) -> PathBuf {
+        fs::create_dir_all(ebin).unwrap();
+        let beam = ebin.join("hello.beam");
+        fs::copy(HELLO_BEAM, &beam).unwrap();
+        beam
+    }
+
+    #[test]
+    fn beams_in_ebin_dirs_are_loaded() {
+        let dir = temp_dir("dir");
+        let beam = put_beam(&dir.join("hello/ebin"));
+        let mut env = Env::new();
+        assert_eq!(env.load_dir(&dir).unwrap(), vec![beam]);
+        assert!(env.modules.contains_key("hello"));
+    }
+
+    #[test]
+    fn latest_app_versions_are_loaded_from_otp_root() {
+        let dir = temp_dir("otp");
+        put_beam(&dir.join("hello-1.9/ebin"));
+        let latest = put_beam(&dir.join("hello-1.10/ebin"));
+        let mut env = Env::new();
+        assert_eq!(env.load_otp_root(&dir).unwrap(), vec![latest.clone()]);
+        let mut env = Env::new();
+        let erl_libs = format!("{}:", dir.display());
+        assert_eq!(env.load_erl_libs(&erl_libs).unwrap(), vec![latest]);
+    }
+
+    #[test]
+    fn modules_of_app_files_are_loaded() {
+        let dir = temp_dir("app");
+        let beam = put_beam(&dir.join("ebin"));
+        let app_file = dir.join("ebin/hello.app");
+        fs::write(&app_file, "{application, hello, [{modules, [hello]}]}.").unwrap();
+        let mut env = Env::new();
+        assert_eq!(env.load_app_file(&app_file).unwrap(), vec![beam]);
+
+        fs::write(&app_file, "{application, hello, [{modules, [hello, missing]}]}.").unwrap();
+        assert!(Env::new().load_app_file(&app_file).is_err());
+    }
+
+    #[test]
+    fn earlier_code_path_entries_shadow_later_ones() {
+        let dir = temp_dir("shadow");
+        let first = put_beam(&dir.join("a/ebin"));
+        put_beam(&dir.join("b/ebin"));
+        let code_path = CodePath {
+            dirs: vec![dir.join("a"), dir.join("b")],
+            ..CodePath::default()
+        };
+        let mut env = Env::new();
+        assert_eq!(env.load_code_path(&[], &code_path).unwrap(), vec![first.clone()]);
+        assert_eq!(env.modules["hello"].beam_file.as_ref(), Some(&first));
+
+        let given = put_beam(&dir.join("c"));
+        let mut env = Env::new();
+        let loaded = env.load_code_path(slice::from_ref(&given), &code_path).unwrap();
+        assert_eq!(loaded, vec![given]);
+    }
+
+    #[test]
+    fn excluded_modules_are_not_loaded() {
+        let dir = temp_dir("exclude");
+        let beam = put_beam(&dir.join("ebin"));
+        let code_path = CodePath {
+            dirs: vec![dir.clone()],
+            exclude: vec!["hel*".to_string()],
+            ..CodePath::default()
+        };
+        let mut env = Env::new();
+        assert!(env.load_code_path(&[beam], &code_path).unwrap().is_empty());
+        assert!(env.modules.is_empty());
+    }
+
+    fn module(name: &str, dependent_modules: &[&str]) -> Module {
+        let mut module = Module::from_beam_file(HELLO_BEAM).unwrap();
+        module.name = name.to_string();
+        module.dependent_modules = dependent_modules.iter().map(|m| m.to_string()).collect();
+        module
+    }
+
+    fn clients(env: &Env, name: &str) -> Vec<String> {
+        let mut clients = env.client_modules(name).cloned().collect::<Vec<_>>();
+        clients.sort();
+        clients
+    }
+
+    #[test]
+    fn client_modules_follow_added_and_removed_modules() {
+        let mut env = Env::new();
+        env.add_module(module("a", &["b", "ext"]));
+        env.add_module(module("c", &["a", "b"]));
+        env.add_module(module("b", &[]));
+        assert_eq!(env.modules["a"].client_modules, ["c".to_string()].iter().cloned().collect());
+        assert_eq!(env.modules["b"].client_modules.len(), 2);
+        assert_eq!(clients(&env, "ext"), ["a"]);
+
+        env.remove_module("c");
+        assert!(env.modules["a"].client_modules.is_empty());
+        assert_eq!(clients(&env, "b"), ["a"]);
+
+        env.remove_module("a");
+        assert!(env.modules["b"].client_modules.is_empty());
+        assert!(clients(&env, "ext").is_empty());
+    }
 }
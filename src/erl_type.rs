@@ -332,7 +332,7 @@ pub struct UnionType {
 impl ProtoType for UnionType {}
 impl UnionType {
     pub fn new(types: Vec<Type>) -> Self {
-        UnionType { types: types }
+        UnionType { types }
     }
 }
 pub fn union(types: &[Type]) -> Type {
@@ -1,11 +1,13 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
+use intern::TypeId;
 use module;
 use module::Arity;
 use ty;
 
 pub type NodeId = usize;
 pub type EdgeId = usize;
+pub type ClauseId = usize;
 
 #[derive(Debug)]
 pub struct Graph {
@@ -13,6 +15,13 @@ pub struct Graph {
     pub next_edge_id: EdgeId,
     pub nodes: HashMap<NodeId, Node>,
     pub edges: HashMap<EdgeId, Edge>,
+    pub clauses: Vec<Clause>,
+    current_clause: Option<ClauseId>,
+}
+impl Default for Graph {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl Graph {
     pub fn new() -> Self {
@@ -21,15 +30,24 @@ impl Graph {
             next_edge_id: 0,
             nodes: HashMap::new(),
             edges: HashMap::new(),
+            clauses: Vec::new(),
+            current_clause: None,
         }
     }
+    /// Returns the interned types of the `Val` nodes (see `intern::collect_garbage`).
+    pub fn type_ids<'a>(&'a self) -> impl Iterator<Item = TypeId> + 'a {
+        self.nodes.values().flat_map(|n| match n.content {
+            Content::Val(ref x) => vec![x.producible_type, x.consumable_type],
+            _ => Vec::new(),
+        })
+    }
     pub fn add_edge(&mut self, kind: EdgeKind, producer: NodeId, consumer: NodeId) -> EdgeId {
         let id = self.next_edge_id();
         let edge = Edge {
-            id: id,
-            kind: kind,
-            producer: producer,
-            consumer: consumer,
+            id,
+            kind,
+            producer,
+            consumer,
         };
         self.edges.insert(id, edge);
         self.nodes.get_mut(&producer).unwrap().edges.insert(id);
@@ -37,12 +55,29 @@ impl Graph {
         id
     }
 
-    pub fn new_conj(&mut self, nodes: Vec<NodeId>) {
+    pub fn new_conj(&mut self, nodes: Vec<NodeId>) -> NodeId {
         let conj = Conj::new(self, nodes.clone());
         let conj_id = self.new_node(Content::Conj(conj));
         for id in nodes {
             self.add_edge(EdgeKind::Conj, id, conj_id);
         }
+        conj_id
+    }
+
+    /// Starts a clause; nodes created until `clause_out` belong to it.
+    pub fn clause_in(&mut self, line: i32) -> ClauseId {
+        let id = self.clauses.len();
+        self.clauses.push(Clause::new(id, self.current_clause, line));
+        self.current_clause = Some(id);
+        id
+    }
+    pub fn clause_out(&mut self) {
+        let current = self.current_clause.expect("No current clause");
+        self.current_clause = self.clauses[current].parent;
+    }
+    pub fn current_clause_mut(&mut self) -> &mut Clause {
+        let current = self.current_clause.expect("No current clause");
+        &mut self.clauses[current]
     }
 
     pub fn new_external_fun_node(&mut self, arity: Arity) -> NodeId {
@@ -64,6 +99,13 @@ impl Graph {
         self.new_node(Content::LocalCall(call))
     }
 
+    /// Makes a call node which destructures its return value into `args` (e.g., tuple patterns).
+    pub fn new_pattern_call_node(&mut self, fun: NodeId, args: Vec<NodeId>) -> NodeId {
+        let mut call = LocalCall::new(self, fun, args);
+        call.is_pattern = true;
+        self.new_node(Content::LocalCall(call))
+    }
+
     pub fn new_value_node(&mut self, value: Val) -> NodeId {
         let content = Content::Val(value);
         self.new_node(content)
@@ -98,7 +140,11 @@ impl Graph {
 
     fn new_node(&mut self, content: Content) -> NodeId {
         let node_id = self.next_node_id();
-        let node = Node::new(node_id, content);
+        let mut node = Node::new(node_id, content);
+        node.clause = self.current_clause;
+        if let Some(clause) = self.current_clause {
+            self.clauses[clause].nodes.push(node_id);
+        }
         self.nodes.insert(node_id, node);
         node_id
     }
@@ -128,8 +174,8 @@ impl Fun {
         let args = (0..arity).map(|_| graph.new_value_node(Val::new())).collect();
         let return_value = graph.new_value_node(Val::new());
         Fun {
-            args: args,
-            return_value: return_value,
+            args,
+            return_value,
         }
     }
 }
@@ -139,14 +185,16 @@ pub struct LocalCall {
     pub fun: NodeId,
     pub args: Vec<NodeId>,
     pub return_value: NodeId,
+    pub is_pattern: bool,
 }
 impl LocalCall {
     pub fn new(graph: &mut Graph, fun: NodeId, args: Vec<NodeId>) -> Self {
         let return_value = graph.new_value_node(Val::new());
         LocalCall {
-            fun: fun,
-            args: args,
-            return_value: return_value,
+            fun,
+            args,
+            return_value,
+            is_pattern: false,
         }
     }
 }
@@ -162,10 +210,10 @@ impl RemoteCall {
     pub fn new(graph: &mut Graph, module: NodeId, fun: NodeId, args: Vec<NodeId>) -> Self {
         let return_value = graph.new_value_node(Val::new());
         RemoteCall {
-            module: module,
-            fun: fun,
-            args: args,
-            return_value: return_value,
+            module,
+            fun,
+            args,
+            return_value,
         }
     }
 }
@@ -176,38 +224,44 @@ pub struct Conj {
 }
 impl Conj {
     pub fn new(_graph: &mut Graph, nodes: Vec<NodeId>) -> Self {
-        Conj { nodes: nodes }
+        Conj { nodes }
     }
 }
 
 #[derive(Debug)]
 pub struct Val {
-    pub producible_type: ty::Type,
-    pub consumable_type: ty::Type,
+    pub producible_type: TypeId,
+    pub consumable_type: TypeId,
+}
+impl Default for Val {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl Val {
     pub fn new() -> Self {
         Val {
-            producible_type: From::from(ty::AnyType),
-            consumable_type: From::from(ty::AnyType),
+            producible_type: TypeId::any(),
+            consumable_type: TypeId::any(),
         }
     }
     pub fn new_any() -> Self {
         Val {
-            producible_type: From::from(ty::AnyType),
-            consumable_type: From::from(ty::AnyType),
+            producible_type: TypeId::any(),
+            consumable_type: TypeId::any(),
         }
     }
     pub fn new_var() -> Self {
         Val {
-            producible_type: From::from(ty::NoneType),
-            consumable_type: From::from(ty::AnyType),
+            producible_type: TypeId::none(),
+            consumable_type: TypeId::any(),
         }
     }
     pub fn with_type(ty: ty::Type) -> Self {
+        let id = TypeId::new(ty);
         Val {
-            producible_type: ty.clone(),
-            consumable_type: ty,
+            producible_type: id,
+            consumable_type: id,
         }
     }
 }
@@ -223,11 +277,11 @@ pub enum Content {
 impl Content {
     pub fn label(&self) -> String {
         match *self {
-            Content::Fun(ref _x) => format!("fun"),
+            Content::Fun(ref _x) => "fun".to_string(),
             Content::Val(ref x) => format!("({}=>{})", x.producible_type, x.consumable_type),
-            Content::LocalCall(ref _x) => format!("local"),
-            Content::RemoteCall(ref _x) => format!("remote"),
-            Content::Conj(ref _x) => format!("conj"),
+            Content::LocalCall(ref _x) => "local".to_string(),
+            Content::RemoteCall(ref _x) => "remote".to_string(),
+            Content::Conj(ref _x) => "conj".to_string(),
         }
     }
     pub fn link_nodes(&self) -> Vec<(EdgeKind, NodeId)> {
@@ -256,7 +310,7 @@ impl Content {
                 nodes.push((EdgeKind::Return, x.return_value));
             }
             Content::Conj(ref x) => {
-                for (_i, n) in x.nodes.iter().enumerate() {
+                for n in x.nodes.iter() {
                     nodes.push((EdgeKind::Conj, *n));
                 }
             }
@@ -272,14 +326,16 @@ pub struct Node {
     pub content: Content,
     pub edges: HashSet<EdgeId>,
     pub depends_on: Vec<Target>,
+    pub clause: Option<ClauseId>, // `None` means "the function head"
 }
 impl Node {
     pub fn new(id: NodeId, content: Content) -> Self {
         Node {
-            id: id,
-            content: content,
+            id,
+            content,
             edges: HashSet::new(),
             depends_on: Vec::new(),
+            clause: None,
         }
     }
     // pub fn add_producer(&mut self, graph: &mut Graph, producer: NodeId) {
@@ -292,6 +348,31 @@ impl Node {
     }
 }
 
+/// A function, `case`, `if`, `try` or `receive` clause.
+#[derive(Debug)]
+pub struct Clause {
+    pub id: ClauseId,
+    pub parent: Option<ClauseId>, // `None` means "a function clause"
+    pub line: i32,
+    pub patterns: Vec<NodeId>,
+    pub guards: Vec<NodeId>, // `Conj` nodes (the clause is selected if any of them holds)
+    pub body: Option<NodeId>,
+    pub nodes: Vec<NodeId>,
+}
+impl Clause {
+    pub fn new(id: ClauseId, parent: Option<ClauseId>, line: i32) -> Self {
+        Clause {
+            id,
+            parent,
+            line,
+            patterns: Vec::new(),
+            guards: Vec::new(),
+            body: None,
+            nodes: Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Edge {
     pub id: EdgeId,
@@ -316,12 +397,12 @@ impl EdgeKind {
         match *self {
             EdgeKind::Param(i) => format!("p[{}]", i),
             EdgeKind::Arg(i) => format!("a[{}]", i),
-            EdgeKind::Return => format!("ret"),
-            EdgeKind::Conj => format!("conj"),
-            EdgeKind::Match => format!("mat"),
-            EdgeKind::Fun => format!("fun"),
-            EdgeKind::Module => format!("mod"),
-            EdgeKind::Unknown => format!("unk"),
+            EdgeKind::Return => "ret".to_string(),
+            EdgeKind::Conj => "conj".to_string(),
+            EdgeKind::Match => "mat".to_string(),
+            EdgeKind::Fun => "fun".to_string(),
+            EdgeKind::Module => "mod".to_string(),
+            EdgeKind::Unknown => "unk".to_string(),
         }
     }
 }
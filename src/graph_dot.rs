@@ -1,39 +1,189 @@
+//! Graphviz (DOT) rendering of function graphs.
+use std::collections::HashMap;
 use std::io;
 use std::io::Write;
 use graph;
+use graph::ClauseId;
+use graph::Content;
+use graph::Node;
+use graph::NodeId;
+use intern;
+use lattice;
+use ty::Type;
+
+#[derive(Debug, Clone)]
+pub struct DotOptions {
+    /// Node shapes and colors depend on the kind of the content,
+    /// and data edges are drawn differently from structural (`link_nodes`) ones
+    pub styled: bool,
+
+    /// Each clause is drawn as a cluster
+    pub clusters: bool,
+
+    /// `Val` nodes whose producible and consumable types have no intersection
+    /// (and nodes inferred to be `none()`) are highlighted
+    pub highlight_conflicts: bool,
+}
+impl DotOptions {
+    /// Options for plain output (every node is a text label).
+    pub fn plain() -> Self {
+        DotOptions {
+            styled: false,
+            clusters: false,
+            highlight_conflicts: false,
+        }
+    }
+}
+impl Default for DotOptions {
+    fn default() -> Self {
+        DotOptions {
+            styled: true,
+            clusters: true,
+            highlight_conflicts: true,
+        }
+    }
+}
 
 pub struct DotWriter<W> {
     w: W,
+    options: DotOptions,
 }
 impl<W> DotWriter<W>
     where W: Write
 {
     pub fn new(w: W) -> Self {
-        DotWriter { w: w }
+        DotWriter::with_options(w, DotOptions::default())
+    }
+    pub fn with_options(w: W, options: DotOptions) -> Self {
+        DotWriter { w, options }
     }
 
     pub fn write(&mut self, graph: &graph::Graph) -> io::Result<()> {
-        try!(write!(self.w, "digraph g {{\n"));
-        for node in graph.nodes.values() {
-            try!(write!(self.w, "{} [label={:?}];\n", node.id, node.label()));
+        self.write_with_types(graph, &HashMap::new())
+    }
+
+    /// Writes `graph` labeling the nodes with the inferred `types` (see `Solver::node_types`).
+    ///
+    /// Nodes and edges are written in the order of their IDs, so the output is deterministic.
+    pub fn write_with_types(&mut self,
+                            graph: &graph::Graph,
+                            types: &HashMap<NodeId, Type>)
+                            -> io::Result<()> {
+        let mut nodes = graph.nodes.values().collect::<Vec<_>>();
+        nodes.sort_by_key(|n| n.id);
+
+        writeln!(self.w, "digraph g {{")?;
+        if self.options.clusters {
+            let mut clause_nodes = HashMap::new();
+            for node in &nodes {
+                clause_nodes.entry(node.clause).or_insert_with(Vec::new).push(*node);
+            }
+            for node in clause_nodes.get(&None).map_or(&[][..], |ns| &ns[..]) {
+                self.write_node(node, types)?;
+            }
+            for clause in graph.clauses.iter().filter(|c| c.parent.is_none()) {
+                self.write_cluster(graph, clause.id, &clause_nodes, types, 1)?;
+            }
+        } else {
+            for node in &nodes {
+                self.write_node(node, types)?;
+            }
         }
-        for node in graph.nodes.values() {
+
+        let (link_style, data_style) = if self.options.styled {
+            (", style=dashed, color=gray50, fontcolor=gray50, arrowhead=empty", ", style=bold")
+        } else {
+            ("", "")
+        };
+        for node in &nodes {
             for (kind, id) in node.content.link_nodes() {
-                try!(write!(self.w,
-                            "{} -> {} [label={:?}];\n",
-                            id,
-                            node.id,
-                            kind.label()));
+                writeln!(self.w,
+                         "{} -> {} [label={:?}{}];",
+                         id,
+                         node.id,
+                         kind.label(),
+                         link_style)?;
             }
         }
-        for edge in graph.edges.values() {
-            try!(write!(self.w,
-                        "{} -> {} [label={:?}];\n",
-                        edge.producer,
-                        edge.consumer,
-                        edge.kind.label()));
+        let mut edges = graph.edges.values().collect::<Vec<_>>();
+        edges.sort_by_key(|e| e.id);
+        for edge in edges {
+            writeln!(self.w,
+                     "{} -> {} [label={:?}{}];",
+                     edge.producer,
+                     edge.consumer,
+                     edge.kind.label(),
+                     data_style)?;
         }
-        try!(write!(self.w, "}}\n"));
+        writeln!(self.w, "}}")?;
         Ok(())
     }
+
+    fn write_cluster(&mut self,
+                     graph: &graph::Graph,
+                     clause: ClauseId,
+                     clause_nodes: &HashMap<Option<ClauseId>, Vec<&Node>>,
+                     types: &HashMap<NodeId, Type>,
+                     depth: usize)
+                     -> io::Result<()> {
+        let indent = "  ".repeat(depth);
+        let line = graph.clauses[clause].line;
+        writeln!(self.w, "{}subgraph cluster_{} {{", indent, clause)?;
+        writeln!(self.w, "{}  label=\"clause {} (line {})\";", indent, clause, line)?;
+        for node in clause_nodes.get(&Some(clause)).map_or(&[][..], |ns| &ns[..]) {
+            write!(self.w, "{}  ", indent)?;
+            self.write_node(node, types)?;
+        }
+        for child in graph.clauses.iter().filter(|c| c.parent == Some(clause)) {
+            self.write_cluster(graph, child.id, clause_nodes, types, depth + 1)?;
+        }
+        writeln!(self.w, "{}}}", indent)?;
+        Ok(())
+    }
+
+    fn write_node(&mut self, node: &Node, types: &HashMap<NodeId, Type>) -> io::Result<()> {
+        let label = match types.get(&node.id) {
+            Some(t) => format!("{} :: {}", node.label(), t),
+            None => node.label(),
+        };
+        let mut attrs = vec![("label", format!("{:?}", label))];
+        if self.options.styled {
+            let (shape, color) = match node.content {
+                Content::Fun(_) => ("doubleoctagon", "lightblue"),
+                Content::Val(_) => ("ellipse", "white"),
+                Content::LocalCall(_) => ("box", "lightyellow"),
+                Content::RemoteCall(_) => ("box", "orange"),
+                Content::Conj(_) => ("diamond", "lightgray"),
+            };
+            set_attr(&mut attrs, "shape", shape);
+            set_attr(&mut attrs, "style", "filled");
+            set_attr(&mut attrs, "fillcolor", color);
+        }
+        if self.options.highlight_conflicts && is_conflicting(node, types) {
+            set_attr(&mut attrs, "style", "filled");
+            set_attr(&mut attrs, "color", "red");
+            set_attr(&mut attrs, "fillcolor", "pink");
+            set_attr(&mut attrs, "penwidth", "2");
+        }
+        let attrs = attrs.iter().map(|&(k, ref v)| format!("{}={}", k, v)).collect::<Vec<_>>();
+        writeln!(self.w, "{} [{}];", node.id, attrs.join(", "))
+    }
+}
+
+// NOTE: A later value replaces the earlier one of the same key
+fn set_attr(attrs: &mut Vec<(&'static str, String)>, key: &'static str, value: &str) {
+    match attrs.iter_mut().find(|a| a.0 == key) {
+        Some(a) => a.1 = value.to_string(),
+        None => attrs.push((key, value.to_string())),
+    }
+}
+
+fn is_conflicting(node: &Node, types: &HashMap<NodeId, Type>) -> bool {
+    if types.get(&node.id).is_some_and(lattice::is_none) {
+        return true;
+    }
+    match node.content {
+        Content::Val(ref x) => intern::inf(x.producible_type, x.consumable_type).is_none(),
+        _ => false,
+    }
 }
@@ -0,0 +1,148 @@
+//! GraphML rendering of function graphs.
+//!
+//! The attribute names are the same as the fields of the JSON schema (see `graph_json`),
+//! except that types are written in Erlang notation and the `depends_on` targets are written
+//! as a space-separated list of `type:M:N/A` and `fun:M:N/A`.
+//! Structural (`link_nodes`) edges have `structural=true`.
+//!
+//! See: [The GraphML File Format](http://graphml.graphdrawing.org/)
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use graph::Content;
+use graph::Graph;
+use graph::NodeId;
+use graph::Target;
+use graph_json;
+use ty::Type;
+use xml::escape;
+
+const KEYS: &[(&str, &str, &str, &str)] = &[("node", "kind", "string", ""),
+                                            ("node", "label", "string", ""),
+                                            ("node", "clause", "int", "-1"),
+                                            ("node", "producible_type", "string", ""),
+                                            ("node", "consumable_type", "string", ""),
+                                            ("node", "is_pattern", "boolean", "false"),
+                                            ("node", "depends_on", "string", ""),
+                                            ("node", "inferred_type", "string", ""),
+                                            ("edge", "kind", "string", ""),
+                                            ("edge", "index", "int", "-1"),
+                                            ("edge", "structural", "boolean", "false")];
+
+pub struct GraphmlWriter<W> {
+    w: W,
+}
+impl<W> GraphmlWriter<W>
+    where W: Write
+{
+    pub fn new(w: W) -> Self {
+        GraphmlWriter { w }
+    }
+
+    pub fn write(&mut self, graph: &Graph) -> io::Result<()> {
+        self.write_with_types(graph, &HashMap::new())
+    }
+
+    /// Writes `graph` including the inferred `types` (see `Solver::node_types`).
+    pub fn write_with_types(&mut self,
+                            graph: &Graph,
+                            types: &HashMap<NodeId, Type>)
+                            -> io::Result<()> {
+        writeln!(self.w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(self.w,
+                 r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+        for &(target, name, ty, default) in KEYS {
+            write!(self.w,
+                   r#"  <key id="{0}_{1}" for="{0}" attr.name="{1}" attr.type="{2}">"#,
+                   target,
+                   name,
+                   ty)?;
+            if !default.is_empty() {
+                write!(self.w, "<default>{}</default>", default)?;
+            }
+            writeln!(self.w, "</key>")?;
+        }
+        writeln!(self.w, r#"  <graph id="g" edgedefault="directed">"#)?;
+
+        let mut nodes = graph.nodes.values().collect::<Vec<_>>();
+        nodes.sort_by_key(|n| n.id);
+        for node in &nodes {
+            writeln!(self.w, r#"    <node id="n{}">"#, node.id)?;
+            self.write_data("node_kind", graph_json::content_kind(&node.content))?;
+            self.write_data("node_label", &node.label())?;
+            if let Some(clause) = node.clause {
+                self.write_data("node_clause", &clause.to_string())?;
+            }
+            match node.content {
+                Content::Val(ref x) => {
+                    self.write_data("node_producible_type", &x.producible_type.to_string())?;
+                    self.write_data("node_consumable_type", &x.consumable_type.to_string())?;
+                }
+                Content::LocalCall(ref x) => {
+                    self.write_data("node_is_pattern", &x.is_pattern.to_string())?;
+                }
+                _ => {}
+            }
+            if !node.depends_on.is_empty() {
+                let targets = node.depends_on
+                    .iter()
+                    .map(|t| match *t {
+                        Target::Type(ref f) => format!("type:{}:{}/{}", f.module, f.name, f.arity),
+                        Target::Fun(ref f) => format!("fun:{}:{}/{}", f.module, f.name, f.arity),
+                    })
+                    .collect::<Vec<_>>();
+                self.write_data("node_depends_on", &targets.join(" "))?;
+            }
+            if let Some(t) = types.get(&node.id) {
+                self.write_data("node_inferred_type", &t.to_string())?;
+            }
+            writeln!(self.w, "    </node>")?;
+        }
+
+        let mut edge_id = 0;
+        for node in &nodes {
+            for (kind, id) in node.content.link_nodes() {
+                let (kind, index) = graph_json::edge_kind(&kind);
+                self.write_edge(&format!("l{}", edge_id), id, node.id, kind, index, true)?;
+                edge_id += 1;
+            }
+        }
+        let mut edges = graph.edges.values().collect::<Vec<_>>();
+        edges.sort_by_key(|e| e.id);
+        for e in edges {
+            let (kind, index) = graph_json::edge_kind(&e.kind);
+            self.write_edge(&format!("e{}", e.id), e.producer, e.consumer, kind, index, false)?;
+        }
+
+        writeln!(self.w, "  </graph>")?;
+        writeln!(self.w, "</graphml>")?;
+        Ok(())
+    }
+
+    fn write_edge(&mut self,
+                  id: &str,
+                  source: NodeId,
+                  target: NodeId,
+                  kind: &str,
+                  index: Option<usize>,
+                  is_structural: bool)
+                  -> io::Result<()> {
+        writeln!(self.w,
+                 r#"    <edge id="{}" source="n{}" target="n{}">"#,
+                 id,
+                 source,
+                 target)?;
+        self.write_data("edge_kind", kind)?;
+        if let Some(index) = index {
+            self.write_data("edge_index", &index.to_string())?;
+        }
+        if is_structural {
+            self.write_data("edge_structural", "true")?;
+        }
+        writeln!(self.w, "    </edge>")
+    }
+
+    fn write_data(&mut self, key: &str, value: &str) -> io::Result<()> {
+        writeln!(self.w, r#"      <data key="{}">{}</data>"#, key, escape(value))
+    }
+}
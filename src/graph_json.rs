@@ -0,0 +1,162 @@
+//! JSON rendering of function graphs.
+//!
+//! Schema (version 1):
+//!
+//! ```text
+//! {"schema": "diagnoser-graph",
+//!  "version": 1,
+//!  "nodes": [NODE],                  // sorted by ID
+//!  "edges": [EDGE],                  // data edges (sorted by ID)
+//!  "clauses": [CLAUSE]}
+//!
+//! NODE = {"id": INTEGER,
+//!         "clause": CLAUSE_ID|null,  // `null` means "the function head"
+//!         "kind": "fun"|"val"|"local_call"|"remote_call"|"conj",
+//!         "label": STRING,
+//!         "producible_type": TYPE,   // only `val` nodes
+//!         "consumable_type": TYPE,   // only `val` nodes
+//!         "is_pattern": BOOL,        // only `local_call` nodes
+//!         "links": [LINK],           // structural edges to the nodes of the content
+//!         "depends_on": [TARGET],
+//!         "inferred_type": TYPE}     // only if the graph is solved
+//! LINK   = {"kind": KIND, "index": INTEGER|null, "node": NODE_ID}
+//! EDGE   = {"id": INTEGER, "kind": KIND, "index": INTEGER|null,
+//!           "producer": NODE_ID, "consumer": NODE_ID}
+//! KIND   = "param"|"arg"|"return"|"conj"|"match"|"fun"|"module"|"unknown"
+//!          // `index` is the position of the parameter (or argument) for "param" and "arg"
+//! TARGET = {"kind": "type"|"fun", "module": STRING, "name": STRING, "arity": INTEGER}
+//! CLAUSE = {"id": INTEGER, "parent": CLAUSE_ID|null, "line": INTEGER,
+//!           "patterns": [NODE_ID], "guards": [NODE_ID], "body": NODE_ID|null}
+//! TYPE   = {"text": STRING,          // Erlang notation
+//!           "term": ENCODED}         // see `plt::type_to_json`
+//! ```
+//!
+//! The version is incremented when fields are removed or their meaning changes.
+use std::collections::HashMap;
+use graph::Content;
+use graph::EdgeKind;
+use graph::Graph;
+use graph::Node;
+use graph::NodeId;
+use graph::Target;
+use json::Json;
+use plt;
+use ty::Type;
+
+pub const SCHEMA: &str = "diagnoser-graph";
+pub const VERSION: i64 = 1;
+
+pub fn to_json(graph: &Graph) -> Json {
+    to_json_with_types(graph, &HashMap::new())
+}
+
+/// Makes a JSON including the inferred `types` (see `Solver::node_types`).
+pub fn to_json_with_types(graph: &Graph, types: &HashMap<NodeId, Type>) -> Json {
+    let mut nodes = graph.nodes.values().collect::<Vec<_>>();
+    nodes.sort_by_key(|n| n.id);
+    let nodes = nodes.into_iter().map(|n| node_to_json(n, types.get(&n.id))).collect();
+
+    let mut edges = graph.edges.values().collect::<Vec<_>>();
+    edges.sort_by_key(|e| e.id);
+    let edges = edges.into_iter()
+        .map(|e| {
+            let (kind, index) = edge_kind(&e.kind);
+            Json::object(vec![("id", Json::from(e.id)),
+                              ("kind", Json::from(kind)),
+                              ("index", Json::from(index)),
+                              ("producer", Json::from(e.producer)),
+                              ("consumer", Json::from(e.consumer))])
+        })
+        .collect();
+
+    let clauses = graph.clauses
+        .iter()
+        .map(|c| {
+            Json::object(vec![("id", Json::from(c.id)),
+                              ("parent", Json::from(c.parent)),
+                              ("line", Json::from(c.line as i64)),
+                              ("patterns", Json::from(c.patterns.clone())),
+                              ("guards", Json::from(c.guards.clone())),
+                              ("body", Json::from(c.body))])
+        })
+        .collect();
+
+    Json::object(vec![("schema", Json::string(SCHEMA)),
+                      ("version", Json::Integer(VERSION)),
+                      ("nodes", Json::Array(nodes)),
+                      ("edges", Json::Array(edges)),
+                      ("clauses", Json::Array(clauses))])
+}
+
+/// Returns the name and the index (of the parameter or argument) of `kind`.
+pub fn edge_kind(kind: &EdgeKind) -> (&'static str, Option<usize>) {
+    match *kind {
+        EdgeKind::Param(i) => ("param", Some(i)),
+        EdgeKind::Arg(i) => ("arg", Some(i)),
+        EdgeKind::Return => ("return", None),
+        EdgeKind::Conj => ("conj", None),
+        EdgeKind::Match => ("match", None),
+        EdgeKind::Fun => ("fun", None),
+        EdgeKind::Module => ("module", None),
+        EdgeKind::Unknown => ("unknown", None),
+    }
+}
+
+/// Returns the name of the kind of `content`.
+pub fn content_kind(content: &Content) -> &'static str {
+    match *content {
+        Content::Fun(_) => "fun",
+        Content::Val(_) => "val",
+        Content::LocalCall(_) => "local_call",
+        Content::RemoteCall(_) => "remote_call",
+        Content::Conj(_) => "conj",
+    }
+}
+
+pub fn type_to_json(t: &Type) -> Json {
+    Json::object(vec![("text", Json::string(t)), ("term", plt::type_to_json(t))])
+}
+
+fn node_to_json(node: &Node, inferred_type: Option<&Type>) -> Json {
+    let mut members = vec![("id", Json::from(node.id)),
+                           ("clause", Json::from(node.clause)),
+                           ("kind", Json::from(content_kind(&node.content))),
+                           ("label", Json::string(node.label()))];
+    match node.content {
+        Content::Val(ref x) => {
+            members.push(("producible_type", type_to_json(&x.producible_type.get())));
+            members.push(("consumable_type", type_to_json(&x.consumable_type.get())));
+        }
+        Content::LocalCall(ref x) => members.push(("is_pattern", Json::Bool(x.is_pattern))),
+        _ => {}
+    }
+    let links = node.content
+        .link_nodes()
+        .into_iter()
+        .map(|(kind, id)| {
+            let (kind, index) = edge_kind(&kind);
+            Json::object(vec![("kind", Json::from(kind)),
+                              ("index", Json::from(index)),
+                              ("node", Json::from(id))])
+        })
+        .collect();
+    members.push(("links", Json::Array(links)));
+    let depends_on = node.depends_on
+        .iter()
+        .map(|t| {
+            let (kind, f) = match *t {
+                Target::Type(ref f) => ("type", f),
+                Target::Fun(ref f) => ("fun", f),
+            };
+            Json::object(vec![("kind", Json::from(kind)),
+                              ("module", Json::string(&f.module)),
+                              ("name", Json::string(&f.name)),
+                              ("arity", Json::from(f.arity as i64))])
+        })
+        .collect();
+    members.push(("depends_on", Json::Array(depends_on)));
+    if let Some(t) = inferred_type {
+        members.push(("inferred_type", type_to_json(t)));
+    }
+    Json::object(members)
+}
@@ -0,0 +1,401 @@
+//! Interned types.
+//!
+//! Types are hash-consed into a process-wide arena and referred by small copyable `TypeId`s,
+//! so equal types share a single representation and are compared by their IDs.
+//! Only whole types are interned (i.e., the subterms of a type are not shared with the other
+//! types). The results of the lattice operations over interned types are memoized
+//! (the operations themselves are delegated to the `lattice` module).
+//!
+//! Types which are no longer referred are freed by `collect_garbage`
+//! (e.g., after each update of a long-running analysis). The types referred between
+//! the collections must be kept by `Roots` (e.g., the ones in the graphs of a `Module`),
+//! and the others are kept only while a `Pin` is alive (e.g., during solving).
+//! A freed `TypeId` is never reused, so using it panics instead of referring another type.
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::sync::RwLock;
+use lattice;
+use ty::FunSpec;
+use ty::Type;
+
+const ANY: TypeId = TypeId {
+    index: 0,
+    generation: 0,
+};
+const NONE: TypeId = TypeId {
+    index: 1,
+    generation: 0,
+};
+
+/// The ID of an interned type.
+///
+/// The generation tells apart the types interned in the same slot of the arena one after
+/// another (see `collect_garbage`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TypeId {
+    index: u32,
+    generation: u32,
+}
+impl TypeId {
+    /// Interns `t`.
+    pub fn new(t: Type) -> Self {
+        if let Some(id) = arena().read().unwrap().ids.get(&t) {
+            return *id;
+        }
+        let mut arena = arena().write().unwrap();
+        arena.insert(t)
+    }
+    pub fn any() -> Self {
+        ANY
+    }
+    pub fn none() -> Self {
+        NONE
+    }
+    pub fn get(self) -> Arc<Type> {
+        arena().read().unwrap().entry(self).0.clone()
+    }
+    pub fn to_type(self) -> Type {
+        (*self.get()).clone()
+    }
+    pub fn is_none(self) -> bool {
+        arena().read().unwrap().entry(self).1
+    }
+}
+impl fmt::Display for TypeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+/// A `FunSpec` of which the types are interned.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Signature {
+    pub args: Option<Vec<TypeId>>,
+    pub return_type: TypeId,
+}
+impl Signature {
+    pub fn new(spec: &FunSpec) -> Self {
+        Signature {
+            args: spec.args.as_ref().map(|a| a.iter().cloned().map(TypeId::new).collect()),
+            return_type: TypeId::new(spec.return_type.clone()),
+        }
+    }
+    pub fn to_fun_spec(&self) -> FunSpec {
+        FunSpec {
+            args: self.args.as_ref().map(|a| a.iter().map(|t| t.to_type()).collect()),
+            return_type: self.return_type.to_type(),
+        }
+    }
+}
+
+/// See `lattice::sup`.
+pub fn sup(a: TypeId, b: TypeId) -> TypeId {
+    join(&[a, b])
+}
+
+/// See `lattice::join`.
+pub fn join(types: &[TypeId]) -> TypeId {
+    memoize(Op::Join(types.to_vec()), || lattice::join(&to_types(types)))
+}
+
+/// See `lattice::inf`.
+pub fn inf(a: TypeId, b: TypeId) -> TypeId {
+    memoize(Op::Inf(a, b), || lattice::inf(&a.get(), &b.get()))
+}
+
+/// See `lattice::widen`.
+pub fn widen(old: TypeId, new: TypeId) -> TypeId {
+    memoize(Op::Widen(old, new), || lattice::widen(&old.get(), &new.get()))
+}
+
+/// See `lattice::tuple`.
+pub fn tuple(elements: &[TypeId]) -> TypeId {
+    memoize(Op::Tuple(elements.to_vec()), || lattice::tuple(to_types(elements)))
+}
+
+/// See `lattice::cons`.
+pub fn cons(head: TypeId, tail: TypeId) -> TypeId {
+    memoize(Op::Cons(head, tail), || lattice::cons(&head.get(), &tail.get()))
+}
+
+/// Returns the head type of a non-empty list of type `t` (see `lattice::uncons`).
+pub fn head(t: TypeId) -> TypeId {
+    memoize(Op::Head(t), || lattice::uncons(&t.get()).0)
+}
+
+/// Returns the tail type of a non-empty list of type `t` (see `lattice::uncons`).
+pub fn tail(t: TypeId) -> TypeId {
+    memoize(Op::Tail(t), || lattice::uncons(&t.get()).1)
+}
+
+/// Returns the `index`-th element type of the `arity`-tuples in `t`
+/// (see `lattice::tuple_elements`).
+pub fn tuple_element(t: TypeId, arity: usize, index: usize) -> TypeId {
+    memoize(Op::TupleElement(t, arity, index),
+            || lattice::tuple_elements(&t.get(), arity).swap_remove(index))
+}
+
+/// See `lattice::is_subtype`.
+pub fn is_subtype(a: TypeId, b: TypeId) -> bool {
+    if a == b {
+        return true;
+    }
+    // NOTE: Subtyping depends on the atom set limit by joining the operands
+    let key = (a, b, lattice::atom_set_limit());
+    if let Some(x) = arena().read().unwrap().subtypes.get(&key) {
+        return *x;
+    }
+    let (x, is_exact) = lattice::track_exactness(|| lattice::is_subtype(&a.get(), &b.get()));
+    if is_exact {
+        arena().write().unwrap().subtypes.insert(key, x);
+    }
+    x
+}
+
+/// See `lattice::is_equivalent`.
+pub fn is_equivalent(a: TypeId, b: TypeId) -> bool {
+    a == b || (is_subtype(a, b) && is_subtype(b, a))
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum Op {
+    Join(Vec<TypeId>),
+    Inf(TypeId, TypeId),
+    Widen(TypeId, TypeId),
+    Tuple(Vec<TypeId>),
+    Cons(TypeId, TypeId),
+    Head(TypeId),
+    Tail(TypeId),
+    TupleElement(TypeId, usize, usize),
+}
+
+/// Interned types which `collect_garbage` keeps while `self` is alive.
+#[derive(Debug)]
+pub struct Roots {
+    key: u64,
+}
+impl Roots {
+    pub fn new<I>(ids: I) -> Self
+        where I: IntoIterator<Item = TypeId>
+    {
+        let mut arena = arena().write().unwrap();
+        let key = arena.next_roots;
+        arena.next_roots += 1;
+        arena.roots.insert(key, ids.into_iter().collect());
+        Roots { key }
+    }
+}
+impl Drop for Roots {
+    fn drop(&mut self) {
+        arena().write().unwrap().roots.remove(&self.key);
+    }
+}
+
+/// Defers `collect_garbage` while `self` is alive (see `pin`).
+#[derive(Debug)]
+pub struct Pin {
+    _private: (),
+}
+impl Drop for Pin {
+    fn drop(&mut self) {
+        PINS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Keeps all the interned types (including the ones which are not in `Roots`) until the
+/// result is dropped.
+pub fn pin() -> Pin {
+    // NOTE: The read lock orders this against a running collection
+    let _arena = arena().read().unwrap();
+    PINS.fetch_add(1, Ordering::SeqCst);
+    Pin { _private: () }
+}
+
+static PINS: AtomicUsize = AtomicUsize::new(0);
+
+/// Frees the interned types which are not in any alive `Roots` (except `any()` and `none()`),
+/// and forgets the memoized results.
+///
+/// Nothing is freed (and `false` is returned) if a `Pin` is alive.
+pub fn collect_garbage() -> bool {
+    let mut guard = arena().write().unwrap();
+    let arena = &mut *guard;
+    if PINS.load(Ordering::SeqCst) > 0 {
+        return false;
+    }
+    let mut live = arena.roots.values().flatten().cloned().collect::<HashSet<_>>();
+    live.insert(ANY);
+    live.insert(NONE);
+    arena.memo.clear();
+    arena.subtypes.clear();
+    for i in 0..arena.slots.len() {
+        let slot = &mut arena.slots[i];
+        let id = TypeId {
+            index: i as u32,
+            generation: slot.generation,
+        };
+        if live.contains(&id) {
+            continue;
+        }
+        if let Some((t, _)) = slot.entry.take() {
+            // NOTE: A slot whose generation is exhausted is never reused
+            slot.generation = slot.generation.wrapping_add(1);
+            let is_exhausted = slot.generation == 0;
+            arena.ids.remove(&t);
+            if !is_exhausted {
+                arena.free.push(i as u32);
+            }
+        }
+    }
+    true
+}
+
+// NOTE: The memoized results depend on `lattice::atom_set_limit` too
+#[derive(PartialEq, Eq, Hash)]
+struct Key(Op, usize);
+
+struct Slot {
+    generation: u32,
+    entry: Option<(Arc<Type>, bool)>, // (type, is none)
+}
+
+struct Arena {
+    slots: Vec<Slot>,
+    ids: HashMap<Arc<Type>, TypeId>,
+    free: Vec<u32>,
+    roots: HashMap<u64, Vec<TypeId>>,
+    next_roots: u64,
+    memo: HashMap<Key, TypeId>,
+    subtypes: HashMap<(TypeId, TypeId, usize), bool>,
+}
+impl Arena {
+    fn new() -> Self {
+        let mut this = Arena {
+            slots: Vec::new(),
+            ids: HashMap::new(),
+            free: Vec::new(),
+            roots: HashMap::new(),
+            next_roots: 0,
+            memo: HashMap::new(),
+            subtypes: HashMap::new(),
+        };
+        this.insert(lattice::any());
+        this.insert(lattice::none());
+        this
+    }
+    fn insert(&mut self, t: Type) -> TypeId {
+        // NOTE: Another thread may have inserted `t` since the caller looked it up
+        if let Some(id) = self.ids.get(&t) {
+            return *id;
+        }
+        let is_none = lattice::is_none(&t);
+        let t = Arc::new(t);
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    entry: None,
+                });
+                self.slots.len() as u32 - 1
+            }
+        };
+        let slot = &mut self.slots[index as usize];
+        slot.entry = Some((t.clone(), is_none));
+        let id = TypeId {
+            index,
+            generation: slot.generation,
+        };
+        self.ids.insert(t, id);
+        id
+    }
+    fn entry(&self, id: TypeId) -> &(Arc<Type>, bool) {
+        let slot = &self.slots[id.index as usize];
+        match slot.entry {
+            Some(ref entry) if slot.generation == id.generation => entry,
+            _ => panic!("The type has been collected: {:?}", id),
+        }
+    }
+}
+
+fn arena() -> &'static RwLock<Arena> {
+    static ARENA: OnceLock<RwLock<Arena>> = OnceLock::new();
+    ARENA.get_or_init(|| RwLock::new(Arena::new()))
+}
+
+// NOTE: The lock is not held while `f` is computed,
+// and inexact results (see `lattice::track_exactness`) are not memoized
+fn memoize<F>(op: Op, f: F) -> TypeId
+    where F: FnOnce() -> Type
+{
+    let key = Key(op, lattice::atom_set_limit());
+    if let Some(id) = arena().read().unwrap().memo.get(&key) {
+        return *id;
+    }
+    let (t, is_exact) = lattice::track_exactness(f);
+    let id = TypeId::new(t);
+    if is_exact {
+        arena().write().unwrap().memo.insert(key, id);
+    }
+    id
+}
+
+fn to_types(ids: &[TypeId]) -> Vec<Type> {
+    ids.iter().map(|t| t.to_type()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+    use std::process;
+    use std::thread;
+    use std::time::Duration;
+    use ty;
+    use super::*;
+
+    // NOTE: A collection is deferred while the other tests are solving (see `Pin`)
+    fn collect() {
+        while !collect_garbage() {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn unique_type(name: &str) -> Type {
+        ty::atom(&format!("intern-test-{}-{}", process::id(), name))
+    }
+
+    #[test]
+    fn collected_ids_are_never_reused() {
+        let id = TypeId::new(unique_type("stale"));
+        collect();
+        assert!(panic::catch_unwind(|| id.get()).is_err());
+        let new_id = TypeId::new(unique_type("stale"));
+        assert_ne!(new_id, id);
+        assert_eq!(*new_id.get(), unique_type("stale"));
+        assert_eq!(TypeId::new(lattice::any()), TypeId::any());
+    }
+
+    #[test]
+    fn rooted_and_pinned_types_are_kept() {
+        let rooted = TypeId::new(unique_type("rooted"));
+        let roots = Roots::new(vec![rooted]);
+        collect();
+        assert_eq!(*rooted.get(), unique_type("rooted"));
+
+        let pin = pin();
+        let pinned = TypeId::new(unique_type("pinned"));
+        assert!(!collect_garbage());
+        assert_eq!(*pinned.get(), unique_type("pinned"));
+        drop(pin);
+
+        drop(roots);
+        collect();
+        assert!(panic::catch_unwind(|| rooted.get()).is_err());
+        assert!(panic::catch_unwind(|| pinned.get()).is_err());
+    }
+}
@@ -0,0 +1,396 @@
+//! A minimal JSON representation for machine-readable outputs and caches.
+//!
+//! `{}` formats a value compactly and `{:#}` formats it with indentation.
+//! Numbers are parsed as `Integer` if they have neither a fraction nor an exponent and fit in
+//! `i64`, and as `Float` otherwise.
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+use module::Result;
+
+#[derive(Debug, Clone)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+impl Json {
+    pub fn object(members: Vec<(&str, Json)>) -> Self {
+        Json::Object(members.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+    pub fn string<T: ToString>(value: T) -> Self {
+        Json::String(value.to_string())
+    }
+    pub fn strings<I, T>(values: I) -> Self
+        where I: IntoIterator<Item = T>,
+              T: ToString
+    {
+        Json::Array(values.into_iter().map(Json::string).collect())
+    }
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser { chars: text.chars().peekable() };
+        let value = parser.parse_value()?;
+        parser.skip_whitespaces();
+        if let Some(c) = parser.chars.next() {
+            return Err(format!("Unexpected character: {:?}", c).into());
+        }
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        if let Json::Object(ref members) = *self {
+            members.iter().find(|m| m.0 == key).map(|m| &m.1)
+        } else {
+            None
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        if let Json::Bool(x) = *self { Some(x) } else { None }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        if let Json::Integer(x) = *self { Some(x) } else { None }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Integer(x) => Some(x as f64),
+            Json::Float(x) => Some(x),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        if let Json::String(ref x) = *self { Some(x) } else { None }
+    }
+    pub fn as_array(&self) -> Option<&[Json]> {
+        if let Json::Array(ref x) = *self { Some(x) } else { None }
+    }
+    pub fn is_null(&self) -> bool {
+        matches!(*self, Json::Null)
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, indent: Option<usize>) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(x) => write!(f, "{}", x),
+            Json::Integer(x) => write!(f, "{}", x),
+            // NOTE: `{:?}` keeps the fraction of integral values (e.g., `1.0`)
+            Json::Float(x) if x.is_finite() => write!(f, "{:?}", x),
+            Json::Float(_) => write!(f, "null"),
+            Json::String(ref x) => write_escaped_str(f, x),
+            Json::Array(ref xs) => {
+                if xs.is_empty() {
+                    return write!(f, "[]");
+                }
+                write!(f, "[")?;
+                for (i, x) in xs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_newline(f, indent.map(|n| n + 1))?;
+                    x.write(f, indent.map(|n| n + 1))?;
+                }
+                write_newline(f, indent)?;
+                write!(f, "]")
+            }
+            Json::Object(ref members) => {
+                if members.is_empty() {
+                    return write!(f, "{{}}");
+                }
+                write!(f, "{{")?;
+                for (i, (k, v)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_newline(f, indent.map(|n| n + 1))?;
+                    write_escaped_str(f, k)?;
+                    write!(f, ":")?;
+                    if indent.is_some() {
+                        write!(f, " ")?;
+                    }
+                    v.write(f, indent.map(|n| n + 1))?;
+                }
+                write_newline(f, indent)?;
+                write!(f, "}}")
+            }
+        }
+    }
+}
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let indent = if f.alternate() { Some(0) } else { None };
+        self.write(f, indent)
+    }
+}
+impl From<bool> for Json {
+    fn from(x: bool) -> Self {
+        Json::Bool(x)
+    }
+}
+impl From<i64> for Json {
+    fn from(x: i64) -> Self {
+        Json::Integer(x)
+    }
+}
+impl From<f64> for Json {
+    fn from(x: f64) -> Self {
+        Json::Float(x)
+    }
+}
+impl From<usize> for Json {
+    fn from(x: usize) -> Self {
+        Json::Integer(x as i64)
+    }
+}
+impl From<String> for Json {
+    fn from(x: String) -> Self {
+        Json::String(x)
+    }
+}
+impl<'a> From<&'a str> for Json {
+    fn from(x: &'a str) -> Self {
+        Json::String(x.to_string())
+    }
+}
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(x: Option<T>) -> Self {
+        x.map_or(Json::Null, Into::into)
+    }
+}
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(x: Vec<T>) -> Self {
+        Json::Array(x.into_iter().map(Into::into).collect())
+    }
+}
+
+fn write_newline(f: &mut fmt::Formatter, indent: Option<usize>) -> fmt::Result {
+    if let Some(n) = indent {
+        write!(f, "\n{:1$}", "", n * 2)?;
+    }
+    Ok(())
+}
+
+fn write_escaped_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+impl<'a> Parser<'a> {
+    fn skip_whitespaces(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespaces();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("Expected {:?}, but got {:?}", expected, c).into()),
+            None => Err(format!("Expected {:?}, but reached EOF", expected).into()),
+        }
+    }
+    fn expect_keyword(&mut self, keyword: &str, value: Json) -> Result<Json> {
+        for expected in keyword.chars() {
+            if self.chars.next() != Some(expected) {
+                return Err(format!("Expected {:?}", keyword).into());
+            }
+        }
+        Ok(value)
+    }
+    fn parse_value(&mut self) -> Result<Json> {
+        self.skip_whitespaces();
+        match self.chars.peek().cloned() {
+            Some('n') => self.expect_keyword("null", Json::Null),
+            Some('t') => self.expect_keyword("true", Json::Bool(true)),
+            Some('f') => self.expect_keyword("false", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.parse_string()?)),
+            Some('[') => {
+                self.chars.next();
+                let mut values = Vec::new();
+                self.skip_whitespaces();
+                if self.chars.peek() == Some(&']') {
+                    self.chars.next();
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.parse_value()?);
+                    self.skip_whitespaces();
+                    match self.chars.next() {
+                        Some(',') => {}
+                        Some(']') => return Ok(Json::Array(values)),
+                        Some(c) => return Err(format!("Unexpected character: {:?}", c).into()),
+                        None => return Err("Unexpected EOF".into()),
+                    }
+                }
+            }
+            Some('{') => {
+                self.chars.next();
+                let mut members = Vec::new();
+                self.skip_whitespaces();
+                if self.chars.peek() == Some(&'}') {
+                    self.chars.next();
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespaces();
+                    let key = self.parse_string()?;
+                    self.expect(':')?;
+                    members.push((key, self.parse_value()?));
+                    self.skip_whitespaces();
+                    match self.chars.next() {
+                        Some(',') => {}
+                        Some('}') => return Ok(Json::Object(members)),
+                        Some(c) => return Err(format!("Unexpected character: {:?}", c).into()),
+                        None => return Err("Unexpected EOF".into()),
+                    }
+                }
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) => Err(format!("Unexpected character: {:?}", c).into()),
+            None => Err("Unexpected EOF".into()),
+        }
+    }
+    // NOTE: number = ["-"] ("0" | [1-9] digit*) ["." digit+] [("e" | "E") ["+" | "-"] digit+]
+    fn parse_number(&mut self) -> Result<Json> {
+        let mut text = String::new();
+        self.take_if(&mut text, |c| c == '-');
+        if !self.take_if(&mut text, |c| c == '0') && self.take_digits(&mut text) == 0 {
+            return Err(format!("Invalid number: {:?}", text).into());
+        }
+        let mut is_integer = true;
+        if self.take_if(&mut text, |c| c == '.') {
+            is_integer = false;
+            if self.take_digits(&mut text) == 0 {
+                return Err(format!("Invalid number: {:?}", text).into());
+            }
+        }
+        if self.take_if(&mut text, |c| c == 'e' || c == 'E') {
+            is_integer = false;
+            self.take_if(&mut text, |c| c == '+' || c == '-');
+            if self.take_digits(&mut text) == 0 {
+                return Err(format!("Invalid number: {:?}", text).into());
+            }
+        }
+        if is_integer {
+            if let Ok(value) = text.parse() {
+                return Ok(Json::Integer(value));
+            }
+        }
+        let value = text.parse().map_err(|_| format!("Invalid number: {:?}", text))?;
+        Ok(Json::Float(value))
+    }
+    fn take_if<F: Fn(char) -> bool>(&mut self, text: &mut String, pred: F) -> bool {
+        match self.chars.peek().cloned() {
+            Some(c) if pred(c) => {
+                text.push(c);
+                self.chars.next();
+                true
+            }
+            _ => false,
+        }
+    }
+    fn take_digits(&mut self, text: &mut String) -> usize {
+        let mut count = 0;
+        while self.take_if(text, |c| c.is_ascii_digit()) {
+            count += 1;
+        }
+        count
+    }
+    fn parse_hex4(&mut self) -> Result<u32> {
+        let hex = (0..4).filter_map(|_| self.chars.next()).collect::<String>();
+        u32::from_str_radix(&hex, 16).map_err(|_| format!("Invalid escape: \\u{}", hex).into())
+    }
+    fn parse_string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.chars.next() {
+                Some('\\') => {
+                    match self.chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some('r') => value.push('\r'),
+                        Some('t') => value.push('\t'),
+                        Some('b') => value.push('\u{8}'),
+                        Some('f') => value.push('\u{c}'),
+                        Some('u') => {
+                            let mut code = self.parse_hex4()?;
+                            // NOTE: A high surrogate is followed by `\u` and a low surrogate
+                            if (0xD800..0xDC00).contains(&code) {
+                                let low = match (self.chars.next(), self.chars.next()) {
+                                    (Some('\\'), Some('u')) => self.parse_hex4()?,
+                                    _ => return Err("Unpaired surrogate".into()),
+                                };
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err("Unpaired surrogate".into());
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            let c = ::std::char::from_u32(code)
+                                .ok_or_else(|| format!("Invalid escape: \\u{:04x}", code))?;
+                            value.push(c);
+                        }
+                        Some(c) => value.push(c),
+                        None => return Err("Unexpected EOF".into()),
+                    }
+                }
+                Some('"') => return Ok(value),
+                Some(c) => value.push(c),
+                None => return Err("Unexpected EOF".into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> String {
+        Json::parse(text).map(|j| j.to_string()).unwrap_or_else(|e| format!("error: {}", e))
+    }
+
+    #[test]
+    fn numbers_are_parsed() {
+        assert_eq!(parse("[0, -12, 9223372036854775807]"), "[0,-12,9223372036854775807]");
+        assert_eq!(parse("[1.5, -0.25, 1.0, 2e3, 1E-2, 5e+1]"), "[1.5,-0.25,1.0,2000.0,0.01,50.0]");
+        assert_eq!(parse("9223372036854775808"), "9.223372036854776e18");
+        assert!(Json::parse("1.5").unwrap().as_i64().is_none());
+        assert_eq!(Json::parse("1.5").unwrap().as_f64(), Some(1.5));
+        assert_eq!(Json::parse("3").unwrap().as_f64(), Some(3.0));
+        let message = Json::parse(r#"{"id": 1, "progress": 0.5}"#).unwrap();
+        assert_eq!(message.get("id").and_then(Json::as_i64), Some(1));
+    }
+
+    #[test]
+    fn malformed_numbers_are_rejected() {
+        for text in &["-", "01", "1.", ".5", "1e", "1e+", "--1", "1.2.3"] {
+            assert!(Json::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn unicode_escapes_are_parsed() {
+        assert_eq!(Json::parse(r#""a\u00e9\n""#).unwrap().as_str(), Some("a\u{e9}\n"));
+        assert_eq!(Json::parse(r#""\ud83d\ude00""#).unwrap().as_str(), Some("\u{1f600}"));
+        assert!(Json::parse(r#""\ud83d""#).is_err());
+        assert!(Json::parse(r#""\ud83dx""#).is_err());
+        assert!(Json::parse(r#""\ud83d\u0041""#).is_err());
+        assert!(Json::parse(r#""\ude00""#).is_err());
+    }
+}
@@ -0,0 +1,1017 @@
+//! Lattice operations (join, meet, subtyping and widening) over concrete types.
+//!
+//! The operations expect types whose built-in, user-defined and remote types have been
+//! expanded by `resolve::Resolver`; the remaining unexpanded types are treated as `any()`.
+//!
+//! Recursive types (`ty::RecType`) are equivalent to their unfoldings and unfolded on demand.
+//! Operations which come back to themselves by unfolding are resolved coinductively
+//! (see `coinductive` and `is_subtype`).
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::cmp;
+use std::slice;
+use std::collections::BTreeSet;
+use std::sync::atomic::AtomicUsize;
+use std::sync::OnceLock;
+use std::sync::atomic::Ordering;
+use num::BigInt;
+use resolve;
+use ty;
+use ty::Type;
+use ty::ListType;
+
+/// Unions of more atoms than this are widened to `atom()` (see `set_atom_set_limit`).
+pub const DEFAULT_ATOM_SET_LIMIT: usize = 13;
+
+static ATOM_SET_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_ATOM_SET_LIMIT);
+
+/// Types nested deeper than this are widened to `any()` by `widen`.
+pub const DEPTH_LIMIT: usize = 5;
+
+/// Operations on recursive types nested deeper than this are approximated
+/// (by `any()`, or by `false` for `is_subtype`).
+pub const MAX_RECURSION_DEPTH: usize = 32;
+
+/// Integer bounds are widened to the bounds of these built-in types (or to infinity).
+pub const WIDENING_THRESHOLD_TYPES: &[&str] = &["neg_integer",
+                                                "non_neg_integer",
+                                                "pos_integer",
+                                                "byte",
+                                                "arity",
+                                                "char"];
+
+/// Sets the maximum number of atoms in a union (for the whole process).
+///
+/// It should be set before the analysis; types computed with another limit are not re-widened.
+pub fn set_atom_set_limit(limit: usize) {
+    ATOM_SET_LIMIT.store(limit, Ordering::Relaxed);
+}
+pub fn atom_set_limit() -> usize {
+    ATOM_SET_LIMIT.load(Ordering::Relaxed)
+}
+
+thread_local! {
+    static IN_PROGRESS: RefCell<Vec<Frame>> = const { RefCell::new(Vec::new()) };
+    static ASSUMED_SUBTYPES: RefCell<Vec<(Type, Type)>> = const { RefCell::new(Vec::new()) };
+    static UNFOLDING: RefCell<Vec<Type>> = const { RefCell::new(Vec::new()) };
+    static CUT_OFFS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Calls `f` and returns its result with `true` if the result is exact, so it can be cached.
+///
+/// A result is inexact if `f` is called inside of an operation on recursive types
+/// (it may refer to or assume the outer operation), or if `f` reaches `MAX_RECURSION_DEPTH`.
+pub fn track_exactness<F, T>(f: F) -> (T, bool)
+    where F: FnOnce() -> T
+{
+    let is_nested = IN_PROGRESS.with(|frames| !frames.borrow().is_empty()) ||
+                    ASSUMED_SUBTYPES.with(|assumed| !assumed.borrow().is_empty()) ||
+                    UNFOLDING.with(|unfolding| !unfolding.borrow().is_empty());
+    let cut_offs = CUT_OFFS.with(Cell::get);
+    let x = f();
+    (x, !is_nested && CUT_OFFS.with(Cell::get) == cut_offs)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecOp {
+    Join,
+    Inf,
+    Widen,
+}
+
+struct Frame {
+    op: RecOp,
+    operands: Vec<Type>,
+    var: String,
+    is_referred: bool,
+}
+
+pub fn any() -> Type {
+    From::from(ty::AnyType)
+}
+pub fn none() -> Type {
+    From::from(ty::NoneType)
+}
+pub fn is_none(t: &Type) -> bool {
+    members(t).is_empty()
+}
+pub fn is_any(t: &Type) -> bool {
+    members(t).iter().any(|m| matches!(*m, Type::Any(_)))
+}
+
+/// Returns `number()` (integers and floats are distinct values, e.g., `1 =/= 1.0`).
+pub fn number() -> Type {
+    sup(&From::from(ty::integer()), &From::from(ty::FloatType))
+}
+
+/// Returns the integer range (`None` if there are no integers) of `t`
+/// and whether `t` includes floats.
+pub fn numeric_parts(t: &Type) -> (Option<ty::IntegerType>, bool) {
+    let mut integers = Vec::new();
+    let mut float = false;
+    for m in members(t) {
+        match m {
+            Type::Integer(_) => integers.push(m),
+            Type::Float(_) => float = true,
+            Type::Any(_) => {
+                integers.push(From::from(ty::integer()));
+                float = true;
+            }
+            _ => {}
+        }
+    }
+    match join(&integers) {
+        Type::Integer(x) => (Some(*x), float),
+        _ => (None, float),
+    }
+}
+
+/// Returns the least upper bound of `a` and `b`.
+pub fn sup(a: &Type, b: &Type) -> Type {
+    join(&[a.clone(), b.clone()])
+}
+
+/// Returns the least upper bound of `types` (`none()` if it is empty).
+pub fn join(types: &[Type]) -> Type {
+    // NOTE: A recursive type joined only with itself is kept folded
+    if let Some(t @ Type::Rec(_)) = types.first() {
+        if types.iter().all(|u| u == t) {
+            return t.clone();
+        }
+    }
+    coinductive(RecOp::Join, types, || {
+        let mut union = Union::default();
+        for t in types {
+            for m in members(t) {
+                union.add(m);
+            }
+        }
+        union.into_type()
+    })
+}
+
+/// Returns (an upper bound of) the greatest lower bound of `a` and `b`.
+pub fn inf(a: &Type, b: &Type) -> Type {
+    // NOTE: A recursive type contained in the other one is kept folded
+    match (a, b) {
+        (&Type::Rec(_), _) if is_subtype(a, b) => return a.clone(),
+        (_, &Type::Rec(_)) if is_subtype(b, a) => return b.clone(),
+        _ => {}
+    }
+    coinductive(RecOp::Inf, &[a.clone(), b.clone()], || {
+        let ys = members(b);
+        let mut result = Vec::new();
+        for x in members(a) {
+            for y in &ys {
+                if let Some(t) = meet_member(&x, y) {
+                    result.push(t);
+                }
+            }
+        }
+        join(&result)
+    })
+}
+
+/// Returns `true` if every value of `a` is a value of `b`.
+///
+/// Pairs of recursive types are compared coinductively
+/// (i.e., a pair which recurs while it is being compared is assumed to be a subtype).
+pub fn is_subtype(a: &Type, b: &Type) -> bool {
+    let is_member_wise_subtype = || {
+        let ys = members(&join(slice::from_ref(b)));
+        members(&join(slice::from_ref(a)))
+            .iter()
+            .all(|x| ys.iter().any(|y| is_member_subtype(x, y)))
+    };
+    if !has_rec(a) && !has_rec(b) {
+        return is_member_wise_subtype();
+    }
+    let (is_assumed, depth) = ASSUMED_SUBTYPES.with(|assumed| {
+        let assumed = assumed.borrow();
+        (assumed.iter().any(|(x, y)| x == a && y == b), assumed.len())
+    });
+    if is_assumed {
+        return true;
+    }
+    if depth >= MAX_RECURSION_DEPTH {
+        CUT_OFFS.with(|n| n.set(n.get() + 1));
+        return false;
+    }
+    ASSUMED_SUBTYPES.with(|assumed| assumed.borrow_mut().push((a.clone(), b.clone())));
+    let result = is_member_wise_subtype();
+    ASSUMED_SUBTYPES.with(|assumed| assumed.borrow_mut().pop());
+    result
+}
+
+/// Returns `true` if `a` and `b` denote the same set of values.
+///
+/// This is the semantic equality (e.g., `1..2` and `1|2` are equivalent), while `==` compares
+/// types structurally.
+pub fn is_equivalent(a: &Type, b: &Type) -> bool {
+    a == b || (is_subtype(a, b) && is_subtype(b, a))
+}
+
+/// Joins `old` and `new` so that repeated widening reaches a fixpoint in finite steps.
+///
+/// Integer bounds which moved are widened to the next threshold (see `WIDENING_THRESHOLD_TYPES`)
+/// or to infinity, and deeply nested types to `any()`.
+pub fn widen(old: &Type, new: &Type) -> Type {
+    coinductive(RecOp::Widen, &[old.clone(), new.clone()], || {
+        // NOTE: Members are widened against the new ones (not the joined ones)
+        // so that the widening of recursive types reaches the same operands again
+        let (olds, news) = (members(old), members(new));
+        let joined = members(&sup(old, new))
+            .into_iter()
+            .map(|m| {
+                let o = olds.iter().find(|o| is_same_shape(o, &m));
+                let n = news.iter().find(|n| is_same_shape(n, &m));
+                match (o, n) {
+                    (Some(o), Some(n)) => widen_member(o, n),
+                    _ => m,
+                }
+            })
+            .collect::<Vec<_>>();
+        limit_depth(&join(&joined), DEPTH_LIMIT)
+    })
+}
+
+/// Returns the type of `[Head | Tail]`.
+pub fn cons(head: &Type, tail: &Type) -> Type {
+    let lists = members(tail)
+        .into_iter()
+        .map(|m| match m {
+            Type::Nil(_) => list(true, head.clone(), m),
+            Type::List(ref l) => {
+                let l = ListParts::from_list(l);
+                list(true, sup(head, &l.element), l.last)
+            }
+            Type::Any(_) => list(true, head.clone(), any()),
+            m => list(true, head.clone(), m),
+        })
+        .collect::<Vec<_>>();
+    join(&lists)
+}
+
+/// Returns the types of the head and the tail of a non-empty list of type `t`.
+pub fn uncons(t: &Type) -> (Type, Type) {
+    let mut heads = Vec::new();
+    let mut tails = Vec::new();
+    for m in members(t) {
+        match m {
+            Type::List(ref l) => {
+                let l = ListParts::from_list(l);
+                heads.push(l.element.clone());
+                tails.push(list(false, l.element, l.last));
+            }
+            Type::Any(_) => {
+                heads.push(any());
+                tails.push(any());
+            }
+            _ => {}
+        }
+    }
+    (join(&heads), join(&tails))
+}
+
+/// Returns the join of the element types of the lists in `t`.
+pub fn list_element(t: &Type) -> Type {
+    let elements = members(t)
+        .into_iter()
+        .filter_map(|m| match m {
+            Type::List(ref l) => Some(ListParts::from_list(l).element),
+            Type::Any(_) => Some(any()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    join(&elements)
+}
+
+/// Returns the types of the elements of the `arity`-tuples in `t`.
+pub fn tuple_elements(t: &Type, arity: usize) -> Vec<Type> {
+    let mut elements = vec![Vec::new(); arity];
+    for m in members(t) {
+        match m {
+            Type::Tuple(ref x) => {
+                match x.elements {
+                    Some(ref es) if es.len() == arity => {
+                        for (i, e) in es.iter().enumerate() {
+                            elements[i].push(e.clone());
+                        }
+                    }
+                    Some(_) => {}
+                    None => elements.iter_mut().for_each(|es| es.push(any())),
+                }
+            }
+            Type::Any(_) => elements.iter_mut().for_each(|es| es.push(any())),
+            _ => {}
+        }
+    }
+    elements.iter().map(|es| join(es)).collect()
+}
+
+pub fn tuple(elements: Vec<Type>) -> Type {
+    if elements.iter().any(is_none) {
+        none()
+    } else {
+        From::from(ty::TupleType { elements: Some(elements) })
+    }
+}
+
+pub fn list(nonempty: bool, element: Type, last: Type) -> Type {
+    ListParts {
+            nonempty,
+            element,
+            last,
+        }
+        .into_type()
+}
+
+/// Returns the concrete members of the (possibly nested) union `t`.
+///
+/// `none()` has no members and tuples containing `none()` are dropped.
+fn members(t: &Type) -> Vec<Type> {
+    let mut result = Vec::new();
+    collect_members(t, &mut result);
+    result
+}
+fn collect_members(t: &Type, acc: &mut Vec<Type>) {
+    match *t {
+        Type::None(_) => {}
+        Type::Union(ref x) => {
+            for t in &x.types {
+                collect_members(t, acc);
+            }
+        }
+        Type::Var(ref x) => {
+            if let Some(ref value) = x.value {
+                collect_members(value, acc);
+            } else {
+                acc.push(any());
+            }
+        }
+        Type::UserDefined(ref x) => collect_members(&x.body, acc),
+        Type::Rec(ref x) => {
+            // NOTE: A recursive type which recurs while it is unfolded is regarded as inhabited
+            // (such members are observed only by the `is_none` checks below)
+            if UNFOLDING.with(|unfolding| unfolding.borrow().contains(t)) {
+                acc.push(t.clone());
+            } else {
+                UNFOLDING.with(|unfolding| unfolding.borrow_mut().push(t.clone()));
+                collect_members(&x.unfold(), acc);
+                UNFOLDING.with(|unfolding| unfolding.borrow_mut().pop());
+            }
+        }
+        Type::Tuple(ref x) => {
+            if x.elements.as_ref().is_none_or(|es| !es.iter().any(is_none)) {
+                acc.push(t.clone());
+            }
+        }
+        Type::List(ref x) => {
+            let l = ListParts::from_list(x);
+            if is_none(&l.element) || is_none(&l.last) {
+                if !l.nonempty {
+                    acc.push(From::from(ty::NilType));
+                }
+            } else {
+                acc.push(t.clone());
+            }
+        }
+        Type::Record(_) => acc.push(From::from(ty::TupleType::any())),
+        Type::Local(_) | Type::Remote(_) => acc.push(any()),
+        _ => acc.push(t.clone()),
+    }
+}
+
+// NOTE: The order of the members of unions follows the term order of Erlang
+fn rank(t: &Type) -> u8 {
+    match *t {
+        Type::Integer(_) => 0,
+        Type::Float(_) => 1,
+        Type::Atom(_) => 2,
+        Type::Reference(_) => 3,
+        Type::Fun(_) => 4,
+        Type::Port(_) => 5,
+        Type::Pid(_) => 6,
+        Type::Tuple(_) => 7,
+        Type::Map(_) => 8,
+        Type::Nil(_) => 9,
+        Type::List(_) => 10,
+        Type::Bitstring(_) => 11,
+        _ => 12,
+    }
+}
+
+#[derive(Default)]
+struct Union {
+    any: bool,
+    integer: Option<ty::IntegerType>,
+    float: bool,
+    any_atom: bool,
+    atoms: BTreeSet<String>,
+    reference: bool,
+    funs: Vec<ty::FunType>,
+    port: bool,
+    pid: bool,
+    tuples: Vec<ty::TupleType>,
+    any_tuple: bool,
+    maps: Vec<ty::MapType>,
+    any_map: bool,
+    nil: bool,
+    list: Option<ListParts>,
+    bitstring: Option<(usize, usize)>, // (bits, align)
+}
+impl Union {
+    fn add(&mut self, t: Type) {
+        match t {
+            Type::Any(_) => self.any = true,
+            Type::Integer(x) => {
+                self.integer = Some(match self.integer.take() {
+                    None => *x,
+                    Some(i) => {
+                        ty::IntegerType {
+                            min: lower_min(&i.min, &x.min),
+                            max: upper_max(&i.max, &x.max),
+                        }
+                    }
+                });
+            }
+            Type::Float(_) => self.float = true,
+            Type::Atom(x) => {
+                match x.value {
+                    Some(name) => {
+                        self.atoms.insert(name);
+                    }
+                    None => self.any_atom = true,
+                }
+            }
+            Type::Reference(_) => self.reference = true,
+            Type::Port(_) => self.port = true,
+            Type::Pid(_) => self.pid = true,
+            Type::Nil(_) => self.nil = true,
+            Type::Fun(x) => self.add_fun(*x),
+            Type::Tuple(x) => self.add_tuple(*x),
+            Type::Map(x) => {
+                if x.pairs.is_empty() {
+                    self.any_map = true;
+                } else if !self.maps.iter().any(|m| is_map_equivalent(m, &x)) {
+                    self.maps.push(*x);
+                }
+            }
+            Type::List(x) => {
+                let x = ListParts::from_list(&x);
+                self.list = Some(match self.list.take() {
+                    None => x,
+                    Some(l) => {
+                        ListParts {
+                            nonempty: l.nonempty && x.nonempty,
+                            element: sup(&l.element, &x.element),
+                            last: sup(&l.last, &x.last),
+                        }
+                    }
+                });
+            }
+            Type::Bitstring(x) => {
+                let (bits, align) = (x.bits, x.align);
+                self.bitstring = Some(match self.bitstring {
+                    None => (bits, align),
+                    Some((b, a)) => {
+                        (cmp::min(b, bits), gcd(gcd(a, align), b.abs_diff(bits)))
+                    }
+                });
+            }
+            _ => self.any = true,
+        }
+    }
+    fn add_fun(&mut self, x: ty::FunType) {
+        if x.clauses.is_empty() {
+            self.funs = vec![x];
+            return;
+        }
+        if self.funs.len() == 1 && self.funs[0].clauses.is_empty() {
+            return;
+        }
+        let arity = fun_arity(&x);
+        if let Some(f) = self.funs.iter_mut().find(|f| fun_arity(f) == arity) {
+            let (a, b) = (&f.clauses[0], &x.clauses[0]);
+            let args = match (a.args.as_ref(), b.args.as_ref()) {
+                (Some(a), Some(b)) => Some(a.iter().zip(b).map(|(a, b)| inf(a, b)).collect()),
+                _ => None,
+            };
+            let return_type = sup(&a.return_type, &b.return_type);
+            f.clauses = vec![ty::FunSpec { args, return_type }];
+            return;
+        }
+        if arity.is_none() {
+            self.funs.retain(|f| fun_arity(f).is_none());
+        }
+        self.funs.push(ty::FunType { clauses: vec![x.clauses[0].clone()] });
+    }
+    fn add_tuple(&mut self, x: ty::TupleType) {
+        let elements = match x.elements {
+            None => {
+                self.any_tuple = true;
+                return;
+            }
+            Some(elements) => elements,
+        };
+        let tag = tuple_tag(&elements);
+        let found = self.tuples.iter_mut().find(|t| {
+            let es = t.elements.as_ref().unwrap();
+            es.len() == elements.len() && tuple_tag(es) == tag
+        });
+        if let Some(t) = found {
+            let es = t.elements.as_mut().unwrap();
+            for (e, x) in es.iter_mut().zip(elements) {
+                *e = sup(e, &x);
+            }
+        } else {
+            self.tuples.push(ty::TupleType { elements: Some(elements) });
+        }
+    }
+    fn into_type(self) -> Type {
+        if self.any {
+            return any();
+        }
+        let mut types = Vec::new();
+        if let Some(i) = self.integer {
+            types.push(From::from(i));
+        }
+        if self.float {
+            types.push(From::from(ty::FloatType));
+        }
+        if self.any_atom || self.atoms.len() > atom_set_limit() {
+            types.push(From::from(ty::AtomType::any()));
+        } else {
+            types.extend(self.atoms.iter().map(|a| ty::atom(a)));
+        }
+        if self.reference {
+            types.push(From::from(ty::ReferenceType));
+        }
+        types.extend(self.funs.into_iter().map(From::from));
+        if self.port {
+            types.push(From::from(ty::PortType));
+        }
+        if self.pid {
+            types.push(From::from(ty::PidType));
+        }
+        if self.any_tuple {
+            types.push(From::from(ty::TupleType::any()));
+        } else {
+            let mut tuples = self.tuples;
+            tuples.sort_by_key(|t| t.elements.as_ref().map(|es| es.len()));
+            types.extend(tuples.into_iter().map(From::from));
+        }
+        if self.any_map {
+            types.push(From::from(ty::MapType::any()));
+        } else {
+            types.extend(self.maps.into_iter().map(From::from));
+        }
+        match self.list {
+            None if self.nil => types.push(From::from(ty::NilType)),
+            None => {}
+            Some(mut l) => {
+                // NOTE: `[] | nonempty_list(T)` is `list(T)`
+                l.nonempty = l.nonempty && !self.nil;
+                types.push(l.into_type());
+            }
+        }
+        if let Some((bits, align)) = self.bitstring {
+            types.push(From::from(ty::BitstringType { bits, align }));
+        }
+        match types.len() {
+            0 => none(),
+            1 => types.pop().unwrap(),
+            _ => From::from(ty::UnionType::new(types)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ListParts {
+    nonempty: bool,
+    element: Type,
+    last: Type, // `[]` means "proper"
+}
+impl ListParts {
+    fn from_list(l: &ListType) -> Self {
+        let nil = || From::from(ty::NilType);
+        match *l {
+            ListType::Proper(ref x) => ListParts::new(false, x.element.clone(), nil()),
+            ListType::NonEmpty(ref x) => ListParts::new(true, x.element.clone(), nil()),
+            ListType::MaybeImproper(ref x) => {
+                ListParts::new(false, x.element.clone(), x.last.clone())
+            }
+            ListType::NonEmptyImproper(ref x) => {
+                ListParts::new(true, x.element.clone(), x.last.clone())
+            }
+        }
+    }
+    fn new(nonempty: bool, element: Type, last: Type) -> Self {
+        ListParts {
+            nonempty,
+            element,
+            last,
+        }
+    }
+    fn into_type(self) -> Type {
+        if is_none(&self.element) || is_none(&self.last) {
+            return if self.nonempty { none() } else { From::from(ty::NilType) };
+        }
+        let is_proper = members(&self.last).iter().all(|m| matches!(*m, Type::Nil(_)));
+        let list = match (self.nonempty, is_proper) {
+            (false, true) => ListType::Proper(ty::ProperListType { element: self.element }),
+            (true, true) => ListType::NonEmpty(ty::NonEmptyListType { element: self.element }),
+            (false, false) => {
+                ListType::MaybeImproper(ty::MaybeImproperListType {
+                    element: self.element,
+                    last: self.last,
+                })
+            }
+            (true, false) => {
+                ListType::NonEmptyImproper(ty::NonEmptyImproperListType {
+                    element: self.element,
+                    last: self.last,
+                })
+            }
+        };
+        From::from(list)
+    }
+}
+
+fn meet_member(x: &Type, y: &Type) -> Option<Type> {
+    match (x, y) {
+        (Type::Any(_), _) => Some(y.clone()),
+        (_, Type::Any(_)) => Some(x.clone()),
+        (Type::Integer(a), Type::Integer(b)) => {
+            let min = lower_max(&a.min, &b.min);
+            let max = upper_min(&a.max, &b.max);
+            match (min.as_ref(), max.as_ref()) {
+                (Some(min), Some(max)) if min > max => None,
+                _ => Some(From::from(ty::IntegerType { min, max })),
+            }
+        }
+        (Type::Atom(a), Type::Atom(b)) => {
+            match (a.value.as_ref(), b.value.as_ref()) {
+                (None, _) => Some(y.clone()),
+                (_, None) => Some(x.clone()),
+                (Some(a), Some(b)) if a == b => Some(x.clone()),
+                _ => None,
+            }
+        }
+        (Type::Float(_), Type::Float(_)) |
+        (Type::Reference(_), Type::Reference(_)) |
+        (Type::Port(_), Type::Port(_)) |
+        (Type::Pid(_), Type::Pid(_)) |
+        (Type::Nil(_), Type::Nil(_)) => Some(x.clone()),
+        (Type::Nil(_), Type::List(l)) |
+        (Type::List(l), Type::Nil(_)) => {
+            if ListParts::from_list(l).nonempty {
+                None
+            } else {
+                Some(From::from(ty::NilType))
+            }
+        }
+        (Type::List(a), Type::List(b)) => {
+            let (a, b) = (ListParts::from_list(a), ListParts::from_list(b));
+            let t = list(a.nonempty || b.nonempty,
+                         inf(&a.element, &b.element),
+                         inf(&a.last, &b.last));
+            if is_none(&t) { None } else { Some(t) }
+        }
+        (Type::Tuple(a), Type::Tuple(b)) => {
+            match (a.elements.as_ref(), b.elements.as_ref()) {
+                (None, _) => Some(y.clone()),
+                (_, None) => Some(x.clone()),
+                (Some(a), Some(b)) if a.len() == b.len() => {
+                    let t = tuple(a.iter().zip(b).map(|(a, b)| inf(a, b)).collect());
+                    if is_none(&t) { None } else { Some(t) }
+                }
+                _ => None,
+            }
+        }
+        (Type::Map(a), Type::Map(_)) => {
+            // TODO: Intersect the associations
+            if a.pairs.is_empty() {
+                Some(y.clone())
+            } else {
+                Some(x.clone())
+            }
+        }
+        (Type::Fun(a), Type::Fun(b)) => {
+            match (fun_arity(a), fun_arity(b)) {
+                (Some(m), Some(n)) if m != n => None,
+                _ if a.clauses.is_empty() => Some(y.clone()),
+                _ if b.clauses.is_empty() => Some(x.clone()),
+                _ => {
+                    let (a, b) = (&a.clauses[0], &b.clauses[0]);
+                    let args = match (a.args.as_ref(), b.args.as_ref()) {
+                        (Some(a), Some(b)) => {
+                            Some(a.iter().zip(b).map(|(a, b)| sup(a, b)).collect())
+                        }
+                        (Some(a), None) => Some(a.clone()),
+                        (None, b) => b.cloned(),
+                    };
+                    let return_type = inf(&a.return_type, &b.return_type);
+                    let clause = ty::FunSpec { args, return_type };
+                    Some(From::from(ty::FunType { clauses: vec![clause] }))
+                }
+            }
+        }
+        (Type::Bitstring(a), Type::Bitstring(b)) => meet_bitstring(a, b),
+        _ => None,
+    }
+}
+
+// NOTE: The sizes of `<<_:B, _:_*A>>` are `B, B + A, B + 2A, ...`
+fn meet_bitstring(a: &ty::BitstringType, b: &ty::BitstringType) -> Option<Type> {
+    let (b1, a1) = (a.bits, a.align);
+    let (b2, a2) = (b.bits, b.align);
+    let common = if a1 == 0 {
+        Some(b1).filter(|&s| has_bit_size(s, b2, a2))
+    } else if a2 == 0 {
+        Some(b2).filter(|&s| has_bit_size(s, b1, a1))
+    } else {
+        let start = if b1 >= b2 { 0 } else { (b2 - b1).div_ceil(a1) };
+        (start..start + a2).map(|n| b1 + n * a1).find(|&s| has_bit_size(s, b2, a2))
+    };
+    common.map(|bits| {
+        let align = if a1 == 0 || a2 == 0 { 0 } else { a1 / gcd(a1, a2) * a2 };
+        From::from(ty::BitstringType { bits, align })
+    })
+}
+fn has_bit_size(size: usize, bits: usize, align: usize) -> bool {
+    size >= bits && if align == 0 { size == bits } else { (size - bits).is_multiple_of(align) }
+}
+
+fn is_member_subtype(x: &Type, y: &Type) -> bool {
+    match (x, y) {
+        (_, Type::Any(_)) => true,
+        (Type::Any(_), _) => false,
+        (Type::Integer(a), Type::Integer(b)) => {
+            lower_max(&a.min, &b.min) == a.min && upper_min(&a.max, &b.max) == a.max
+        }
+        (Type::Atom(a), Type::Atom(b)) => b.value.is_none() || a.value == b.value,
+        (Type::Float(_), Type::Float(_)) |
+        (Type::Reference(_), Type::Reference(_)) |
+        (Type::Port(_), Type::Port(_)) |
+        (Type::Pid(_), Type::Pid(_)) |
+        (Type::Nil(_), Type::Nil(_)) => true,
+        (Type::Nil(_), Type::List(l)) => !ListParts::from_list(l).nonempty,
+        (Type::List(a), Type::List(b)) => {
+            let (a, b) = (ListParts::from_list(a), ListParts::from_list(b));
+            (a.nonempty || !b.nonempty) && is_subtype(&a.element, &b.element) &&
+            is_subtype(&a.last, &b.last)
+        }
+        (Type::Tuple(a), Type::Tuple(b)) => {
+            match (a.elements.as_ref(), b.elements.as_ref()) {
+                (_, None) => true,
+                (None, _) => false,
+                (Some(a), Some(b)) => {
+                    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| is_subtype(a, b))
+                }
+            }
+        }
+        // TODO: Compare the associations
+        (Type::Map(a), Type::Map(b)) => b.pairs.is_empty() || !a.pairs.is_empty(),
+        (Type::Fun(a), Type::Fun(b)) => {
+            if b.clauses.is_empty() {
+                return true;
+            }
+            if a.clauses.is_empty() {
+                return false;
+            }
+            let (a, b) = (&a.clauses[0], &b.clauses[0]);
+            let args = match (a.args.as_ref(), b.args.as_ref()) {
+                (_, None) => true,
+                (None, _) => false,
+                (Some(a), Some(b)) => {
+                    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| is_subtype(b, a))
+                }
+            };
+            args && is_subtype(&a.return_type, &b.return_type)
+        }
+        (Type::Bitstring(a), Type::Bitstring(b)) => {
+            let (b1, a1) = (a.bits, a.align);
+            let (b2, a2) = (b.bits, b.align);
+            has_bit_size(b1, b2, a2) && (a1 == 0 || (a2 != 0 && a1 % a2 == 0))
+        }
+        _ => false,
+    }
+}
+
+fn is_same_shape(a: &Type, b: &Type) -> bool {
+    match (a, b) {
+        (Type::Tuple(a), Type::Tuple(b)) => {
+            match (a.elements.as_ref(), b.elements.as_ref()) {
+                (Some(a), Some(b)) => a.len() == b.len() && tuple_tag(a) == tuple_tag(b),
+                _ => false,
+            }
+        }
+        _ => rank(a) == rank(b),
+    }
+}
+
+fn widen_member(old: &Type, new: &Type) -> Type {
+    match (old, new) {
+        (Type::Integer(o), Type::Integer(n)) => {
+            let thresholds = widening_thresholds();
+            let min = if lower_min(&o.min, &n.min) == o.min {
+                o.min.clone()
+            } else {
+                n.min.as_ref().and_then(|m| thresholds.iter().rev().find(|t| *t <= m).cloned())
+            };
+            let max = if upper_max(&o.max, &n.max) == o.max {
+                o.max.clone()
+            } else {
+                n.max.as_ref().and_then(|m| thresholds.iter().find(|t| *t >= m).cloned())
+            };
+            From::from(ty::IntegerType { min, max })
+        }
+        (Type::List(o), Type::List(n)) => {
+            let (o, n) = (ListParts::from_list(o), ListParts::from_list(n));
+            list(o.nonempty && n.nonempty,
+                 widen(&o.element, &n.element),
+                 widen(&o.last, &n.last))
+        }
+        (Type::Tuple(o), Type::Tuple(n)) => {
+            match (o.elements.as_ref(), n.elements.as_ref()) {
+                (Some(o), Some(n)) if o.len() == n.len() => {
+                    tuple(o.iter().zip(n).map(|(o, n)| widen(o, n)).collect())
+                }
+                _ => sup(old, new),
+            }
+        }
+        _ => sup(old, new),
+    }
+}
+
+// NOTE: The thresholds are sorted in ascending order
+fn widening_thresholds() -> &'static [BigInt] {
+    static THRESHOLDS: OnceLock<Vec<BigInt>> = OnceLock::new();
+    THRESHOLDS.get_or_init(|| {
+        let mut thresholds = Vec::new();
+        for name in WIDENING_THRESHOLD_TYPES {
+            if let Some(Type::Integer(x)) = resolve::builtin_type(name, &[]) {
+                thresholds.extend(x.min.iter().chain(x.max.iter()).cloned());
+            }
+        }
+        thresholds.sort();
+        thresholds.dedup();
+        thresholds
+    })
+}
+
+fn limit_depth(t: &Type, depth: usize) -> Type {
+    // NOTE: Recursive types (and the references to them) are finite already
+    match *t {
+        Type::Rec(_) => return t.clone(),
+        Type::Var(ref x) if x.value.is_none() => return t.clone(),
+        _ => {}
+    }
+    if depth == 0 {
+        return any();
+    }
+    let limited = members(t)
+        .into_iter()
+        .map(|m| match m {
+            Type::Tuple(ref x) if x.elements.is_some() => {
+                let es = x.elements.as_ref().unwrap();
+                tuple(es.iter().map(|e| limit_depth(e, depth - 1)).collect())
+            }
+            Type::List(ref x) => {
+                let l = ListParts::from_list(x);
+                list(l.nonempty,
+                     limit_depth(&l.element, depth - 1),
+                     limit_depth(&l.last, depth - 1))
+            }
+            Type::Map(ref x) if !x.pairs.is_empty() => {
+                if depth == 1 { From::from(ty::MapType::any()) } else { m.clone() }
+            }
+            Type::Fun(ref x) if !x.clauses.is_empty() => {
+                if depth == 1 { From::from(ty::FunType::any()) } else { m.clone() }
+            }
+            m => m,
+        })
+        .collect::<Vec<_>>();
+    join(&limited)
+}
+
+// NOTE:
+// Recursive types are unfolded by the operations, so an operation may come back to itself
+// (e.g., joining two tree types joins their subtrees).
+// Such a cycle is closed by a reference to the result of the outer operation,
+// which is made a recursive type.
+fn coinductive<F>(op: RecOp, operands: &[Type], f: F) -> Type
+    where F: FnOnce() -> Type
+{
+    if !operands.iter().any(has_rec) {
+        return f();
+    }
+    let (referred, depth) = IN_PROGRESS.with(|frames| {
+        let mut frames = frames.borrow_mut();
+        let referred = frames.iter_mut()
+            .find(|f| f.op == op && f.operands == operands)
+            .map(|f| {
+                f.is_referred = true;
+                f.var.clone()
+            });
+        (referred, frames.len())
+    });
+    if let Some(var) = referred {
+        return From::from(ty::Var::new(&var));
+    }
+    if depth >= MAX_RECURSION_DEPTH {
+        CUT_OFFS.with(|n| n.set(n.get() + 1));
+        return any();
+    }
+    let var = rec_var(operands, depth);
+    IN_PROGRESS.with(|frames| {
+        frames.borrow_mut().push(Frame {
+            op,
+            operands: operands.to_vec(),
+            var: var.clone(),
+            is_referred: false,
+        })
+    });
+    let t = f();
+    let frame = IN_PROGRESS.with(|frames| frames.borrow_mut().pop()).expect("Never fails");
+    if !frame.is_referred {
+        return t;
+    }
+    let rec = ty::RecType::new(&var, t);
+    if !rec.is_productive() {
+        any()
+    } else if rec.unfold() == rec.body {
+        rec.body
+    } else {
+        From::from(rec)
+    }
+}
+
+// NOTE: The variable is named after the first recursive operand (e.g., `Tree`)
+fn rec_var(operands: &[Type], depth: usize) -> String {
+    let name = operands.iter()
+        .find_map(|t| match *t {
+            Type::Rec(ref x) => Some(x.var.as_str()),
+            _ => None,
+        })
+        .unwrap_or("Rec");
+    let is_used = IN_PROGRESS.with(|frames| frames.borrow().iter().any(|f| f.var == name));
+    if is_used { format!("{}{}", name, depth) } else { name.to_string() }
+}
+
+/// Returns `true` if `t` has recursive types which are unfolded by `members`.
+fn has_rec(t: &Type) -> bool {
+    match *t {
+        Type::Rec(_) => true,
+        Type::Union(ref x) => x.types.iter().any(has_rec),
+        Type::Var(ref x) => x.value.as_ref().is_some_and(has_rec),
+        Type::UserDefined(ref x) => has_rec(&x.body),
+        _ => false,
+    }
+}
+
+fn tuple_tag(elements: &[Type]) -> Option<&str> {
+    match elements.first() {
+        Some(Type::Atom(a)) => a.value.as_deref(),
+        _ => None,
+    }
+}
+
+fn fun_arity(f: &ty::FunType) -> Option<usize> {
+    f.clauses.first().and_then(|c| c.args.as_ref()).map(|args| args.len())
+}
+
+fn is_map_equivalent(a: &ty::MapType, b: &ty::MapType) -> bool {
+    a.pairs.len() == b.pairs.len() &&
+    a.pairs.iter().zip(&b.pairs).all(|(a, b)| {
+        a.is_assoc == b.is_assoc && is_equivalent(&a.key, &b.key) &&
+        is_equivalent(&a.value, &b.value)
+    })
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// NOTE: `None` of lower bounds means "-infinity" and of upper bounds means "+infinity"
+fn lower_min(a: &Option<BigInt>, b: &Option<BigInt>) -> Option<BigInt> {
+    a.as_ref().and_then(|a| b.as_ref().map(|b| cmp::min(a, b).clone()))
+}
+fn lower_max(a: &Option<BigInt>, b: &Option<BigInt>) -> Option<BigInt> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(cmp::max(a, b).clone()),
+        (a, None) => a.clone(),
+        (None, b) => b.clone(),
+    }
+}
+fn upper_min(a: &Option<BigInt>, b: &Option<BigInt>) -> Option<BigInt> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(cmp::min(a, b).clone()),
+        (a, None) => a.clone(),
+        (None, b) => b.clone(),
+    }
+}
+fn upper_max(a: &Option<BigInt>, b: &Option<BigInt>) -> Option<BigInt> {
+    a.as_ref().and_then(|a| b.as_ref().map(|b| cmp::max(a, b).clone()))
+}
@@ -1,12 +1,38 @@
 extern crate num;
 extern crate erl_ast;
+extern crate eetf;
 
 pub mod env;
+pub mod code_path;
+pub mod config;
+pub mod term;
+pub mod dep_graph;
+pub mod diagnostic;
+pub mod baseline;
+pub mod report;
+pub mod lsp;
+pub mod repl;
+pub mod watch;
+pub mod scc;
+pub mod call_graph;
+pub mod solver;
+pub mod resolve;
+pub mod lattice;
+pub mod intern;
+pub mod bif;
+pub mod json;
+pub mod xml;
+pub mod plt;
+pub mod pool;
 pub mod module;
 pub mod ty;
+pub mod typespec;
+pub mod pretty;
 pub mod ast;
 pub mod graph;
 pub mod graph_dot;
+pub mod graph_json;
+pub mod graph_graphml;
 pub mod meta;
 
 pub mod beam;
@@ -0,0 +1,349 @@
+//! Language server speaking the Language Server Protocol over stdio.
+//!
+//! Supported messages:
+//!
+//! - `initialize`, `initialized`, `shutdown` and `exit`
+//! - `textDocument/hover`: the success typing and the `-spec` of the function under the cursor
+//! - `textDocument/publishDiagnostics`: the findings (see `diagnostic::check`)
+//!
+//! Source files are not compiled by the server; instead, beam files are watched
+//! (see `watch::BeamWatcher`), so the results are updated when the build tool writes them.
+//!
+//! See: [Language Server Protocol](https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/)
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::env;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+use diagnostic::Diagnostic;
+use diagnostic::Kind;
+use diagnostic::Severity;
+use json::Json;
+use module::Remote;
+use module::Result;
+use watch::Analysis;
+use watch::BeamWatcher;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_REQUEST: i64 = -32600;
+
+// NOTE: The values of `MessageType`
+const LOG_ERROR: i64 = 1;
+const LOG_INFO: i64 = 3;
+
+pub struct Server<W> {
+    analysis: Analysis,
+    watcher: BeamWatcher,
+    kinds: BTreeSet<Kind>,
+    w: W,
+
+    /// URIs of the files which have findings on the client
+    published: BTreeSet<String>,
+    is_shutdown: bool,
+}
+impl<W> Server<W>
+    where W: Write
+{
+    /// Makes a server which reports the findings of `kinds`.
+    pub fn new(analysis: Analysis, watcher: BeamWatcher, kinds: BTreeSet<Kind>, w: W) -> Self {
+        Server {
+            analysis,
+            watcher,
+            kinds,
+            w,
+            published: BTreeSet::new(),
+            is_shutdown: false,
+        }
+    }
+
+    /// Serves requests read from `r` until an `exit` notification arrives,
+    /// checking the beam files every `poll_interval`.
+    ///
+    /// Returns the exit code (`0` if `shutdown` was requested before `exit`).
+    pub fn run<R>(mut self, r: R, poll_interval: Duration) -> Result<i32>
+        where R: BufRead + Send + 'static
+    {
+        // NOTE: Messages are read on another thread, so that polling is not blocked
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut r = r;
+            loop {
+                let message = read_message(&mut r).map_err(|e| e.to_string());
+                let is_end = !matches!(message, Ok(Some(_)));
+                if tx.send(message).is_err() || is_end {
+                    break;
+                }
+            }
+        });
+
+        let mut last_poll = Instant::now();
+        loop {
+            let timeout = poll_interval.saturating_sub(last_poll.elapsed());
+            match rx.recv_timeout(timeout) {
+                Ok(Ok(Some(message))) => {
+                    if let Some(code) = self.handle(&message)? {
+                        return Ok(code);
+                    }
+                }
+                Ok(Ok(None)) | Err(RecvTimeoutError::Disconnected) => return Ok(1),
+                Ok(Err(e)) => return Err(e.into()),
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            if last_poll.elapsed() >= poll_interval {
+                self.poll()?;
+                last_poll = Instant::now();
+            }
+        }
+    }
+
+    // NOTE: Returns the exit code if the server should exit
+    fn handle(&mut self, message: &Json) -> Result<Option<i32>> {
+        let method = match message.get("method").and_then(Json::as_str) {
+            Some(method) => method,
+            None => return Ok(None), // A response to a request of the server
+        };
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => {
+                match method {
+                    "initialized" => self.publish_diagnostics()?,
+                    "exit" => return Ok(Some(if self.is_shutdown { 0 } else { 1 })),
+                    _ => {}
+                }
+                return Ok(None);
+            }
+        };
+        let result = match method {
+            _ if self.is_shutdown => Err((INVALID_REQUEST, "The server is shutting down")),
+            "initialize" => Ok(initialize_result()),
+            "shutdown" => {
+                self.is_shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/hover" => Ok(self.hover(params).unwrap_or(Json::Null)),
+            _ => Err((METHOD_NOT_FOUND, "Unsupported method")),
+        };
+        let response = match result {
+            Ok(result) => {
+                Json::object(vec![("jsonrpc", Json::from("2.0")), ("id", id), ("result", result)])
+            }
+            Err((code, message)) => {
+                let error = Json::object(vec![("code", Json::Integer(code)),
+                                              ("message", Json::from(message))]);
+                Json::object(vec![("jsonrpc", Json::from("2.0")), ("id", id), ("error", error)])
+            }
+        };
+        write_message(&mut self.w, &response)?;
+        Ok(None)
+    }
+
+    fn poll(&mut self) -> Result<()> {
+        let changes = self.watcher.scan()?;
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let update = self.analysis.update(&changes);
+        for (beam_file, e) in &update.errors {
+            // NOTE: The file is retried on the next poll
+            self.watcher.forget(beam_file);
+            self.log(LOG_ERROR, &e.to_string())?;
+        }
+        for beam_file in &update.reloaded {
+            self.log(LOG_INFO, &format!("Reloaded {}", beam_file.display()))?;
+        }
+        if !update.checked_modules.is_empty() {
+            self.publish_diagnostics()?;
+        }
+        Ok(())
+    }
+
+    fn publish_diagnostics(&mut self) -> Result<()> {
+        let mut files = BTreeMap::new();
+        for d in self.analysis.all_diagnostics() {
+            if self.kinds.contains(&d.kind) {
+                let uri = path_to_uri(&absolute_path(&d.location.file));
+                files.entry(uri).or_insert_with(Vec::new).push(diagnostic_to_json(&d));
+            }
+        }
+        // NOTE: Findings which disappeared are cleared by publishing empty lists
+        for uri in &self.published {
+            files.entry(uri.clone()).or_insert_with(Vec::new);
+        }
+        self.published.clear();
+        for (uri, diagnostics) in files {
+            if !diagnostics.is_empty() {
+                self.published.insert(uri.clone());
+            }
+            let params = Json::object(vec![("uri", Json::String(uri)),
+                                           ("diagnostics", Json::Array(diagnostics))]);
+            self.notify("textDocument/publishDiagnostics", params)?;
+        }
+        Ok(())
+    }
+
+    fn hover(&self, params: &Json) -> Option<Json> {
+        let uri = params.get("textDocument").and_then(|d| d.get("uri")).and_then(Json::as_str)?;
+        let line = params.get("position").and_then(|p| p.get("line")).and_then(Json::as_i64)?;
+        let path = uri_to_path(uri)?;
+        let module = self.analysis
+            .env
+            .modules
+            .values()
+            .find(|m| m.source_file.as_ref().is_some_and(|f| is_same_file(f, &path)))?;
+
+        // NOTE: The function whose first clause is the nearest above the cursor is chosen
+        let line = line as i32 + 1;
+        let (_, f) = module.functions
+            .iter()
+            .filter_map(|(f, fun)| {
+                let first = fun.graph.clauses.iter().map(|c| c.line).min()?;
+                Some((first, f)).filter(|&(first, _)| first <= line)
+            })
+            .max()?;
+
+        let mut text = String::from("```erlang\n");
+        let remote = Remote::new(&module.name, &f.name, f.arity);
+        if let Some(signature) = self.analysis.signatures.get(&remote) {
+            text.push_str(&format!("%% success typing\n{}{}\n", f.name, signature));
+        }
+        if let Some(spec) = module.specs.get(f) {
+            text.push_str(&format!("%% spec\n{}\n", spec.to_attribute(&f.name)));
+        }
+        text.push_str("```");
+        let contents = Json::object(vec![("kind", Json::from("markdown")),
+                                         ("value", Json::String(text))]);
+        Some(Json::object(vec![("contents", contents)]))
+    }
+
+    fn log(&mut self, level: i64, message: &str) -> Result<()> {
+        let params = Json::object(vec![("type", Json::Integer(level)),
+                                       ("message", Json::from(message))]);
+        self.notify("window/logMessage", params)
+    }
+
+    fn notify(&mut self, method: &str, params: Json) -> Result<()> {
+        let message = Json::object(vec![("jsonrpc", Json::from("2.0")),
+                                        ("method", Json::from(method)),
+                                        ("params", params)]);
+        write_message(&mut self.w, &message)?;
+        Ok(())
+    }
+}
+
+/// Reads a message framed by the `Content-Length` header.
+///
+/// Returns `None` if `r` reaches EOF before a message.
+pub fn read_message<R: BufRead>(r: &mut R) -> Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if r.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+    let length = length.ok_or("Missing Content-Length header")?;
+    let mut body = vec![0; length];
+    r.read_exact(&mut body)?;
+    Ok(Some(Json::parse(&String::from_utf8(body)?)?))
+}
+
+pub fn write_message<W: Write>(w: &mut W, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(w, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    w.flush()
+}
+
+fn initialize_result() -> Json {
+    // NOTE: Documents are not synchronized (`TextDocumentSyncKind.None`)
+    let sync = Json::object(vec![("openClose", Json::Bool(false)), ("change", Json::Integer(0))]);
+    let capabilities = Json::object(vec![("textDocumentSync", sync),
+                                         ("hoverProvider", Json::Bool(true))]);
+    let info = Json::object(vec![("name", Json::from("diagnoser")),
+                                 ("version", Json::from(env!("CARGO_PKG_VERSION")))]);
+    Json::object(vec![("capabilities", capabilities), ("serverInfo", info)])
+}
+
+fn diagnostic_to_json(d: &Diagnostic) -> Json {
+    // NOTE: LSP lines start from 0 and the whole line is marked
+    let line = (d.location.line.max(1) - 1) as i64;
+    let position = |line: i64| {
+        Json::object(vec![("line", Json::Integer(line)), ("character", Json::Integer(0))])
+    };
+    let range = Json::object(vec![("start", position(line)), ("end", position(line + 1))]);
+    let severity = match d.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    };
+    let mut message = d.message.clone();
+    for (role, t) in &d.types {
+        message.push_str(&format!("\n{}: {}", role, t));
+    }
+    Json::object(vec![("range", range),
+                      ("severity", Json::Integer(severity)),
+                      ("code", Json::from(d.kind.id())),
+                      ("source", Json::from("diagnoser")),
+                      ("message", Json::String(message))])
+}
+
+fn absolute_path(file: &str) -> PathBuf {
+    let path = Path::new(file);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().map(|d| d.join(path)).unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+fn is_same_file(source_file: &str, path: &Path) -> bool {
+    let source_file = absolute_path(source_file);
+    source_file == path ||
+    source_file.canonicalize().ok().is_some_and(|f| path.canonicalize().ok() == Some(f))
+}
+
+// NOTE: Only the characters which are not allowed in paths of URIs are encoded
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for b in path.to_string_lossy().bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~:@!$&'()*+,;=".contains(&b) {
+            uri.push(b as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", b));
+        }
+    }
+    uri
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] == b'%' && i + 2 < encoded.len() {
+            let hex = ::std::str::from_utf8(&encoded[i + 1..i + 3]).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            bytes.push(encoded[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
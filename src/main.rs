@@ -1,14 +1,384 @@
 extern crate clap;
 extern crate diagnoser;
 
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::panic;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::slice;
+use std::thread;
+use std::time::Duration;
 use clap::Parser;
+use clap::ValueEnum;
+use diagnoser::baseline::Baseline;
+use diagnoser::code_path::CodePath;
+use diagnoser::config::Config;
+use diagnoser::dep_graph::ModuleGraph;
+use diagnoser::diagnostic;
 use diagnoser::env::Env;
+use diagnoser::graph_dot::DotOptions;
+use diagnoser::graph::Graph;
+use diagnoser::graph::NodeId;
+use diagnoser::graph_dot::DotWriter;
+use diagnoser::graph_graphml::GraphmlWriter;
+use diagnoser::graph_json;
+use diagnoser::lattice;
+use diagnoser::lsp;
+use diagnoser::module::Arity;
+use diagnoser::module::Local;
 use diagnoser::module::Module;
+use diagnoser::module::Remote;
+use diagnoser::module::Result;
+use diagnoser::plt::Plt;
+use diagnoser::pool;
+use diagnoser::pretty::Aliases;
+use diagnoser::pretty::Printer;
+use diagnoser::repl::Repl;
+use diagnoser::report;
+use diagnoser::solver::Solver;
+use diagnoser::ty::Type;
+use diagnoser::watch;
+use diagnoser::watch::Analysis;
+use diagnoser::watch::BeamWatcher;
 
 #[derive(Parser)]
 enum Args {
     DumpAst { beam_file: String },
-    Analyze { beam_file: Vec<String> },
+    /// Infers the signatures of the functions and reports the findings
+    ///
+    /// Exit codes: 0 = no findings, 1 = findings, 2 = analysis error.
+    Analyze {
+        beam_file: Vec<String>,
+        #[command(flatten)]
+        code_path: CodePathArgs,
+
+        /// Analysis cache used for the modules which are not loaded (it is not updated)
+        #[arg(long)]
+        plt: Option<String>,
+
+        /// Output format (only `text` includes the inferred signatures) [default: text]
+        #[arg(long, value_enum)]
+        format: Option<ReportFormat>,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+
+        #[command(flatten)]
+        watch: WatchArgs,
+
+        #[command(flatten)]
+        print: PrintArgs,
+    },
+    /// Writes the graphs of the functions in `beam_file` as DOT files
+    DumpGraph {
+        beam_file: String,
+        #[command(flatten)]
+        code_path: CodePathArgs,
+
+        /// Function to dump (`name/arity`); all the functions are dumped if omitted
+        #[arg(long)]
+        function: Vec<String>,
+
+        /// Output directory
+        #[arg(long, default_value = ".")]
+        out: String,
+
+        /// Also write the graphs labeled with the types inferred by the solver
+        #[arg(long)]
+        solved: bool,
+
+        /// Write plain DOT graphs (no styles, clusters or highlights)
+        #[arg(long)]
+        plain: bool,
+
+        #[arg(long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+    },
+    /// Manages the persistent analysis cache (PLT)
+    Plt {
+        #[command(subcommand)]
+        command: PltCommand,
+    },
+    /// Loads the modules once and answers queries (see `help` in the prompt)
+    Repl {
+        beam_file: Vec<String>,
+        #[command(flatten)]
+        code_path: CodePathArgs,
+    },
+    /// Runs a language server (LSP over stdio) for editors
+    ///
+    /// The beam files under `--dir` directories are reloaded when they are modified.
+    Lsp {
+        #[command(flatten)]
+        code_path: CodePathArgs,
+
+        /// Interval of checking the beam files (in milliseconds)
+        #[arg(long, default_value = "1000")]
+        poll_interval: u64,
+    },
+    /// Prints the inter-module dependency graph
+    Deps {
+        beam_file: Vec<String>,
+        #[command(flatten)]
+        code_path: CodePathArgs,
+        #[arg(long, value_enum, default_value = "dot")]
+        format: DepsFormat,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum PltCommand {
+    /// Analyzes the given modules and writes the results to a new cache
+    Build {
+        beam_file: Vec<String>,
+        #[command(flatten)]
+        code_path: CodePathArgs,
+        /// Cache file (defaults to `plt` of the config)
+        #[arg(long)]
+        plt: Option<String>,
+    },
+    /// Prints the cached modules (and the signatures and types of `--module`)
+    Inspect {
+        #[arg(long)]
+        plt: String,
+        #[arg(long)]
+        module: Option<String>,
+    },
+    /// Re-analyzes the modified modules (and their clients) and adds the given ones
+    Update {
+        beam_file: Vec<String>,
+        #[command(flatten)]
+        code_path: CodePathArgs,
+        /// Cache file (defaults to `plt` of the config)
+        #[arg(long)]
+        plt: Option<String>,
+    },
+}
+
+/// Exit code of `analyze` when there are no findings.
+const EXIT_NO_FINDINGS: i32 = 0;
+
+/// Exit code of `analyze` when there are findings.
+const EXIT_FINDINGS: i32 = 1;
+
+/// Exit code when the analysis fails (e.g., a beam file can't be loaded or the config is invalid).
+const EXIT_ERROR: i32 = 2;
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ReportFormat {
+    Text,
+    Json,
+    Sarif,
+    Checkstyle,
+    Github,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum GraphFormat {
+    Dot,
+    Json,
+    Graphml,
+}
+impl GraphFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            GraphFormat::Dot => "dot",
+            GraphFormat::Json => "json",
+            GraphFormat::Graphml => "graphml",
+        }
+    }
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum DepsFormat {
+    Dot,
+    Json,
+}
+
+#[derive(clap::Args)]
+struct CodePathArgs {
+    /// Directory searched recursively for `ebin/*.beam` files
+    #[arg(long = "dir")]
+    dirs: Vec<String>,
+
+    /// Application resource file (`.app`) whose modules are loaded
+    #[arg(long = "app")]
+    apps: Vec<String>,
+
+    /// Colon-separated list of library directories (same format as `ERL_LIBS`)
+    #[arg(long)]
+    erl_libs: Option<String>,
+
+    /// OTP library root directory (e.g., `/usr/lib/erlang/lib`)
+    #[arg(long)]
+    otp_root: Option<String>,
+
+    /// Load only the given modules and, from the code path, the modules they depend on
+    #[arg(long)]
+    lazy: bool,
+
+    /// Maximum number of dependency hops followed in `--lazy` mode
+    #[arg(long, requires = "lazy")]
+    depth: Option<usize>,
+
+    /// Number of threads used to load modules (defaults to the number of CPUs)
+    #[arg(long, short = 'j')]
+    jobs: Option<usize>,
+
+    /// Modules to load (globs like `my_*`; separated by commas)
+    #[arg(long, value_delimiter = ',')]
+    include: Vec<String>,
+
+    /// Modules not to load (globs like `*_SUITE`; separated by commas)
+    #[arg(long, value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// Configuration file (by default, `diagnoser.config` in the current directory or its
+    /// ancestors)
+    #[arg(long)]
+    config: Option<String>,
+
+    /// Ignore configuration files
+    #[arg(long, conflicts_with = "config")]
+    no_config: bool,
+}
+impl CodePathArgs {
+    fn read_config(&self) -> Result<Config> {
+        if self.no_config {
+            return Ok(Config::new());
+        }
+        let path = match self.config {
+            Some(ref path) => Some(PathBuf::from(path)),
+            None => Config::find(std::env::current_dir()?),
+        };
+        match path {
+            Some(path) => Config::read_from_file(path),
+            None => Ok(Config::new()),
+        }
+    }
+
+    // NOTE: Options given on the command line take precedence over the config
+    fn merge_config(&mut self, config: &Config) {
+        let to_strings = |paths: &[PathBuf]| {
+            paths.iter().map(|p| p.to_string_lossy().into_owned()).collect::<Vec<_>>()
+        };
+        if self.dirs.is_empty() {
+            self.dirs = to_strings(&config.dirs);
+        }
+        if self.apps.is_empty() {
+            self.apps = to_strings(&config.apps);
+        }
+        if self.erl_libs.is_none() {
+            self.erl_libs = config.erl_libs.clone();
+        }
+        if self.otp_root.is_none() {
+            self.otp_root = config.otp_root.as_ref().map(|p| p.to_string_lossy().into_owned());
+        }
+        if self.include.is_empty() && self.exclude.is_empty() {
+            self.include = config.include.clone();
+            self.exclude = config.exclude.clone();
+        }
+    }
+
+    /// Reads the configuration file, merges it into `self` and applies the global settings.
+    fn configure(&mut self) -> Result<Config> {
+        let config = self.read_config().map_err(|e| format!("Can't read config: {}", e))?;
+        self.merge_config(&config);
+        if let Some(limit) = config.atom_set_limit {
+            lattice::set_atom_set_limit(limit);
+        }
+        Ok(config)
+    }
+
+    fn load(&self, env: &mut Env, beam_files: &[String]) -> Result<Vec<PathBuf>> {
+        let beam_files = beam_files.iter().map(PathBuf::from).collect::<Vec<_>>();
+        env.jobs = self.jobs.unwrap_or_else(pool::default_jobs).max(1);
+        env.load_code_path(&beam_files, &self.to_code_path())
+    }
+
+    fn to_code_path(&self) -> CodePath {
+        CodePath {
+            dirs: self.dirs.iter().map(PathBuf::from).collect(),
+            apps: self.apps.iter().map(PathBuf::from).collect(),
+            erl_libs: self.erl_libs.clone(),
+            otp_root: self.otp_root.as_ref().map(PathBuf::from),
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            lazy: self.lazy,
+            depth: self.depth,
+        }
+    }
+}
+
+#[derive(clap::Args)]
+struct FilterArgs {
+    /// Diagnostics to report (e.g., `call-fails,no-return` or `all`)
+    #[arg(long, value_delimiter = ',')]
+    enable: Vec<String>,
+
+    /// Diagnostics not to report (takes precedence over `--enable`)
+    #[arg(long, value_delimiter = ',')]
+    disable: Vec<String>,
+
+    /// File of known findings which are not reported (nor counted in the exit code)
+    #[arg(long)]
+    baseline: Option<String>,
+
+    /// Writes the (enabled) findings to the given baseline file
+    #[arg(long)]
+    write_baseline: Option<String>,
+
+    /// Unions of more atoms than this are widened to `atom()`
+    #[arg(long)]
+    atom_set_limit: Option<usize>,
+}
+impl FilterArgs {
+    // NOTE: Options given on the command line take precedence over the config
+    fn merge_config(&mut self, config: &Config) {
+        if self.enable.is_empty() && self.disable.is_empty() {
+            self.enable = config.enable.clone();
+            self.disable = config.disable.clone();
+        }
+        self.atom_set_limit = self.atom_set_limit.or(config.atom_set_limit);
+    }
+}
+
+#[derive(clap::Args)]
+struct WatchArgs {
+    /// Keeps running and re-analyzes the modules whose beam files in the given `ebin`
+    /// directories (or directories containing them) are modified; only the findings which
+    /// appeared (`+`) or disappeared (`-`) are printed
+    #[arg(long, conflicts_with_all = ["format", "write_baseline"])]
+    watch: Vec<String>,
+
+    /// Interval of checking the watched directories (in milliseconds)
+    #[arg(long, default_value = "1000")]
+    poll_interval: u64,
+}
+
+#[derive(clap::Args)]
+struct PrintArgs {
+    /// Line width of the inferred signatures and the types of the findings (text output)
+    #[arg(long, default_value = "80")]
+    width: usize,
+
+    /// Types nested deeper than this are abbreviated as `...` (text output)
+    #[arg(long)]
+    max_depth: Option<usize>,
+}
+impl PrintArgs {
+    fn printer(&self) -> Printer {
+        let printer = Printer::new().width(self.width);
+        match self.max_depth {
+            Some(depth) => printer.max_depth(depth),
+            None => printer,
+        }
+    }
 }
 
 fn main() {
@@ -16,17 +386,311 @@ fn main() {
     match args {
         Args::DumpAst { beam_file } => {
             let module = Module::from_beam_file(&beam_file)
-                .expect(&format!("Can't parse file: {}", beam_file));
+                .unwrap_or_else(|_| panic!("Can't parse file: {}", beam_file));
             println!("{:?}", module);
         }
-        Args::Analyze { beam_file } => {
+        Args::Analyze { beam_file, code_path, plt, format, filter, watch, print } => {
+            let result = panic::catch_unwind(move || {
+                analyze(&beam_file, code_path, plt, format, filter, watch, print)
+            });
+            let code = match result {
+                Ok(Ok(true)) => EXIT_FINDINGS,
+                Ok(Ok(false)) => EXIT_NO_FINDINGS,
+                Ok(Err(e)) => {
+                    eprintln!("Error: {}", e);
+                    EXIT_ERROR
+                }
+                Err(_) => EXIT_ERROR,
+            };
+            process::exit(code);
+        }
+        Args::DumpGraph { beam_file, mut code_path, function, out, solved, plain, format } => {
+            code_path.configure().unwrap_or_else(|e| exit_with_error(e));
             let mut env = Env::new();
-            for beam_file in &beam_file {
-                println!("LOAD: {}", beam_file);
-                let module = Module::from_beam_file(beam_file)
-                    .expect(&format!("Can't parse file: {}", beam_file));
-                env.add_module(module);
+            load(&mut env, &code_path, slice::from_ref(&beam_file), &[]);
+            let module = env.modules
+                .values()
+                .find(|m| m.beam_file.as_deref() == Some(Path::new(&beam_file)))
+                .unwrap_or_else(|| panic!("Can't load file: {}", beam_file));
+            let funs = if function.is_empty() {
+                let mut funs = module.functions.keys().cloned().collect::<Vec<_>>();
+                funs.sort();
+                funs
+            } else {
+                function.iter().map(|f| parse_function(f)).collect()
+            };
+            let signatures = if solved {
+                Some(Solver::new(&env).solve())
+            } else {
+                None
+            };
+            let solver = signatures.map(|s| Solver::with_signatures(&env, s));
+            let options = if plain {
+                DotOptions::plain()
+            } else {
+                DotOptions::default()
+            };
+            let write = |path: &Path, graph: &Graph, types: &HashMap<NodeId, Type>| {
+                let result = fs::File::create(path).and_then(|w| match format {
+                    GraphFormat::Dot => {
+                        DotWriter::with_options(w, options.clone()).write_with_types(graph, types)
+                    }
+                    GraphFormat::Json => {
+                        let mut w = w;
+                        writeln!(w, "{:#}", graph_json::to_json_with_types(graph, types))
+                    }
+                    GraphFormat::Graphml => GraphmlWriter::new(w).write_with_types(graph, types),
+                });
+                result.unwrap_or_else(|e| panic!("Can't write graph: {}: {}", path.display(), e));
+                println!("WRITE: {}", path.display());
+            };
+            for f in funs {
+                let fun = module.functions
+                    .get(&f)
+                    .unwrap_or_else(|| panic!("Undefined function: {}/{}", f.name, f.arity));
+                let name = format!("{}.{}.{}", module.name, f.name, f.arity);
+                let name = name.replace(|c: char| !(c.is_alphanumeric() || "_.".contains(c)), "_");
+                let path = Path::new(&out).join(format!("{}.{}", name, format.extension()));
+                write(&path, &fun.graph, &HashMap::new());
+
+                let remote = Remote::new(&module.name, &f.name, f.arity);
+                let types = solver.as_ref().and_then(|s| s.node_types(&remote));
+                if let Some(types) = types {
+                    let path = Path::new(&out)
+                        .join(format!("{}.solved.{}", name, format.extension()));
+                    write(&path, &fun.graph, &types);
+                }
+            }
+        }
+        Args::Plt { command } => handle_plt_command(command),
+        Args::Repl { beam_file, mut code_path } => {
+            code_path.configure().unwrap_or_else(|e| exit_with_error(e));
+            let mut env = Env::new();
+            load(&mut env, &code_path, &beam_file, &[]);
+            let signatures = Solver::new(&env).solve();
+            let stdin = io::stdin();
+            Repl::new(&env, signatures)
+                .run(stdin.lock(), io::stdout())
+                .unwrap_or_else(|e| panic!("Can't run REPL: {}", e));
+        }
+        Args::Lsp { mut code_path, poll_interval } => {
+            let config = code_path.configure().unwrap_or_else(|e| exit_with_error(e));
+            let kinds = diagnostic::select_kinds(&config.enable, &config.disable)
+                .unwrap_or_else(|e| panic!("Invalid config: {}", e));
+            let mut env = Env::new();
+            load(&mut env, &code_path, &[], &[]);
+            let dirs = code_path.dirs.iter().map(PathBuf::from).collect();
+            let watcher = BeamWatcher::new(dirs, Vec::new())
+                .unwrap_or_else(|e| panic!("Can't watch beam files: {}", e));
+            let server = lsp::Server::new(Analysis::new(env), watcher, kinds, io::stdout());
+            let code = server.run(io::BufReader::new(io::stdin()),
+                                  Duration::from_millis(poll_interval))
+                .unwrap_or_else(|e| panic!("Language server failed: {}", e));
+            process::exit(code);
+        }
+        Args::Deps { beam_file, mut code_path, format } => {
+            code_path.configure().unwrap_or_else(|e| exit_with_error(e));
+            let mut env = Env::new();
+            code_path.load(&mut env, &beam_file)
+                .unwrap_or_else(|e| panic!("Can't load modules: {}", e));
+            let graph = ModuleGraph::new(&env);
+            match format {
+                DepsFormat::Dot => {
+                    graph.write_as_dot(std::io::stdout()).expect("Can't write DOT");
+                }
+                DepsFormat::Json => println!("{:#}", graph.to_json()),
             }
         }
     }
 }
+
+// NOTE: Returns `true` if there are findings
+fn analyze(beam_files: &[String],
+           mut code_path: CodePathArgs,
+           plt: Option<String>,
+           format: Option<ReportFormat>,
+           mut filter: FilterArgs,
+           watch: WatchArgs,
+           print: PrintArgs)
+           -> Result<bool> {
+    let config = code_path.configure()?;
+    filter.merge_config(&config);
+    let plt = plt.or_else(|| config.plt.as_ref().map(|p| p.to_string_lossy().into_owned()));
+    let format = match (format, config.format) {
+        (Some(format), _) => format,
+        (None, _) if !watch.watch.is_empty() => ReportFormat::Text,
+        (None, Some(ref format)) => {
+            ReportFormat::from_str(format, false)
+                .map_err(|_| format!("Unknown format in config: {}", format))?
+        }
+        (None, None) => ReportFormat::Text,
+    };
+    if let Some(limit) = filter.atom_set_limit {
+        lattice::set_atom_set_limit(limit);
+    }
+    let kinds = diagnostic::select_kinds(&filter.enable, &filter.disable)?;
+    let baseline = match filter.baseline {
+        Some(ref path) => Baseline::read_from_file(path)?,
+        None => Baseline::new(),
+    };
+    let mut env = Env::new();
+    let stale = match plt {
+        Some(plt) => {
+            let plt = Plt::read_from_file(&plt).map_err(|e| format!("{}: {}", plt, e))?;
+            env.use_plt(plt)
+        }
+        None => Vec::new(),
+    };
+    let mut loaded = code_path.load(&mut env, beam_files)?;
+    loaded.extend(env.load_beam_files(&stale)?);
+    let mut analysis = Analysis::new(env);
+    let mut diagnostics = analysis.all_diagnostics();
+    diagnostics.retain(|d| kinds.contains(&d.kind));
+    if let Some(ref path) = filter.write_baseline {
+        Baseline::from_diagnostics(&diagnostics).write_to_file(path)?;
+    }
+    diagnostics.retain(|d| !baseline.contains(d));
+
+    let printer = print.printer();
+    match format {
+        ReportFormat::Text => {
+            for beam_file in &loaded {
+                println!("LOAD: {}", beam_file.display());
+            }
+            let aliases = Aliases::new(&analysis.env);
+            for (f, signature) in &analysis.signatures {
+                let head = format!("{}:{}/{} :: ", f.module, f.name, f.arity);
+                let signature = aliases.fold_fun_spec(signature, &f.module);
+                println!("{}{}", head, printer.print_fun_spec(&signature, head.chars().count()));
+            }
+            report::write_text(io::stdout(), &diagnostics, &printer)?;
+        }
+        ReportFormat::Json => println!("{:#}", report::to_json(&diagnostics)),
+        ReportFormat::Sarif => println!("{:#}", report::to_sarif(&diagnostics)),
+        ReportFormat::Checkstyle => report::write_checkstyle(io::stdout(), &diagnostics)?,
+        ReportFormat::Github => report::write_github(io::stdout(), &diagnostics)?,
+    }
+    if watch.watch.is_empty() {
+        return Ok(!diagnostics.is_empty());
+    }
+
+    let dirs = watch.watch.iter().map(PathBuf::from).collect();
+    let mut watcher = BeamWatcher::new(dirs, Vec::new())?;
+    loop {
+        thread::sleep(Duration::from_millis(watch.poll_interval));
+        let update = analysis.update(&watcher.scan()?);
+        for (beam_file, e) in &update.errors {
+            // NOTE: The file is retried on the next poll
+            watcher.forget(beam_file);
+            eprintln!("Error: {}", e);
+        }
+        if update.checked_modules.is_empty() {
+            continue;
+        }
+        for beam_file in &update.reloaded {
+            println!("RELOAD: {}", beam_file.display());
+        }
+        let mut current = analysis.all_diagnostics();
+        current.retain(|d| kinds.contains(&d.kind) && !baseline.contains(d));
+        let (appeared, disappeared) = watch::diff(&diagnostics, &current);
+        for d in disappeared {
+            println!("- {}", d);
+        }
+        for d in appeared {
+            println!("+ {}", d);
+            for (role, t) in &d.types {
+                println!("    {}: {}", role, printer.print(t, role.len() + 6));
+            }
+        }
+        io::stdout().flush()?;
+        diagnostics = current;
+    }
+}
+
+// NOTE: The stale modules of the cache (see `Env::use_plt`) are re-analyzed too
+fn load(env: &mut Env,
+        code_path: &CodePathArgs,
+        beam_files: &[String],
+        stale: &[PathBuf])
+        -> Vec<PathBuf> {
+    let mut loaded = code_path.load(env, beam_files)
+        .unwrap_or_else(|e| panic!("Can't load modules: {}", e));
+    loaded.extend(env.load_beam_files(stale)
+        .unwrap_or_else(|e| panic!("Can't load modules: {}", e)));
+    loaded
+}
+
+fn handle_plt_command(command: PltCommand) {
+    match command {
+        PltCommand::Build { beam_file, mut code_path, plt } => {
+            let config = code_path.configure().unwrap_or_else(|e| exit_with_error(e));
+            let plt = plt_path(plt, &config);
+            let mut env = Env::new();
+            load(&mut env, &code_path, &beam_file, &[]);
+            write_plt(&env, &plt);
+        }
+        PltCommand::Update { beam_file, mut code_path, plt } => {
+            let config = code_path.configure().unwrap_or_else(|e| exit_with_error(e));
+            let plt = plt_path(plt, &config);
+            let cache = Plt::read_from_file(&plt)
+                .unwrap_or_else(|e| panic!("Can't read PLT: {}", e));
+            let mut env = Env::new();
+            let stale = env.use_plt(cache);
+            for beam_file in load(&mut env, &code_path, &beam_file, &stale) {
+                println!("ANALYZE: {}", beam_file.display());
+            }
+            write_plt(&env, &plt);
+        }
+        PltCommand::Inspect { plt, module } => {
+            let plt = Plt::read_from_file(&plt)
+                .unwrap_or_else(|e| panic!("Can't read PLT: {}", e));
+            let stale = plt.stale_modules();
+            for m in plt.modules.values() {
+                if module.as_ref().is_some_and(|name| *name != m.name) {
+                    continue;
+                }
+                println!("{} {:016x} {} ({} functions, {} types){}",
+                         m.name,
+                         m.hash,
+                         m.beam_file.display(),
+                         m.signatures.len(),
+                         m.exported_types.len(),
+                         if stale.contains(&m.name) { " [stale]" } else { "" });
+                if module.is_some() {
+                    for t in m.exported_types.values() {
+                        println!("  -type {}({}) :: {}.", t.name, t.vars.join(", "), t.body);
+                    }
+                    for (f, signature) in &m.signatures {
+                        println!("  {}/{} :: {}", f.name, f.arity, signature);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reports `error` and exits with `EXIT_ERROR`.
+fn exit_with_error<E: fmt::Display>(error: E) -> ! {
+    eprintln!("Error: {}", error);
+    process::exit(EXIT_ERROR)
+}
+
+fn plt_path(plt: Option<String>, config: &Config) -> String {
+    plt.or_else(|| config.plt.as_ref().map(|p| p.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| panic!("No PLT is specified (by `--plt` or the config)"))
+}
+
+fn write_plt(env: &Env, path: &str) {
+    let signatures = Solver::new(env).solve();
+    let plt = Plt::from_env(env, &signatures).unwrap_or_else(|e| panic!("Can't make PLT: {}", e));
+    plt.write_to_file(path).unwrap_or_else(|e| panic!("Can't write PLT: {}: {}", path, e));
+    println!("WRITE: {} ({} modules)", path, plt.modules.len());
+}
+
+// NOTE: `f` is formatted as `name/arity`
+fn parse_function(f: &str) -> Local {
+    let (name, arity) = f.rsplit_once('/')
+        .unwrap_or_else(|| panic!("Function must be formatted as `name/arity`: {}", f));
+    let arity = arity.parse::<Arity>().unwrap_or_else(|_| panic!("Invalid arity: {}", f));
+    Local::new(name, arity)
+}
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use num::traits::ToPrimitive;
+use num::BigInt;
 use erl_ast::ast;
 use ty;
 use graph;
@@ -44,6 +44,16 @@ impl GraphBuilder {
             id
         }
     }
+    // NOTE: Variables bound in all branches of `case` can be used after it
+    fn lookup(&mut self, name: &str) -> NodeId {
+        if let Some(id) = self.find_binding(name) {
+            id
+        } else {
+            let id = self.graph.new_value_node(graph::Val::new_any());
+            self.bindings.last_mut().unwrap().insert(name.to_string(), id);
+            id
+        }
+    }
     fn find_binding(&self, name: &str) -> Option<NodeId> {
         for b in self.bindings.iter().rev() {
             if let Some(id) = b.get(name) {
@@ -61,13 +71,6 @@ impl GraphBuilder {
             self.parse_clause(&args, fun_return, c);
         }
 
-        {
-            use std::fs;
-            
-            let f = fs::File::create(format!("/tmp/graph_{}_{}.dot", decl.name, arity)).unwrap();
-            self.graph.write_as_dot(f).unwrap();
-        }
-
         self.graph
     }
     pub fn parse_clause(&mut self,
@@ -78,11 +81,13 @@ impl GraphBuilder {
             panic!("args.len={}, clause={:?}", args.len(), clause);
         }
         self.scope_in();
+        self.graph.clause_in(clause.line);
 
         for (i, p) in clause.patterns.iter().enumerate() {
             let pattern = self.parse_pattern(p);
             let arg = args[i];
             self.graph.add_edge(graph::EdgeKind::Match, arg, pattern);
+            self.graph.current_clause_mut().patterns.push(pattern);
         }
 
         // NOTE:
@@ -91,10 +96,13 @@ impl GraphBuilder {
         // (他と同じ仕組みで扱えるようにするには)
         // e.g., `is_atom() => -spec guard_is_atom(atom()) -> true.`
         for g in &clause.guards {
-            self.parse_and_guards(&g.and_guards);
+            let conj = self.parse_and_guards(&g.and_guards);
+            self.graph.current_clause_mut().guards.push(conj);
         }
         let clause_result = self.parse_body(&clause.body);
+        self.graph.current_clause_mut().body = Some(clause_result);
         self.graph.add_edge(graph::EdgeKind::Return, clause_result, result);
+        self.graph.clause_out();
         self.scope_out();
     }
     pub fn parse_body(&mut self, body: &[ast::expr::Expression]) -> graph::NodeId {
@@ -105,12 +113,12 @@ impl GraphBuilder {
         }
         return_value.unwrap()
     }
-    pub fn parse_and_guards(&mut self, guards: &Vec<ast::guard::Guard>) {
+    pub fn parse_and_guards(&mut self, guards: &Vec<ast::guard::Guard>) -> graph::NodeId {
         let mut conjunctions = Vec::with_capacity(guards.len());
         for g in guards {
             conjunctions.push(self.parse_guard(g));
         }
-        self.graph.new_conj(conjunctions);
+        self.graph.new_conj(conjunctions)
     }
     pub fn parse_guard(&mut self, guard: &ast::guard::Guard) -> graph::NodeId {
         use erl_ast::ast::guard::Guard as G;
@@ -120,21 +128,14 @@ impl GraphBuilder {
                 self.graph.new_value_node(value)
             }
             G::Integer(ref x) => {
-                let value = if let Some(v) = x.value.to_i64() {
-                    graph::Val::with_type(From::from(ty::integer().value(v)))
-                } else {
-                    graph::Val::with_type(From::from(ty::integer()))
-                };
+                let value = graph::Val::with_type(From::from(ty::integer().value(x.value.clone())));
                 self.graph.new_value_node(value)
             }
             G::Nil(_) => {
                 let value = graph::Val::with_type(From::from(ty::NilType));
                 self.graph.new_value_node(value)
             }
-            G::Var(ref x) => {
-                let var = self.find_binding(&x.name).unwrap();
-                var
-            }
+            G::Var(ref x) => self.lookup(&x.name),
             G::BinaryOp(ref x) => {
                 let name = {
                     let name = graph::Val::with_type(ty::atom(&format!("__op_{}", x.operator)));
@@ -154,7 +155,17 @@ impl GraphBuilder {
                 let node_id = self.graph.new_local_call_node(fun, args);
                 self.graph.get_return_node(node_id).unwrap()
             }
-            _ => panic!("GUARD: {:?}", guard),
+            G::UnaryOp(ref x) => {
+                let name = {
+                    let name = graph::Val::with_type(ty::atom(&format!("__op_{}", x.operator)));
+                    self.graph.new_value_node(name)
+                };
+                let arg0 = self.parse_guard(&x.operand);
+                let node_id = self.graph.new_local_call_node(name, vec![arg0]);
+                self.graph.get_return_node(node_id).unwrap()
+            }
+            // TODO: Support the remaining guard expressions
+            _ => self.graph.new_value_node(graph::Val::new_any()),
         }
     }
 
@@ -167,11 +178,7 @@ impl GraphBuilder {
                 self.graph.new_value_node(value)
             }
             P::Integer(ref x) => {
-                let value = if let Some(v) = x.value.to_i64() {
-                    graph::Val::with_type(From::from(ty::integer().value(v)))
-                } else {
-                    graph::Val::with_type(From::from(ty::integer()))
-                };
+                let value = graph::Val::with_type(From::from(ty::integer().value(x.value.clone())));
                 self.graph.new_value_node(value)
             }
             P::Nil(_) => {
@@ -192,7 +199,7 @@ impl GraphBuilder {
                 };
                 let arg0 = self.parse_pattern(&x.head);
                 let arg1 = self.parse_pattern(&x.tail);
-                let node_id = self.graph.new_local_call_node(name, vec![arg0, arg1]);
+                let node_id = self.graph.new_pattern_call_node(name, vec![arg0, arg1]);
                 self.graph.get_return_node(node_id).unwrap()
             }
             P::Record(ref x) => {
@@ -203,17 +210,19 @@ impl GraphBuilder {
                 let mut args = Vec::with_capacity(x.fields.len());
                 for f in &x.fields {
                     let field_id = {
-                        let name = f.name.clone().expect("Not Implemented");
+                        // NOTE: `None` means "the other fields" (i.e., `_ = Value`)
+                        let name = f.name.clone().unwrap_or_else(|| "_".to_string());
                         let name = self.graph
                             .new_value_node(graph::Val::with_type(ty::atom(&format!("__field_{}_{}",
                                                                                     x.name,
                                                                                     name))));
                         let arg = self.parse_pattern(&f.value);
-                        self.graph.new_local_call_node(name, vec![arg])
+                        let node_id = self.graph.new_pattern_call_node(name, vec![arg]);
+                        self.graph.get_return_node(node_id).unwrap()
                     };
                     args.push(field_id);
                 }
-                let node_id = self.graph.new_local_call_node(name, args);
+                let node_id = self.graph.new_pattern_call_node(name, args);
                 self.graph.get_return_node(node_id).unwrap()
             }
             P::Tuple(ref x) => {
@@ -225,10 +234,42 @@ impl GraphBuilder {
                 for e in &x.elements {
                     args.push(self.parse_pattern(e));
                 }
-                let node_id = self.graph.new_local_call_node(name, args);
+                let node_id = self.graph.new_pattern_call_node(name, args);
                 self.graph.get_return_node(node_id).unwrap()
             }
-            _ => panic!("PAT: {:?}", pattern),
+            P::Char(ref x) => {
+                let value = graph::Val::with_type(From::from(ty::integer().value(x.value as i64)));
+                self.graph.new_value_node(value)
+            }
+            P::Float(_) => {
+                let value = graph::Val::with_type(From::from(ty::FloatType));
+                self.graph.new_value_node(value)
+            }
+            P::String(ref x) => {
+                let value = graph::Val::with_type(string_literal_type(&x.value));
+                self.graph.new_value_node(value)
+            }
+            P::UnaryOp(ref x) => {
+                let value = match (x.operator.as_str(), &x.operand) {
+                    ("-", P::Integer(i)) => {
+                        Some(From::from(ty::integer().value(-BigInt::from(i.value.clone()))))
+                    }
+                    _ => None,
+                };
+                let value = value.map_or_else(graph::Val::new_any, graph::Val::with_type);
+                self.graph.new_value_node(value)
+            }
+            P::Binary(ref x) => {
+                // NOTE: Segments are parsed only for binding their variables
+                for e in &x.elements {
+                    self.parse_pattern(&e.element);
+                }
+                let value = ty::BitstringType::default().align(1);
+                let value = graph::Val::with_type(From::from(value));
+                self.graph.new_value_node(value)
+            }
+            // TODO: Support the remaining patterns (e.g., maps)
+            _ => self.graph.new_value_node(graph::Val::new_any()),
         }
     }
 
@@ -241,21 +282,14 @@ impl GraphBuilder {
                 self.graph.new_value_node(value)
             }
             E::Integer(ref x) => {
-                let value = if let Some(v) = x.value.to_i64() {
-                    graph::Val::with_type(From::from(ty::integer().value(v)))
-                } else {
-                    graph::Val::with_type(From::from(ty::integer()))
-                };
+                let value = graph::Val::with_type(From::from(ty::integer().value(x.value.clone())));
                 self.graph.new_value_node(value)
             }
             E::Nil(_) => {
                 let value = graph::Val::with_type(From::from(ty::NilType));
                 self.graph.new_value_node(value)
             }
-            E::Var(ref x) => {
-                let var = self.find_binding(&x.name).unwrap();
-                var
-            }
+            E::Var(ref x) => self.lookup(&x.name),
             E::Match(ref x) => {
                 let left = self.parse_pattern(&x.left);
                 let right = self.parse_expr(&x.right);
@@ -303,13 +337,15 @@ impl GraphBuilder {
                 let mut args = Vec::with_capacity(x.fields.len());
                 for f in &x.fields {
                     let field_id = {
-                        let name = f.name.clone().expect("Not Implemented");
+                        // NOTE: `None` means "the other fields" (i.e., `_ = Value`)
+                        let name = f.name.clone().unwrap_or_else(|| "_".to_string());
                         let name = self.graph
                             .new_value_node(graph::Val::with_type(ty::atom(&format!("__field_{}_{}",
                                                                                     x.name,
                                                                                     name))));
                         let arg = self.parse_expr(&f.value);
-                        self.graph.new_local_call_node(name, vec![arg])
+                        let node_id = self.graph.new_local_call_node(name, vec![arg]);
+                        self.graph.get_return_node(node_id).unwrap()
                     };
                     args.push(field_id);
                 }
@@ -379,8 +415,50 @@ impl GraphBuilder {
                 // TODO: implements
                 self.graph.new_value_node(graph::Val::new_any())
             }
-            _ => panic!("EXPR: {:?}", expr),
+            E::Char(ref x) => {
+                let value = graph::Val::with_type(From::from(ty::integer().value(x.value as i64)));
+                self.graph.new_value_node(value)
+            }
+            E::Float(_) => {
+                let value = graph::Val::with_type(From::from(ty::FloatType));
+                self.graph.new_value_node(value)
+            }
+            E::String(ref x) => {
+                let value = graph::Val::with_type(string_literal_type(&x.value));
+                self.graph.new_value_node(value)
+            }
+            E::UnaryOp(ref x) => {
+                let name = {
+                    let name = graph::Val::with_type(ty::atom(&format!("__op_{}", x.operator)));
+                    self.graph.new_value_node(name)
+                };
+                let arg0 = self.parse_expr(&x.operand);
+                let node_id = self.graph.new_local_call_node(name, vec![arg0]);
+                self.graph.get_return_node(node_id).unwrap()
+            }
+            E::Block(ref x) => self.parse_body(&x.body),
+            E::Binary(ref x) => {
+                for e in &x.elements {
+                    self.parse_expr(&e.element);
+                }
+                let value = ty::BitstringType::default().align(1);
+                let value = graph::Val::with_type(From::from(value));
+                self.graph.new_value_node(value)
+            }
+            // TODO: Support the remaining expressions (e.g., comprehensions, maps and funs)
+            _ => self.graph.new_value_node(graph::Val::new_any()),
         }
     }
 }
+fn string_literal_type(value: &str) -> ty::Type {
+    let min = value.chars().map(|c| c as i64).min();
+    let max = value.chars().map(|c| c as i64).max();
+    if let (Some(min), Some(max)) = (min, max) {
+        let element = From::from(ty::integer().min(min).max(max));
+        From::from(ty::ListType::NonEmpty(ty::NonEmptyListType { element }))
+    } else {
+        From::from(ty::NilType)
+    }
+}
+
 // cargo run -- analyze /usr/lib/erlang/lib/stdlib-2.8/ebin/*.beam
@@ -1,13 +1,18 @@
 use std::path::Path;
+use std::path::PathBuf;
 use std::error;
 use std::collections::HashSet;
 use std::collections::HashMap;
+use eetf;
 use erl_ast::AST;
 use erl_ast::ast;
 use ty;
 use ty::TypeClass;
 use ast::FromAst;
+use intern;
 use meta;
+use beam;
+use pool;
 
 pub type Arity = u8;
 pub type Result<T> = ::std::result::Result<T, Box<dyn error::Error>>;
@@ -15,55 +20,104 @@ pub type Result<T> = ::std::result::Result<T, Box<dyn error::Error>>;
 #[derive(Debug)]
 pub struct Module {
     pub name: String,
+    pub beam_file: Option<PathBuf>,
+    pub source_file: Option<String>, // The first `-file(...)` attribute
     pub behaviours: HashSet<String>,
     pub exports: HashSet<Local>,
     pub callbacks: HashSet<Local>,
     pub export_types: HashSet<Local>,
     pub imports: HashSet<Remote>,
     pub types: HashMap<Local, Box<dyn TypeClass>>,
+    pub type_lines: HashMap<Local, i32>, // The lines of the `-type` (and `-opaque`) attributes
     pub specs: HashMap<Local, Spec>,
     pub functions: HashMap<Local, meta::Function>,
+    pub dialyzer_options: DialyzerOptions,
+    pub dependent_modules: HashSet<String>,
+    pub client_modules: HashSet<String>,
+    _roots: intern::Roots, // Keeps the interned types in the graphs of `functions`
 }
 impl Module {
     pub fn from_beam_file<P: AsRef<Path>>(beam_file: P) -> Result<Self> {
-        let ast = try!(AST::from_beam_file(beam_file));
-        ModuleBuilder::new().build(ast)
+        Module::from_beam_file_with_jobs(beam_file, 1)
+    }
+
+    /// Loads `beam_file`, building the graphs of the functions on `jobs` threads.
+    pub fn from_beam_file_with_jobs<P: AsRef<Path>>(beam_file: P, jobs: usize) -> Result<Self> {
+        let ast = AST::from_beam_file(beam_file.as_ref())?;
+        let mut module = ModuleBuilder::new().jobs(jobs).build(ast)?;
+        module.beam_file = Some(beam_file.as_ref().to_path_buf());
+        Ok(module)
     }
 }
 
 #[derive(Default)]
 struct ModuleBuilder {
+    jobs: usize,
     name: Option<String>,
+    source_file: Option<String>,
     behaviours: HashSet<String>,
     exports: HashSet<Local>,
     callbacks: HashSet<Local>,
     export_types: HashSet<Local>,
     imports: HashSet<Remote>,
     types: HashMap<Local, Box<dyn TypeClass>>,
+    type_lines: HashMap<Local, i32>,
     specs: HashMap<Local, Spec>,
     functions: HashMap<Local, meta::Function>,
+    dialyzer_options: DialyzerOptions,
 }
 impl ModuleBuilder {
     pub fn new() -> Self {
-        ModuleBuilder::default()
+        ModuleBuilder { jobs: 1, ..ModuleBuilder::default() }
+    }
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
     }
     pub fn build(mut self, ast: AST) -> Result<Module> {
+        // NOTE: The types in the graphs are kept by the pin until they are put in `Roots`
+        let _pin = intern::pin();
         for form in &ast.module.forms {
-            try!(self.handle_form(form));
+            self.handle_form(form)?;
         }
+
+        // NOTE: Function graphs are independent of each other, so they are built in parallel
+        let decls = ast.module
+            .forms
+            .iter()
+            .filter_map(|f| match *f {
+                ast::form::Form::Fun(ref x) => Some(x),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let functions = pool::map(self.jobs, decls, |x| {
+            let key = Local::new(&x.name, x.clauses[0].patterns.len() as Arity);
+            (key, FromAst::from_ast(x))
+        });
+        self.functions.extend(functions);
         // TODO: Resolve imported functions
 
-        let name = try!(self.name.ok_or("No `-module(...)` directive"));
+        let name = self.name.ok_or("No `-module(...)` directive")?;
+        let mut dependent_modules = beam::collect_dependent_modules_from_ast(&ast);
+        dependent_modules.remove(&name);
+        let roots = intern::Roots::new(self.functions.values().flat_map(|f| f.graph.type_ids()));
         Ok(Module {
-            name: name,
+            name,
+            beam_file: None,
+            source_file: self.source_file,
             behaviours: self.behaviours,
             exports: self.exports,
             callbacks: self.callbacks,
             export_types: self.export_types,
             imports: self.imports,
             types: self.types,
+            type_lines: self.type_lines,
             specs: self.specs,
             functions: self.functions,
+            dialyzer_options: self.dialyzer_options,
+            dependent_modules,
+            client_modules: HashSet::new(),
+            _roots: roots,
         })
     }
     fn handle_form(&mut self, form: &ast::form::Form) -> Result<()> {
@@ -72,6 +126,9 @@ impl ModuleBuilder {
             Form::Module(ref x) => {
                 self.name = Some(x.name.to_string());
             }
+            Form::File(ref x) if self.source_file.is_none() => {
+                self.source_file = Some(x.original_file.clone());
+            }
             Form::Behaviour(ref x) => {
                 self.behaviours.insert(x.name.clone());
             }
@@ -90,12 +147,14 @@ impl ModuleBuilder {
             Form::Type(ref x) => {
                 let key = Local::new(&x.name, x.vars.len() as Arity);
                 let value = FromAst::from_ast(x);
-                self.types.insert(key, value);
+                self.types.insert(key.clone(), value);
+                self.type_lines.insert(key, x.line);
             }
             Form::Spec(ref x) => {
                 assert!(!x.types.is_empty());
-                if x.module.is_some() {
-                    unimplemented!();
+                if x.module.is_some() && x.module != self.name {
+                    // NOTE: Specs for functions of other modules are meaningless
+                    return Ok(());
                 }
                 let arity = x.types[0].args.len() as Arity;
                 let key = Local::new(&x.name, arity);
@@ -119,11 +178,14 @@ impl ModuleBuilder {
                         SpecClause {
                             args: c.args.iter().map(FromAst::from_ast).collect(),
                             return_type: FromAst::from_ast(&c.return_type),
-                            constraints: constraints,
+                            constraints,
                         }
                     })
                     .collect();
-                self.specs.insert(key, Spec { clauses: clauses });
+                self.specs.insert(key, Spec { clauses });
+            }
+            Form::Attr(ref x) if x.name == "dialyzer" => {
+                self.dialyzer_options.add(&x.value);
             }
             Form::Record(ref _x) => {
                 // TODO:
@@ -131,9 +193,6 @@ impl ModuleBuilder {
             }
             Form::Fun(ref x) => {
                 assert!(!x.clauses.is_empty());
-                let key = Local::new(&x.name, x.clauses[0].patterns.len() as Arity);
-                let value = FromAst::from_ast(x);
-                self.functions.insert(key, value);
             }
             _ => {}
         }
@@ -141,7 +200,64 @@ impl ModuleBuilder {
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+/// Warning options given by `-dialyzer(...)` attributes.
+///
+/// See: [Requesting or Suppressing Warnings in Source Files](http://erlang.org/doc/man/dialyzer.html)
+#[derive(Debug, Default, Clone)]
+pub struct DialyzerOptions {
+    /// Options for the whole module (e.g., `-dialyzer(no_return).`)
+    pub module: HashSet<String>,
+
+    /// Options for functions (e.g., `-dialyzer({nowarn_function, f/1}).`)
+    pub functions: HashMap<Local, HashSet<String>>,
+}
+impl DialyzerOptions {
+    /// Returns `true` if `option` (e.g., `no_return`) or `nowarn_function` is set for `fun`.
+    pub fn is_set(&self, fun: &Local, option: &str) -> bool {
+        self.module.contains(option) ||
+        self.functions.get(fun).is_some_and(|o| o.contains(option) || o.contains("nowarn_function"))
+    }
+
+    // NOTE: `value` is an option, a `{Option, Functions}` tuple or a list of them
+    fn add(&mut self, value: &eetf::Term) {
+        match *value {
+            eetf::Term::Atom(ref x) => {
+                self.module.insert(x.name.clone());
+            }
+            eetf::Term::List(ref x) => {
+                for e in &x.elements {
+                    self.add(e);
+                }
+            }
+            eetf::Term::Tuple(ref x) if x.elements.len() == 2 => {
+                if let eetf::Term::Atom(ref option) = x.elements[0] {
+                    for f in function_refs(&x.elements[1]) {
+                        self.functions.entry(f).or_default().insert(option.name.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// NOTE: `f/1` is represented as `{f, 1}` in attributes
+fn function_refs(value: &eetf::Term) -> Vec<Local> {
+    match *value {
+        eetf::Term::Tuple(ref x) => {
+            match x.elements[..] {
+                [eetf::Term::Atom(ref name), eetf::Term::FixInteger(ref arity)] => {
+                    vec![Local::new(&name.name, arity.value as Arity)]
+                }
+                _ => Vec::new(),
+            }
+        }
+        eetf::Term::List(ref x) => x.elements.iter().flat_map(function_refs).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct Local {
     pub name: String,
     pub arity: Arity,
@@ -150,12 +266,12 @@ impl Local {
     pub fn new(name: &str, arity: Arity) -> Self {
         Local {
             name: name.to_string(),
-            arity: arity,
+            arity,
         }
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct Remote {
     pub module: String,
     pub name: String,
@@ -166,15 +282,50 @@ impl Remote {
         Remote {
             module: module.to_string(),
             name: name.to_string(),
-            arity: arity,
+            arity,
         }
     }
+    pub fn to_local(&self) -> Local {
+        Local::new(&self.name, self.arity)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Spec {
     pub clauses: Vec<SpecClause>,
 }
+impl Spec {
+    /// Makes the spec `(any(), ...) -> any()`.
+    pub fn any(arity: Arity) -> Self {
+        let clause = SpecClause {
+            args: (0..arity).map(|_| From::from(ty::AnyType)).collect(),
+            return_type: From::from(ty::AnyType),
+            constraints: Vec::new(),
+        };
+        Spec { clauses: vec![clause] }
+    }
+
+    /// Formats `self` as the `-spec` attribute of the function `name`.
+    pub fn to_attribute(&self, name: &str) -> String {
+        let clauses = self.clauses
+            .iter()
+            .map(|c| {
+                let args = c.args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+                let mut clause = format!("({}) -> {}", args.join(", "), c.return_type);
+                if !c.constraints.is_empty() {
+                    let constraints = c.constraints
+                        .iter()
+                        .map(|c| format!("{} :: {}", c.var, c.subtype))
+                        .collect::<Vec<_>>();
+                    clause.push_str(&format!(" when {}", constraints.join(", ")));
+                }
+                clause
+            })
+            .collect::<Vec<_>>();
+        let name = ty::atom_name(name);
+        format!("-spec {}{}.", name, clauses.join(&format!(";\n      {}", name)))
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct SpecClause {
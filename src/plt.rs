@@ -0,0 +1,699 @@
+//! Persistent analysis cache (like the PLT of Dialyzer).
+//!
+//! The cache is a JSON document of the following form:
+//!
+//! ```text
+//! {"format": "diagnoser-plt",
+//!  "version": 3,
+//!  "modules": [{"name": "lists",
+//!               "beam_file": "/usr/lib/erlang/lib/stdlib-2.8/ebin/lists.beam",
+//!               "hash": "<FNV-1a (64 bits) of the beam file in hex>",
+//!               "dependent_modules": ["erlang"],
+//!               "exported_types": [{"name": "t", "vars": ["$0"], "opaque": false, "body": TYPE}],
+//!               "specs": [{"name": "map", "arity": 2,
+//!                          "clauses": [{"args": [TYPE, TYPE], "return": TYPE,
+//!                                       "constraints": [{"var": "A", "type": TYPE}]}]}],
+//!               "signatures": [{"name": "map", "arity": 2,
+//!                               "args": [TYPE, TYPE], "return": TYPE}]}]}
+//! ```
+//!
+//! `TYPE` is a JSON array whose first element is the kind of the type (see `type_to_json`).
+//! The specs of the exported functions are kept as written (i.e., unresolved), so that
+//! the opaque types in them are known to the clients (see `Env::remote_spec`).
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use num::BigInt;
+use num::ToPrimitive;
+use env::Env;
+use json::Json;
+use module::Arity;
+use module::Constraint;
+use module::Local;
+use module::Module;
+use module::Remote;
+use module::Result;
+use module::Spec;
+use module::SpecClause;
+use ty;
+use ty::FunSpec;
+use ty::Type;
+
+pub const FORMAT: &str = "diagnoser-plt";
+/// Caches of other versions are rejected (bumped when the encoding changes).
+pub const VERSION: i64 = 3;
+
+#[derive(Debug, Default)]
+pub struct Plt {
+    pub modules: BTreeMap<String, CachedModule>,
+}
+impl Plt {
+    pub fn new() -> Self {
+        Plt::default()
+    }
+
+    /// Makes a cache of the analysis results of `env`.
+    ///
+    /// The entries for the loaded modules are made from `signatures` and the others are
+    /// copied from the cache used by `env` (see `Env::use_plt`).
+    pub fn from_env(env: &Env, signatures: &BTreeMap<Remote, FunSpec>) -> Result<Self> {
+        let mut modules = env.cached_modules
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<BTreeMap<_, _>>();
+        for m in env.modules.values() {
+            modules.insert(m.name.clone(), CachedModule::new(m, signatures)?);
+        }
+        Ok(Plt { modules })
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let json = Json::parse(&text)?;
+        Plt::from_json(&json).map_err(|e| format!("Invalid PLT: {}: {}", path.display(), e).into())
+    }
+
+    // NOTE: The file is replaced at once, so that concurrent readers never see partial data
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, format!("{}\n", self.to_json()))?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Returns the modules whose beam files were modified or removed after they were cached,
+    /// and the modules which depend on them (transitively).
+    pub fn stale_modules(&self) -> BTreeSet<String> {
+        let mut stale = self.modules
+            .values()
+            .filter(|m| m.is_modified())
+            .map(|m| m.name.clone())
+            .collect::<BTreeSet<_>>();
+        loop {
+            let clients = self.modules
+                .values()
+                .filter(|m| !stale.contains(&m.name))
+                .filter(|m| m.dependent_modules.iter().any(|d| stale.contains(d)))
+                .map(|m| m.name.clone())
+                .collect::<Vec<_>>();
+            if clients.is_empty() {
+                return stale;
+            }
+            stale.extend(clients);
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        let modules = self.modules.values().map(CachedModule::to_json).collect();
+        Json::object(vec![("format", Json::string(FORMAT)),
+                          ("version", Json::Integer(VERSION)),
+                          ("modules", Json::Array(modules))])
+    }
+    pub fn from_json(json: &Json) -> Result<Self> {
+        if json.get("format").and_then(Json::as_str) != Some(FORMAT) {
+            return Err("Unknown format".into());
+        }
+        let version = json.get("version").and_then(Json::as_i64);
+        if version != Some(VERSION) {
+            let version = version.map_or_else(|| "unknown".to_string(), |v| v.to_string());
+            return Err(format!("The cache is of version {} (expected {}); rebuild it by \
+                                `plt build`",
+                               version,
+                               VERSION)
+                .into());
+        }
+        let mut modules = BTreeMap::new();
+        for m in array(json, "modules")? {
+            let m = CachedModule::from_json(m)?;
+            modules.insert(m.name.clone(), m);
+        }
+        Ok(Plt { modules })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedModule {
+    pub name: String,
+    pub beam_file: PathBuf,
+    pub hash: u64,
+    pub dependent_modules: BTreeSet<String>,
+    pub exported_types: BTreeMap<Local, ty::UserDefinedClass>,
+    pub specs: BTreeMap<Local, Spec>, // The specs of the exported functions
+    pub signatures: BTreeMap<Local, FunSpec>,
+}
+impl CachedModule {
+    pub fn new(module: &Module, signatures: &BTreeMap<Remote, FunSpec>) -> Result<Self> {
+        let beam_file = module.beam_file
+            .clone()
+            .ok_or_else(|| format!("The beam file of {:?} is unknown", module.name))?;
+        let beam_file = fs::canonicalize(&beam_file).unwrap_or(beam_file);
+        let hash = hash_file(&beam_file)?;
+        let mut exported_types = BTreeMap::new();
+        for key in &module.export_types {
+            if let Some(class) = module.types.get(key) {
+                // NOTE: The type variables are renamed to `$0`, `$1`, ...
+                let vars = (0..key.arity).map(|i| format!("${}", i)).collect::<Vec<_>>();
+                let args = vars.iter().map(|v| From::from(ty::Var::new(v))).collect::<Vec<_>>();
+                let (is_opaque, body) = match class.make_instance(&args) {
+                    Type::UserDefined(x) => (x.is_opaque, x.body),
+                    t => (false, t),
+                };
+                let class = ty::UserDefinedClass {
+                    is_opaque,
+                    name: key.name.clone(),
+                    vars,
+                    body,
+                };
+                exported_types.insert(key.clone(), class);
+            }
+        }
+        let specs = module.specs
+            .iter()
+            .filter(|&(f, _)| module.exports.contains(f))
+            .map(|(f, s)| (f.clone(), s.clone()))
+            .collect();
+        let signatures = signatures.iter()
+            .filter(|&(f, _)| f.module == module.name)
+            .map(|(f, s)| (f.to_local(), s.clone()))
+            .collect();
+        Ok(CachedModule {
+            name: module.name.clone(),
+            beam_file,
+            hash,
+            dependent_modules: module.dependent_modules.iter().cloned().collect(),
+            exported_types,
+            specs,
+            signatures,
+        })
+    }
+
+    /// Returns `true` if the beam file was modified (or removed) after it was cached.
+    pub fn is_modified(&self) -> bool {
+        hash_file(&self.beam_file).map_or(true, |h| h != self.hash)
+    }
+
+    pub fn to_json(&self) -> Json {
+        let types = self.exported_types
+            .values()
+            .map(|t| {
+                Json::object(vec![("name", Json::string(&t.name)),
+                                  ("vars", Json::strings(&t.vars)),
+                                  ("opaque", Json::Bool(t.is_opaque)),
+                                  ("body", type_to_json(&t.body))])
+            })
+            .collect();
+        let specs = self.specs
+            .iter()
+            .map(|(f, s)| {
+                let clauses = s.clauses.iter().map(spec_clause_to_json).collect();
+                Json::object(vec![("name", Json::string(&f.name)),
+                                  ("arity", Json::from(f.arity as i64)),
+                                  ("clauses", Json::Array(clauses))])
+            })
+            .collect();
+        let signatures = self.signatures
+            .iter()
+            .map(|(f, s)| {
+                let args = s.args
+                    .as_ref()
+                    .map(|args| Json::Array(args.iter().map(type_to_json).collect()));
+                Json::object(vec![("name", Json::string(&f.name)),
+                                  ("arity", Json::from(f.arity as i64)),
+                                  ("args", Json::from(args)),
+                                  ("return", type_to_json(&s.return_type))])
+            })
+            .collect();
+        Json::object(vec![("name", Json::string(&self.name)),
+                          ("beam_file", Json::string(self.beam_file.display())),
+                          ("hash", Json::string(format!("{:016x}", self.hash))),
+                          ("dependent_modules", Json::strings(&self.dependent_modules)),
+                          ("exported_types", Json::Array(types)),
+                          ("specs", Json::Array(specs)),
+                          ("signatures", Json::Array(signatures))])
+    }
+    pub fn from_json(json: &Json) -> Result<Self> {
+        let hash = string(json, "hash")?;
+        let hash = u64::from_str_radix(hash, 16).map_err(|_| format!("Invalid hash: {}", hash))?;
+        let mut exported_types = BTreeMap::new();
+        for t in array(json, "exported_types")? {
+            let vars = array(t, "vars")?
+                .iter()
+                .map(|v| v.as_str().map(|v| v.to_string()).ok_or("Invalid type variable"))
+                .collect::<::std::result::Result<Vec<_>, _>>()?;
+            let class = ty::UserDefinedClass {
+                is_opaque: t.get("opaque").and_then(Json::as_bool).ok_or("Missing `opaque`")?,
+                name: string(t, "name")?.to_string(),
+                vars,
+                body: type_from_json(t.get("body").ok_or("Missing `body`")?)?,
+            };
+            let key = Local::new(&class.name, class.vars.len() as Arity);
+            exported_types.insert(key, class);
+        }
+        let mut specs = BTreeMap::new();
+        for s in array(json, "specs")? {
+            let arity = s.get("arity").and_then(Json::as_i64).ok_or("Missing `arity`")?;
+            let clauses = array(s, "clauses")?
+                .iter()
+                .map(spec_clause_from_json)
+                .collect::<Result<Vec<_>>>()?;
+            specs.insert(Local::new(string(s, "name")?, arity as Arity), Spec { clauses });
+        }
+        let mut signatures = BTreeMap::new();
+        for s in array(json, "signatures")? {
+            let arity = s.get("arity").and_then(Json::as_i64).ok_or("Missing `arity`")?;
+            let args = match s.get("args") {
+                Some(args) if !args.is_null() => Some(types_from_json(args)?),
+                _ => None,
+            };
+            let signature = FunSpec {
+                args,
+                return_type: type_from_json(s.get("return").ok_or("Missing `return`")?)?,
+            };
+            signatures.insert(Local::new(string(s, "name")?, arity as Arity), signature);
+        }
+        Ok(CachedModule {
+            name: string(json, "name")?.to_string(),
+            beam_file: PathBuf::from(string(json, "beam_file")?),
+            hash,
+            dependent_modules: array(json, "dependent_modules")?
+                .iter()
+                .filter_map(|m| m.as_str().map(|m| m.to_string()))
+                .collect(),
+            exported_types,
+            specs,
+            signatures,
+        })
+    }
+}
+
+fn spec_clause_to_json(clause: &SpecClause) -> Json {
+    let constraints = clause.constraints
+        .iter()
+        .map(|c| {
+            Json::object(vec![("var", Json::string(&c.var)), ("type", type_to_json(&c.subtype))])
+        })
+        .collect();
+    Json::object(vec![("args", Json::Array(clause.args.iter().map(type_to_json).collect())),
+                      ("return", type_to_json(&clause.return_type)),
+                      ("constraints", Json::Array(constraints))])
+}
+
+fn spec_clause_from_json(json: &Json) -> Result<SpecClause> {
+    let constraints = array(json, "constraints")?
+        .iter()
+        .map(|c| {
+            Ok(Constraint {
+                var: string(c, "var")?.to_string(),
+                subtype: type_from_json(c.get("type").ok_or("Missing `type`")?)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(SpecClause {
+        args: types_from_json(json.get("args").ok_or("Missing `args`")?)?,
+        return_type: type_from_json(json.get("return").ok_or("Missing `return`")?)?,
+        constraints,
+    })
+}
+
+/// Returns the FNV-1a (64 bits) hash of the content of `path`.
+pub fn hash_file<P: AsRef<Path>>(path: P) -> Result<u64> {
+    Ok(hash_bytes(&fs::read(path)?))
+}
+
+/// Returns the FNV-1a (64 bits) hash of `bytes`.
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Encodes `t` as a JSON array whose first element is the kind of the type:
+///
+/// - `["any"]`, `["none"]`, `["pid"]`, `["port"]`, `["reference"]`, `["nil"]`, `["float"]`
+/// - `["atom", NAME|null]`
+/// - `["bitstring", BITS, ALIGN]` (`0` means absent)
+/// - `["integer", MIN|null, MAX|null]`
+///   (bounds out of the range of 64-bit integers are decimal strings)
+/// - `["fun", [[ARGS|null, RETURN], ...]]`
+/// - `["list", KIND, ELEMENT, LAST|null]`
+///   (`KIND` is `proper`, `nonempty`, `maybe_improper` or `nonempty_improper`)
+/// - `["map", [[KEY, VALUE, ASSOC], ...]]` (`ASSOC` is `false` for `:=`)
+/// - `["record", NAME, [[FIELD, TYPE], ...]]`
+/// - `["tuple", ELEMENTS|null]`
+/// - `["union", TYPES]`
+/// - `["user", NAME, OPAQUE, BODY, ARGS]`
+/// - `["rec", VAR, BODY]`
+/// - `["local", NAME, ARGS]`, `["remote", MODULE, NAME, ARGS]`
+/// - `["var", NAME, VALUE|null]`
+pub fn type_to_json(t: &Type) -> Json {
+    let types = |ts: &[Type]| Json::Array(ts.iter().map(type_to_json).collect());
+    let values: Vec<Json> = match *t {
+        Type::Any(_) => vec!["any".into()],
+        Type::None(_) => vec!["none".into()],
+        Type::Pid(_) => vec!["pid".into()],
+        Type::Port(_) => vec!["port".into()],
+        Type::Reference(_) => vec!["reference".into()],
+        Type::Nil(_) => vec!["nil".into()],
+        Type::Float(_) => vec!["float".into()],
+        Type::Atom(ref x) => vec!["atom".into(), x.value.clone().into()],
+        Type::Bitstring(ref x) => {
+            vec!["bitstring".into(), x.bits.into(), x.align.into()]
+        }
+        Type::Integer(ref x) => {
+            vec!["integer".into(), bound_to_json(&x.min), bound_to_json(&x.max)]
+        }
+        Type::Fun(ref x) => {
+            let clauses = x.clauses
+                .iter()
+                .map(|c| Json::Array(vec![c.args.as_ref().map(|a| types(a)).into(),
+                                          type_to_json(&c.return_type)]))
+                .collect();
+            vec!["fun".into(), Json::Array(clauses)]
+        }
+        Type::List(ref x) => {
+            let (kind, element, last) = match **x {
+                ty::ListType::Proper(ref l) => ("proper", &l.element, None),
+                ty::ListType::NonEmpty(ref l) => ("nonempty", &l.element, None),
+                ty::ListType::MaybeImproper(ref l) => ("maybe_improper", &l.element, Some(&l.last)),
+                ty::ListType::NonEmptyImproper(ref l) => {
+                    ("nonempty_improper", &l.element, Some(&l.last))
+                }
+            };
+            vec!["list".into(), kind.into(), type_to_json(element), last.map(type_to_json).into()]
+        }
+        Type::Map(ref x) => {
+            let pairs = x.pairs
+                .iter()
+                .map(|p| {
+                    let (key, value) = (type_to_json(&p.key), type_to_json(&p.value));
+                    Json::Array(vec![key, value, p.is_assoc.into()])
+                })
+                .collect();
+            vec!["map".into(), Json::Array(pairs)]
+        }
+        Type::Record(ref x) => {
+            let fields = x.fields
+                .iter()
+                .map(|f| Json::Array(vec![Json::string(&f.name), type_to_json(&f.value)]))
+                .collect();
+            vec!["record".into(), Json::string(&x.name), Json::Array(fields)]
+        }
+        Type::Tuple(ref x) => vec!["tuple".into(), x.elements.as_ref().map(|es| types(es)).into()],
+        Type::Union(ref x) => vec!["union".into(), types(&x.types)],
+        Type::UserDefined(ref x) => {
+            vec!["user".into(),
+                 Json::string(&x.name),
+                 x.is_opaque.into(),
+                 type_to_json(&x.body),
+                 types(&x.args)]
+        }
+        Type::Rec(ref x) => vec!["rec".into(), Json::string(&x.var), type_to_json(&x.body)],
+        Type::Local(ref x) => vec!["local".into(), Json::string(&x.name), types(&x.args)],
+        Type::Remote(ref x) => {
+            vec!["remote".into(), Json::string(&x.module), Json::string(&x.name), types(&x.args)]
+        }
+        Type::Var(ref x) => {
+            vec!["var".into(), Json::string(&x.name), x.value.as_ref().map(type_to_json).into()]
+        }
+    };
+    Json::Array(values)
+}
+
+/// Decodes a type encoded by `type_to_json`.
+pub fn type_from_json(json: &Json) -> Result<Type> {
+    let values = json.as_array().ok_or("A type must be an array")?;
+    let kind = values.first().and_then(Json::as_str).ok_or("Missing type kind")?;
+    let arg = |i: usize| values.get(i).ok_or_else(|| format!("Too few elements: {}", json));
+    let int = |i: usize| -> Result<i64> {
+        let v = arg(i)?;
+        v.as_i64().ok_or_else(|| format!("Not an integer: {}", v).into())
+    };
+    let opt_bound = |i: usize| -> Result<Option<BigInt>> {
+        let v = arg(i)?;
+        if v.is_null() {
+            return Ok(None);
+        }
+        let bound = v.as_i64().map(From::from).or_else(|| v.as_str().and_then(|s| s.parse().ok()));
+        bound.map(Some).ok_or_else(|| format!("Not an integer: {}", v).into())
+    };
+    let opt_type = |i: usize| -> Result<Option<Type>> {
+        let v = arg(i)?;
+        if v.is_null() { Ok(None) } else { type_from_json(v).map(Some) }
+    };
+    let str_arg = |i: usize| -> Result<String> {
+        let v = arg(i)?;
+        v.as_str().map(|s| s.to_string()).ok_or_else(|| format!("Not a string: {}", v).into())
+    };
+    let t = match kind {
+        "any" => From::from(ty::AnyType),
+        "none" => From::from(ty::NoneType),
+        "pid" => From::from(ty::PidType),
+        "port" => From::from(ty::PortType),
+        "reference" => From::from(ty::ReferenceType),
+        "nil" => From::from(ty::NilType),
+        "float" => From::from(ty::FloatType),
+        "atom" => {
+            let value = arg(1)?;
+            From::from(ty::AtomType { value: value.as_str().map(|s| s.to_string()) })
+        }
+        "bitstring" => {
+            From::from(ty::BitstringType {
+                bits: int(1)? as usize,
+                align: int(2)? as usize,
+            })
+        }
+        "integer" => {
+            From::from(ty::IntegerType {
+                min: opt_bound(1)?,
+                max: opt_bound(2)?,
+            })
+        }
+        "fun" => {
+            let mut clauses = Vec::new();
+            for c in arg(1)?.as_array().ok_or("Invalid fun clauses")? {
+                let c = c.as_array().filter(|c| c.len() == 2).ok_or("Invalid fun clause")?;
+                let args = if c[0].is_null() { None } else { Some(types_from_json(&c[0])?) };
+                clauses.push(FunSpec {
+                    args,
+                    return_type: type_from_json(&c[1])?,
+                });
+            }
+            From::from(ty::FunType { clauses })
+        }
+        "list" => {
+            let element = type_from_json(arg(2)?)?;
+            let last = opt_type(3)?;
+            let list = match (str_arg(1)?.as_str(), last) {
+                ("proper", None) => ty::ListType::Proper(ty::ProperListType { element }),
+                ("nonempty", None) => ty::ListType::NonEmpty(ty::NonEmptyListType { element }),
+                ("maybe_improper", Some(last)) => {
+                    ty::ListType::MaybeImproper(ty::MaybeImproperListType { element, last })
+                }
+                ("nonempty_improper", Some(last)) => {
+                    ty::ListType::NonEmptyImproper(ty::NonEmptyImproperListType { element, last })
+                }
+                _ => return Err(format!("Invalid list type: {}", json).into()),
+            };
+            From::from(list)
+        }
+        "map" => {
+            let mut pairs = Vec::new();
+            for p in arg(1)?.as_array().ok_or("Invalid map pairs")? {
+                let p = p.as_array().filter(|p| p.len() == 3).ok_or("Invalid map pair")?;
+                pairs.push(ty::MapPair {
+                    is_assoc: p[2].as_bool().ok_or("Invalid map association")?,
+                    key: type_from_json(&p[0])?,
+                    value: type_from_json(&p[1])?,
+                });
+            }
+            From::from(ty::MapType { pairs })
+        }
+        "record" => {
+            let mut fields = Vec::new();
+            for f in arg(2)?.as_array().ok_or("Invalid record fields")? {
+                let f = f.as_array().filter(|f| f.len() == 2).ok_or("Invalid record field")?;
+                fields.push(ty::RecordField {
+                    name: f[0].as_str().ok_or("Invalid field name")?.to_string(),
+                    value: type_from_json(&f[1])?,
+                });
+            }
+            From::from(ty::RecordType {
+                name: str_arg(1)?,
+                fields,
+            })
+        }
+        "tuple" => {
+            let elements = if arg(1)?.is_null() { None } else { Some(types_from_json(arg(1)?)?) };
+            From::from(ty::TupleType { elements })
+        }
+        "union" => From::from(ty::UnionType::new(types_from_json(arg(1)?)?)),
+        "user" => {
+            From::from(ty::UserDefinedType {
+                name: str_arg(1)?,
+                is_opaque: arg(2)?.as_bool().ok_or("Invalid opaque flag")?,
+                body: type_from_json(arg(3)?)?,
+                args: types_from_json(arg(4)?)?,
+            })
+        }
+        "rec" => {
+            let x = ty::RecType::new(&str_arg(1)?, type_from_json(arg(2)?)?);
+            if !x.is_productive() {
+                return Err(format!("Non-productive recursive type: {}", json).into());
+            }
+            From::from(x)
+        }
+        "local" => ty::local(&str_arg(1)?, &types_from_json(arg(2)?)?),
+        "remote" => ty::remote(&str_arg(1)?, &str_arg(2)?, &types_from_json(arg(3)?)?),
+        "var" => {
+            From::from(ty::Var {
+                name: str_arg(1)?,
+                value: opt_type(2)?,
+            })
+        }
+        _ => return Err(format!("Unknown type kind: {:?}", kind).into()),
+    };
+    Ok(t)
+}
+
+fn bound_to_json(bound: &Option<BigInt>) -> Json {
+    match *bound {
+        Some(ref x) => x.to_i64().map_or_else(|| Json::string(x), Json::from),
+        None => Json::Null,
+    }
+}
+
+fn types_from_json(json: &Json) -> Result<Vec<Type>> {
+    json.as_array().ok_or("Types must be an array")?.iter().map(type_from_json).collect()
+}
+
+fn array<'a>(json: &'a Json, key: &str) -> Result<&'a [Json]> {
+    json.get(key).and_then(Json::as_array).ok_or_else(|| format!("Missing `{}`", key).into())
+}
+
+fn string<'a>(json: &'a Json, key: &str) -> Result<&'a str> {
+    json.get(key).and_then(Json::as_str).ok_or_else(|| format!("Missing `{}`", key).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+    use lattice;
+    use super::*;
+
+    const HELLO_BEAM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/beam/testdata/hello.beam");
+
+    // NOTE: Each test uses its own directory because tests run in parallel
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = ::std::env::temp_dir().join(format!("diagnoser-plt-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cached_module(name: &str, beam_file: &Path, dependent_modules: &[&str]) -> CachedModule {
+        CachedModule {
+            name: name.to_string(),
+            beam_file: beam_file.to_path_buf(),
+            hash: hash_file(beam_file).unwrap(),
+            dependent_modules: dependent_modules.iter().map(|m| m.to_string()).collect(),
+            exported_types: BTreeMap::new(),
+            specs: BTreeMap::new(),
+            signatures: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn plt_round_trips_through_file() {
+        let dir = temp_dir("round-trip");
+        let mut env = Env::new();
+        env.add_module(Module::from_beam_file(HELLO_BEAM).unwrap());
+        let mut signatures = BTreeMap::new();
+        signatures.insert(Remote::new("hello", "world", 0),
+                          FunSpec {
+                              args: Some(vec![]),
+                              return_type: ty::atom("ok"),
+                          });
+        let mut plt = Plt::from_env(&env, &signatures).unwrap();
+
+        let var = Type::from(ty::Var::new("$0"));
+        let mut other = cached_module("other", Path::new(HELLO_BEAM), &["hello"]);
+        other.exported_types.insert(Local::new("t", 1),
+                                    ty::UserDefinedClass {
+                                        is_opaque: true,
+                                        name: "t".to_string(),
+                                        vars: vec!["$0".to_string()],
+                                        body: lattice::tuple(vec![var.clone(),
+                                                                  From::from(ty::integer())]),
+                                    });
+        other.signatures.insert(Local::new("f", 1),
+                                FunSpec {
+                                    args: Some(vec![var]),
+                                    return_type: lattice::any(),
+                                });
+        other.signatures.insert(Local::new("g", 0),
+                                FunSpec {
+                                    args: None,
+                                    return_type: lattice::none(),
+                                });
+        plt.modules.insert(other.name.clone(), other);
+
+        let path = dir.join("plt.json");
+        plt.write_to_file(&path).unwrap();
+        let loaded = Plt::read_from_file(&path).unwrap();
+        assert_eq!(loaded.modules.keys().collect::<Vec<_>>(), ["hello", "other"]);
+        assert_eq!(loaded.to_json().to_string(), plt.to_json().to_string());
+        assert_eq!(loaded.modules["hello"].signatures[&Local::new("world", 0)].to_string(),
+                   "() -> 'ok'");
+        assert!(loaded.modules["other"].exported_types[&Local::new("t", 1)].is_opaque);
+    }
+
+    #[test]
+    fn caches_of_other_versions_are_rejected() {
+        let json = Json::object(vec![("format", Json::string(FORMAT)),
+                                     ("version", Json::Integer(VERSION - 1)),
+                                     ("modules", Json::Array(vec![]))]);
+        let e = Plt::from_json(&json).unwrap_err().to_string();
+        assert!(e.contains(&format!("version {} (expected {})", VERSION - 1, VERSION)),
+                "{}",
+                e);
+
+        let json = Json::object(vec![("format", Json::string(FORMAT)),
+                                     ("modules", Json::Array(vec![]))]);
+        assert!(Plt::from_json(&json).unwrap_err().to_string().contains("version unknown"));
+    }
+
+    #[test]
+    fn modified_modules_invalidate_their_transitive_clients() {
+        let dir = temp_dir("stale");
+        let beam_file = |name: &str| {
+            let path = dir.join(format!("{}.beam", name));
+            fs::copy(HELLO_BEAM, &path).unwrap();
+            path
+        };
+        let (a, b, c, d) = (beam_file("a"), beam_file("b"), beam_file("c"), beam_file("d"));
+        let mut plt = Plt::new();
+        for m in [cached_module("a", &a, &["erlang"]),
+                      cached_module("b", &b, &["a"]),
+                      cached_module("c", &c, &["b", "lists"]),
+                      cached_module("d", &d, &["erlang"])] {
+            plt.modules.insert(m.name.clone(), m);
+        }
+        assert!(plt.stale_modules().is_empty());
+
+        fs::write(&a, b"modified").unwrap();
+        assert_eq!(plt.stale_modules().into_iter().collect::<Vec<_>>(), ["a", "b", "c"]);
+
+        fs::remove_file(&d).unwrap();
+        assert_eq!(plt.stale_modules().into_iter().collect::<Vec<_>>(), ["a", "b", "c", "d"]);
+    }
+}
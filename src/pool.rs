@@ -0,0 +1,50 @@
+//! Minimal thread pool for data-parallel jobs.
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::thread;
+
+/// Returns the default number of jobs (the available parallelism of the machine).
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Applies `f` to each of `items` on (at most) `jobs` threads.
+///
+/// The results are in the same order as `items` regardless of the scheduling.
+pub fn map<T, U, F>(jobs: usize, items: Vec<T>, f: F) -> Vec<U>
+    where T: Send,
+          U: Send,
+          F: Fn(T) -> U + Sync
+{
+    let jobs = jobs.min(items.len());
+    if jobs <= 1 {
+        return items.into_iter().map(f).collect();
+    }
+
+    let len = items.len();
+    let items = items.into_iter().map(|x| Mutex::new(Some(x))).collect::<Vec<_>>();
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let workers = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::SeqCst);
+                        if i >= len {
+                            return results;
+                        }
+                        let item = items[i].lock().unwrap().take().expect("Never fails");
+                        results.push((i, f(item)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers.into_iter()
+            .flat_map(|w| w.join().unwrap_or_else(|e| ::std::panic::resume_unwind(e)))
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, x)| x).collect()
+}
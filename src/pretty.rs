@@ -0,0 +1,545 @@
+//! Pretty printing of (large) inferred types.
+//!
+//! `Aliases` folds expanded types back into the names of known types (e.g., `'false'|'true'`
+//! into `boolean()`), and `Printer` lays a type out within a line width.
+use std::collections::BTreeSet;
+use std::mem;
+use env::Env;
+use lattice;
+use resolve::Resolver;
+use ty;
+use ty::FunSpec;
+use ty::Type;
+
+/// Built-in types which are folded (the others are short enough when expanded).
+const BUILTIN_ALIASES: &[&str] = &["boolean", "number", "timeout", "mfa", "binary", "bitstring",
+                                   "string", "nonempty_string", "iolist", "iodata"];
+
+pub const DEFAULT_WIDTH: usize = 80;
+
+#[derive(Debug)]
+struct Alias {
+    module: Option<String>, // `None` means "built-in"
+    name: String,
+    is_exported: bool,
+    body: Type,
+}
+impl Alias {
+    fn is_visible_from(&self, module: &str) -> bool {
+        match self.module {
+            None => true,
+            Some(ref m) => self.is_exported || m == module,
+        }
+    }
+    fn to_type(&self, module: &str) -> Type {
+        match self.module {
+            None => ty::builtin0(&self.name),
+            Some(ref m) if m == module => ty::local(&self.name, &[]),
+            Some(ref m) => ty::remote(m, &self.name, &[]),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Aliases {
+    entries: Vec<Alias>,
+}
+impl Aliases {
+    /// Collects the built-in aliases and the user-defined types without parameters in `env`.
+    ///
+    /// Only user-defined types whose bodies are compound (i.e., unions, tuples, maps and
+    /// records) are used, so that e.g. `-type name() :: atom().` does not rename every atom.
+    pub fn new(env: &Env) -> Self {
+        let mut entries = Vec::new();
+        let resolver = Resolver::new(env, "");
+        for name in BUILTIN_ALIASES {
+            entries.push(Alias {
+                module: None,
+                name: name.to_string(),
+                is_exported: true,
+                body: resolver.resolve(&ty::builtin0(name)),
+            });
+        }
+        let mut modules = env.modules.values().collect::<Vec<_>>();
+        modules.sort_by(|a, b| a.name.cmp(&b.name));
+        for module in modules {
+            let mut types = module.types.keys().filter(|t| t.arity == 0).collect::<Vec<_>>();
+            types.sort();
+            let resolver = Resolver::new(env, &module.name);
+            for t in types {
+                let body = resolver.resolve(&ty::local(&t.name, &[]));
+                let is_compound = match body {
+                    Type::Union(_) | Type::Record(_) | Type::Rec(_) => true,
+                    Type::Tuple(ref x) => x.elements.is_some(),
+                    Type::Map(ref x) => !x.pairs.is_empty(),
+                    _ => false,
+                };
+                if is_compound {
+                    entries.push(Alias {
+                        module: Some(module.name.clone()),
+                        name: t.name.clone(),
+                        is_exported: module.export_types.contains(t),
+                        body,
+                    });
+                }
+            }
+        }
+        Aliases { entries }
+    }
+
+    /// Replaces the parts of `t` which are equivalent to known types with their names
+    /// (as seen from `module`).
+    pub fn fold(&self, t: &Type, module: &str) -> Type {
+        let visible = || self.entries.iter().filter(|a| a.is_visible_from(module));
+        if let Some(a) = visible().find(|a| is_equivalent(&a.body, t)) {
+            return a.to_type(module);
+        }
+        let fold = |t: &Type| self.fold(t, module);
+        let fold_all = |ts: &[Type]| ts.iter().map(&fold).collect::<Vec<_>>();
+        match *t {
+            Type::Union(ref x) => {
+                let mut members = Vec::new();
+                flatten_union(t, &mut members);
+                let mut folded = Vec::new();
+                for a in visible() {
+                    let mut alias_members = Vec::new();
+                    flatten_union(&a.body, &mut alias_members);
+                    if alias_members.len() < 2 || alias_members.len() > members.len() {
+                        continue;
+                    }
+                    let matched = alias_members.iter()
+                        .filter_map(|e| members.iter().position(|m| is_equivalent(e, m)))
+                        .collect::<BTreeSet<_>>();
+                    if matched.len() == alias_members.len() {
+                        for i in matched.into_iter().rev() {
+                            members.remove(i);
+                        }
+                        folded.push(a.to_type(module));
+                    }
+                }
+                if folded.is_empty() {
+                    return From::from(ty::UnionType::new(fold_all(&x.types)));
+                }
+                folded.extend(members.iter().map(&fold));
+                if folded.len() == 1 { folded.remove(0) } else { ty::union(&folded) }
+            }
+            Type::Tuple(ref x) => {
+                From::from(ty::TupleType { elements: x.elements.as_ref().map(|es| fold_all(es)) })
+            }
+            Type::List(ref x) => {
+                let list = match **x {
+                    ty::ListType::Proper(ref l) => {
+                        ty::ListType::Proper(ty::ProperListType { element: fold(&l.element) })
+                    }
+                    ty::ListType::NonEmpty(ref l) => {
+                        ty::ListType::NonEmpty(ty::NonEmptyListType { element: fold(&l.element) })
+                    }
+                    ty::ListType::MaybeImproper(ref l) => {
+                        ty::ListType::MaybeImproper(ty::MaybeImproperListType {
+                            element: fold(&l.element),
+                            last: fold(&l.last),
+                        })
+                    }
+                    ty::ListType::NonEmptyImproper(ref l) => {
+                        ty::ListType::NonEmptyImproper(ty::NonEmptyImproperListType {
+                            element: fold(&l.element),
+                            last: fold(&l.last),
+                        })
+                    }
+                };
+                From::from(list)
+            }
+            Type::Map(ref x) => {
+                let pairs = x.pairs
+                    .iter()
+                    .map(|p| {
+                        ty::MapPair {
+                            is_assoc: p.is_assoc,
+                            key: fold(&p.key),
+                            value: fold(&p.value),
+                        }
+                    })
+                    .collect();
+                From::from(ty::MapType { pairs })
+            }
+            Type::Record(ref x) => {
+                let fields = x.fields
+                    .iter()
+                    .map(|f| {
+                        ty::RecordField {
+                            name: f.name.clone(),
+                            value: fold(&f.value),
+                        }
+                    })
+                    .collect();
+                From::from(ty::RecordType {
+                    name: x.name.clone(),
+                    fields,
+                })
+            }
+            Type::Fun(ref x) => {
+                let clauses = x.clauses.iter().map(|c| self.fold_fun_spec(c, module)).collect();
+                From::from(ty::FunType { clauses })
+            }
+            _ => t.clone(),
+        }
+    }
+
+    pub fn fold_fun_spec(&self, spec: &FunSpec, module: &str) -> FunSpec {
+        let fold_all = |args: &Vec<Type>| args.iter().map(|a| self.fold(a, module)).collect();
+        FunSpec {
+            args: spec.args.as_ref().map(fold_all),
+            return_type: self.fold(&spec.return_type, module),
+        }
+    }
+}
+
+fn is_equivalent(a: &Type, b: &Type) -> bool {
+    // NOTE:
+    // Types of different kinds are never equivalent after resolution
+    // (except for recursive types, which are equivalent to their unfoldings)
+    let kind = |t: &Type| match *t {
+        Type::Rec(ref x) => mem::discriminant(&x.unfold()),
+        _ => mem::discriminant(t),
+    };
+    kind(a) == kind(b) && lattice::is_equivalent(a, b)
+}
+
+fn flatten_union(t: &Type, acc: &mut Vec<Type>) {
+    match *t {
+        Type::Union(ref x) => {
+            for t in &x.types {
+                flatten_union(t, acc);
+            }
+        }
+        Type::Rec(ref x) => flatten_union(&x.unfold(), acc),
+        _ => acc.push(t.clone()),
+    }
+}
+
+/// Lays types out within `width` columns.
+///
+/// A union which doesn't fit is written one member per line (each following line starts
+/// with `| `), and the elements of the other compound types are aligned after the opening
+/// bracket. Types nested deeper than `max_depth` are written as `...`.
+#[derive(Debug, Clone)]
+pub struct Printer {
+    width: usize,
+    max_depth: Option<usize>,
+}
+impl Printer {
+    pub fn new() -> Self {
+        Printer {
+            width: DEFAULT_WIDTH,
+            max_depth: None,
+        }
+    }
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Formats `t` which starts at `column` (the following lines are indented accordingly).
+    pub fn print(&self, t: &Type, column: usize) -> String {
+        self.render(&self.type_doc(t, 0), column)
+    }
+
+    /// Formats `spec` as `(ARGS) -> RETURN` (see `print`).
+    pub fn print_fun_spec(&self, spec: &FunSpec, column: usize) -> String {
+        self.render(&self.fun_spec_doc(spec, 0), column)
+    }
+
+    fn render(&self, doc: &Doc, column: usize) -> String {
+        let mut out = String::new();
+        self.render_doc(doc, column, &mut out);
+        out
+    }
+    fn render_doc(&self, doc: &Doc, start: usize, out: &mut String) {
+        let column = current_column(out, start);
+        let flat = doc.flat();
+        if column + flat.chars().count() <= self.width {
+            out.push_str(&flat);
+            return;
+        }
+        match *doc {
+            Doc::Text(ref x) => out.push_str(x),
+            Doc::Concat(ref docs) => {
+                for d in docs {
+                    self.render_doc(d, start, out);
+                }
+            }
+            Doc::Group { ref open, ref items, separator, ref close } => {
+                out.push_str(open);
+                let indent = current_column(out, start);
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(separator);
+                        out.push('\n');
+                        out.push_str(&" ".repeat(indent));
+                    }
+                    self.render_doc(item, start, out);
+                }
+                out.push_str(close);
+            }
+            Doc::Union(ref members) => {
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push('\n');
+                        out.push_str(&" ".repeat(column));
+                        out.push_str("| ");
+                    }
+                    self.render_doc(member, start, out);
+                }
+            }
+        }
+    }
+
+    fn type_doc(&self, t: &Type, depth: usize) -> Doc {
+        let is_compound = match *t {
+            Type::Tuple(ref x) => x.elements.as_ref().is_some_and(|es| !es.is_empty()),
+            Type::Map(ref x) => !x.pairs.is_empty(),
+            Type::Local(ref x) => !x.args.is_empty(),
+            Type::Remote(ref x) => !x.args.is_empty(),
+            Type::UserDefined(ref x) => !x.args.is_empty(),
+            Type::Fun(ref x) => !x.clauses.is_empty(),
+            Type::List(_) | Type::Record(_) | Type::Rec(_) => true,
+            _ => false,
+        };
+        if is_compound && self.max_depth.is_some_and(|d| depth >= d) {
+            return Doc::Text("...".to_string());
+        }
+        let docs = |ts: &[Type]| ts.iter().map(|t| self.type_doc(t, depth + 1)).collect();
+        match *t {
+            Type::Union(ref x) if !x.types.is_empty() => {
+                let members = x.types
+                    .iter()
+                    .map(|t| match *t {
+                        // NOTE: `::` binds more loosely than `|`
+                        Type::Var(ref v) if v.value.is_some() => {
+                            Doc::group("(", vec![self.type_doc(t, depth)], ")")
+                        }
+                        _ => self.type_doc(t, depth),
+                    })
+                    .collect();
+                Doc::Union(members)
+            }
+            Type::Tuple(ref x) if is_compound => {
+                Doc::group("{", docs(x.elements.as_ref().expect("Never fails")), "}")
+            }
+            Type::List(ref x) => {
+                let (name, types) = match **x {
+                    ty::ListType::Proper(ref l) => ("list", vec![l.element.clone()]),
+                    ty::ListType::NonEmpty(ref l) => ("nonempty_list", vec![l.element.clone()]),
+                    ty::ListType::MaybeImproper(ref l) if !l.may_be_proper() => {
+                        let types = vec![l.element.clone(), l.last.clone()];
+                        let list = Doc::group("nonempty_improper_list(", docs(&types), ")");
+                        return Doc::Union(vec![Doc::Text("[]".to_string()), list]);
+                    }
+                    ty::ListType::MaybeImproper(ref l) => {
+                        ("maybe_improper_list", vec![l.element.clone(), l.last.clone()])
+                    }
+                    ty::ListType::NonEmptyImproper(ref l) => {
+                        ("nonempty_improper_list", vec![l.element.clone(), l.last.clone()])
+                    }
+                };
+                Doc::group(&format!("{}(", name), docs(&types), ")")
+            }
+            Type::Map(ref x) if is_compound => {
+                let pairs = x.pairs
+                    .iter()
+                    .map(|p| {
+                        Doc::Concat(vec![self.type_doc(&p.key, depth + 1),
+                                         Doc::Text(format!(" {} ", p.operator())),
+                                         self.type_doc(&p.value, depth + 1)])
+                    })
+                    .collect();
+                Doc::group("#{", pairs, "}")
+            }
+            Type::Record(ref x) => {
+                let fields = x.fields
+                    .iter()
+                    .map(|f| {
+                        Doc::Concat(vec![Doc::Text(format!("{} :: ", ty::atom_name(&f.name))),
+                                         self.type_doc(&f.value, depth + 1)])
+                    })
+                    .collect();
+                Doc::group(&format!("#{}{{", ty::atom_name(&x.name)), fields, "}")
+            }
+            Type::Fun(ref x) if is_compound => {
+                let clauses = x.clauses.iter().map(|c| self.fun_spec_doc(c, depth + 1)).collect();
+                Doc::clauses("fun(", clauses, ")")
+            }
+            Type::Local(ref x) if is_compound => {
+                Doc::group(&format!("{}(", ty::atom_name(&x.name)), docs(&x.args), ")")
+            }
+            Type::Remote(ref x) if is_compound => {
+                let open = format!("{}:{}(", ty::atom_name(&x.module), ty::atom_name(&x.name));
+                Doc::group(&open, docs(&x.args), ")")
+            }
+            Type::UserDefined(ref x) if is_compound => {
+                Doc::group(&format!("{}(", ty::atom_name(&x.name)), docs(&x.args), ")")
+            }
+            Type::Var(ref x) if x.value.is_some() => {
+                let value = x.value.as_ref().expect("Never fails");
+                Doc::Concat(vec![Doc::Text(format!("{} :: ", x.name)),
+                                 self.type_doc(value, depth)])
+            }
+            Type::Rec(ref x) => {
+                let body = Doc::Concat(vec![Doc::Text(format!("{} :: ", x.var)),
+                                            self.type_doc(&x.body, depth)]);
+                Doc::group("(", vec![body], ")")
+            }
+            _ => Doc::Text(t.to_string()),
+        }
+    }
+    fn fun_spec_doc(&self, spec: &FunSpec, depth: usize) -> Doc {
+        let args = match spec.args {
+            None => Doc::Text("(...)".to_string()),
+            Some(ref args) => {
+                let args = args.iter().map(|a| self.type_doc(a, depth)).collect();
+                Doc::group("(", args, ")")
+            }
+        };
+        Doc::Concat(vec![args,
+                         Doc::Text(" -> ".to_string()),
+                         self.type_doc(&spec.return_type, depth)])
+    }
+}
+impl Default for Printer {
+    fn default() -> Self {
+        Printer::new()
+    }
+}
+
+enum Doc {
+    Text(String),
+    Concat(Vec<Doc>),
+    Group {
+        open: String,
+        items: Vec<Doc>,
+        separator: &'static str,
+        close: String,
+    },
+    Union(Vec<Doc>),
+}
+impl Doc {
+    fn group(open: &str, items: Vec<Doc>, close: &str) -> Self {
+        Doc::Group {
+            open: open.to_string(),
+            items,
+            separator: ",",
+            close: close.to_string(),
+        }
+    }
+    // NOTE: The clauses of a fun are separated by `;` (as in `-spec` attributes)
+    fn clauses(open: &str, items: Vec<Doc>, close: &str) -> Self {
+        Doc::Group {
+            open: open.to_string(),
+            items,
+            separator: ";",
+            close: close.to_string(),
+        }
+    }
+    fn flat(&self) -> String {
+        match *self {
+            Doc::Text(ref x) => x.clone(),
+            Doc::Concat(ref docs) => docs.iter().map(Doc::flat).collect(),
+            Doc::Group { ref open, ref items, separator, ref close } => {
+                let items = items.iter().map(Doc::flat).collect::<Vec<_>>();
+                format!("{}{}{}", open, items.join(&format!("{} ", separator)), close)
+            }
+            Doc::Union(ref members) => {
+                members.iter().map(Doc::flat).collect::<Vec<_>>().join(" | ")
+            }
+        }
+    }
+}
+
+fn current_column(out: &str, start: usize) -> usize {
+    match out.rfind('\n') {
+        Some(i) => out[i + 1..].chars().count(),
+        None => start + out.chars().count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use typespec::parse_type;
+    use super::*;
+
+    fn print(text: &str, printer: &Printer, column: usize) -> String {
+        printer.print(&parse_type(text).unwrap(), column)
+    }
+
+    #[test]
+    fn short_types_are_printed_in_a_line() {
+        assert_eq!(print("{ok, [integer()]}", &Printer::new(), 0), "{'ok', list(integer())}");
+        let t = "{ok, #{atom() => [binary()]}} | {error, {bad_arg, term()}}";
+        assert_eq!(print(t, &Printer::new().width(40), 0),
+                   "{'ok', #{atom() => list(binary())}}\n\
+                    | {'error', {'bad_arg', term()}}");
+    }
+
+    #[test]
+    fn nested_types_are_aligned_after_brackets() {
+        let t = "{ok, #{atom() => [binary()]}} | {error, {bad_arg, term()}}";
+        assert_eq!(print(t, &Printer::new().width(30), 0),
+                   "{'ok',\n \
+                    #{atom() => list(binary())}}\n\
+                    | {'error',\n   \
+                    {'bad_arg', term()}}");
+        let t = "{node, {leaf, integer()}, {node, {leaf, atom()}, [{key, value} | none]}}";
+        assert_eq!(print(t, &Printer::new().width(30), 0),
+                   "{'node',\n \
+                    {'leaf', integer()},\n \
+                    {'node',\n  \
+                    {'leaf', atom()},\n  \
+                    list({'key', 'value'}\n       \
+                    | 'none')}}");
+        let t = "#{name := binary(), children => [{atom(), 0..255}], meta => #{atom() => term()}}";
+        assert_eq!(print(t, &Printer::new().width(30), 0),
+                   "#{'name' := binary(),\n  \
+                    'children' => list({atom(),\n                      \
+                    0..255}),\n  \
+                    'meta' => #{atom() => term()}}");
+        assert_eq!(print("fun((atom(), [integer()]) -> {ok, binary()} | error)",
+                         &Printer::new().width(25),
+                         0),
+                   "fun((atom(),\n     \
+                    list(integer())) -> {'ok',\n                          \
+                    binary()}\n                         \
+                    | 'error')");
+    }
+
+    #[test]
+    fn following_lines_are_indented_from_the_start_column() {
+        let t = "{ok, #{atom() => [binary()]}} | {error, term()}";
+        assert_eq!(format!("foo() -> {}", print(t, &Printer::new().width(30), 9)),
+                   "foo() -> {'ok',\n          \
+                    #{atom() => list(binary())}}\n         \
+                    | {'error', term()}");
+        let spec = FunSpec {
+            args: Some(vec![parse_type("[{atom(), binary()}]").unwrap(),
+                            parse_type("#{atom() => integer()}").unwrap()]),
+            return_type: parse_type("{ok, [binary()]} | {error, atom()}").unwrap(),
+        };
+        assert_eq!(format!("f{}", Printer::new().width(30).print_fun_spec(&spec, 1)),
+                   "f(list({atom(), binary()}),\n  \
+                    #{atom() => integer()}) -> {'ok',\n                              \
+                    list(binary())}\n                             \
+                    | {'error',\n                                \
+                    atom()}");
+    }
+
+    #[test]
+    fn deep_types_are_abbreviated() {
+        let t = "{a, {b, {c, {d, e}}}}";
+        assert_eq!(print(t, &Printer::new().max_depth(2), 0), "{'a', {'b', ...}}");
+        assert_eq!(print(t, &Printer::new().max_depth(0), 0), "...");
+        assert_eq!(print(t, &Printer::new(), 0), "{'a', {'b', {'c', {'d', 'e'}}}}");
+    }
+}
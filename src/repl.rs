@@ -0,0 +1,175 @@
+//! Interactive queries over a loaded `Env`.
+//!
+//! Commands:
+//!
+//! - `type M:F/A`: the inferred success typing
+//! - `spec M:F/A`: the declared spec
+//! - `expand M:T/A`: the fully expanded definition of a type (parameters are `any()`)
+//! - `callers M:F/A`: the call sites of a function
+//! - `subtype "T1" "T2"`: whether `T1` is a subtype of `T2` (after expansion)
+//! - `help` and `quit`
+use std::collections::BTreeMap;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use env::Env;
+use lattice;
+use module::Arity;
+use module::Remote;
+use module::Result;
+use resolve::Resolver;
+use solver;
+use ty;
+use ty::FunSpec;
+use typespec;
+
+const HELP: &str = "\
+type M:F/A         the inferred success typing
+spec M:F/A         the declared spec
+expand M:T/A       the expanded definition of a type (parameters are any())
+callers M:F/A      the call sites of a function
+subtype \"T1\" \"T2\"  whether T1 is a subtype of T2
+quit               exits";
+
+pub struct Repl<'a> {
+    env: &'a Env,
+    signatures: BTreeMap<Remote, FunSpec>,
+}
+impl<'a> Repl<'a> {
+    /// Makes a REPL answering with the inferred `signatures` (see `Solver::solve`).
+    pub fn new(env: &'a Env, signatures: BTreeMap<Remote, FunSpec>) -> Self {
+        Repl { env, signatures }
+    }
+
+    /// Reads commands from `r` until EOF or `quit`, writing the results (and prompts) to `w`.
+    pub fn run<R: BufRead, W: Write>(&self, r: R, mut w: W) -> io::Result<()> {
+        let mut lines = r.lines();
+        loop {
+            write!(w, "> ")?;
+            w.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return writeln!(w),
+            };
+            let line = line.trim();
+            if line == "quit" || line == "exit" {
+                return Ok(());
+            }
+            if line.is_empty() {
+                continue;
+            }
+            match self.eval(line) {
+                Ok(output) => writeln!(w, "{}", output)?,
+                Err(e) => writeln!(w, "Error: {}", e)?,
+            }
+        }
+    }
+
+    /// Evaluates a command and returns the output.
+    pub fn eval(&self, line: &str) -> Result<String> {
+        let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let rest = rest.trim();
+        match command {
+            "help" => Ok(HELP.to_string()),
+            "type" => {
+                let f = parse_mfa(rest)?;
+                let signature = self.signatures
+                    .get(&f)
+                    .or_else(|| self.env.cached_signature(&f))
+                    .ok_or_else(|| format!("Unknown function: {}", rest))?;
+                Ok(format!("{} :: {}", rest, signature))
+            }
+            "spec" => {
+                let f = parse_mfa(rest)?;
+                let module = self.env
+                    .modules
+                    .get(&f.module)
+                    .ok_or_else(|| format!("Unknown module: {}", f.module))?;
+                let spec = module.specs
+                    .get(&f.to_local())
+                    .ok_or_else(|| format!("No spec: {}", rest))?;
+                Ok(spec.to_attribute(&f.name))
+            }
+            "expand" => {
+                let t = parse_mfa(rest)?;
+                let args = vec![lattice::any(); t.arity as usize];
+                let is_loaded = self.env
+                    .modules
+                    .get(&t.module)
+                    .is_some_and(|m| m.types.contains_key(&t.to_local()));
+                let known = is_loaded || self.env.cached_type(&t.module, &t.to_local()).is_some();
+                if !known {
+                    return Err(format!("Unknown type: {}", rest).into());
+                }
+                let remote = ty::remote(&t.module, &t.name, &args);
+                Ok(Resolver::new(self.env, &t.module).resolve(&remote).to_string())
+            }
+            "callers" => {
+                let f = parse_mfa(rest)?;
+                Ok(self.callers(&f).join("\n"))
+            }
+            "subtype" => {
+                let args = split_quoted(rest)?;
+                if args.len() != 2 {
+                    return Err("Usage: subtype \"T1\" \"T2\"".into());
+                }
+                // NOTE: Local types are resolved as built-in types
+                let resolver = Resolver::new(self.env, "");
+                let a = resolver.resolve(&typespec::parse_type(&args[0])?);
+                let b = resolver.resolve(&typespec::parse_type(&args[1])?);
+                Ok(format!("{} ({} <: {})", lattice::is_subtype(&a, &b), a, b))
+            }
+            _ => Err(format!("Unknown command: {} (see `help`)", command).into()),
+        }
+    }
+
+    // NOTE: Each call site is formatted as `M:F/A (FILE:LINE)`
+    fn callers(&self, callee: &Remote) -> Vec<String> {
+        let mut sites = Vec::new();
+        for module in self.env.modules.values() {
+            for (f, fun) in &module.functions {
+                let graph = &fun.graph;
+                for node in graph.nodes.values() {
+                    let resolved = solver::resolve_callee(module, graph, &node.content);
+                    if resolved.as_ref() != Some(callee) {
+                        continue;
+                    }
+                    let line = node.clause
+                        .or_else(|| graph.clauses.first().map(|c| c.id))
+                        .map_or(0, |c| graph.clauses[c].line);
+                    let file = module.source_file.clone().unwrap_or_default();
+                    sites.push((module.name.clone(), f.clone(), file, line));
+                }
+            }
+        }
+        sites.sort();
+        sites.dedup();
+        sites.into_iter()
+            .map(|(m, f, file, line)| format!("{}:{}/{} ({}:{})", m, f.name, f.arity, file, line))
+            .collect()
+    }
+}
+
+fn parse_mfa(s: &str) -> Result<Remote> {
+    let invalid = || format!("Expected `module:name/arity`, but got {:?}", s);
+    let (module, fa) = s.split_once(':').ok_or_else(invalid)?;
+    let (name, arity) = fa.rsplit_once('/').ok_or_else(invalid)?;
+    let arity = arity.parse::<Arity>().map_err(|_| invalid())?;
+    Ok(Remote::new(module, name, arity))
+}
+
+fn split_quoted(s: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let arg = chars.by_ref().take_while(|&c| c != '"').collect();
+                args.push(arg);
+            }
+            c if c.is_whitespace() => {}
+            _ => return Err(format!("Expected a quoted type, but got {:?}", c).into()),
+        }
+    }
+    Ok(args)
+}
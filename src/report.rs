@@ -0,0 +1,164 @@
+//! Output formats of diagnostics.
+//!
+//! - text: `FILE:LINE: SEVERITY: MESSAGE [KIND]` followed by the involved types
+//! - json: see `to_json`
+//! - sarif: [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html)
+//! - checkstyle: the XML format of [Checkstyle](https://checkstyle.org/)
+//! - github: [workflow commands](https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions)
+//!   which annotate pull requests
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Write;
+use diagnostic::Diagnostic;
+use diagnostic::Kind;
+use json::Json;
+use pretty::Printer;
+use xml::escape;
+
+/// The version of the JSON output (see `to_json`).
+pub const JSON_VERSION: i64 = 1;
+
+pub fn write_text<W: Write>(mut w: W,
+                            diagnostics: &[Diagnostic],
+                            printer: &Printer)
+                            -> io::Result<()> {
+    for d in diagnostics {
+        writeln!(w, "{}", d)?;
+        for (role, t) in &d.types {
+            writeln!(w, "    {}: {}", role, printer.print(t, role.len() + 6))?;
+        }
+    }
+    Ok(())
+}
+
+/// Makes a JSON of the form:
+///
+/// ```text
+/// {"version": 1,
+///  "diagnostics": [{"kind": "no-return",
+///                   "severity": "warning",
+///                   "mfa": "foo:bar/1",
+///                   "function": {"module": "foo", "name": "bar", "arity": 1},
+///                   "location": {"file": "src/foo.erl", "line": 10},
+///                   "message": "Function bar/1 has no local return",
+///                   "types": [{"role": "argument 1", "type": "integer()"}]}]}
+/// ```
+pub fn to_json(diagnostics: &[Diagnostic]) -> Json {
+    let diagnostics = diagnostics.iter()
+        .map(|d| {
+            let f = &d.function;
+            Json::object(vec![("kind", Json::from(d.kind.id())),
+                              ("severity", Json::from(d.severity.as_str())),
+                              ("mfa", Json::string(mfa(d))),
+                              ("function",
+                               Json::object(vec![("module", Json::string(&f.module)),
+                                                 ("name", Json::string(&f.name)),
+                                                 ("arity", Json::from(f.arity as i64))])),
+                              ("location",
+                               Json::object(vec![("file", Json::string(&d.location.file)),
+                                                 ("line", Json::from(d.location.line as i64))])),
+                              ("message", Json::string(&d.message)),
+                              ("types", types_to_json(d))])
+        })
+        .collect();
+    Json::object(vec![("version", Json::Integer(JSON_VERSION)),
+                      ("diagnostics", Json::Array(diagnostics))])
+}
+
+pub fn to_sarif(diagnostics: &[Diagnostic]) -> Json {
+    let level = |k: &Kind| k.severity().as_str();
+    let rules = Kind::all()
+        .iter()
+        .map(|k| {
+            Json::object(vec![("id", Json::from(k.id())),
+                              ("shortDescription",
+                               Json::object(vec![("text", Json::from(k.description()))])),
+                              ("defaultConfiguration",
+                               Json::object(vec![("level", Json::from(level(k)))]))])
+        })
+        .collect();
+    let driver = Json::object(vec![("name", Json::from("diagnoser")),
+                                   ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+                                   ("rules", Json::Array(rules))]);
+    let results = diagnostics.iter()
+        .map(|d| {
+            // NOTE: SARIF lines start from 1
+            let line = d.location.line.max(1) as i64;
+            let region = Json::object(vec![("startLine", Json::from(line))]);
+            let artifact = Json::object(vec![("uri", Json::string(&d.location.file))]);
+            let physical = Json::object(vec![("artifactLocation", artifact),
+                                             ("region", region)]);
+            let logical = Json::object(vec![("fullyQualifiedName", Json::string(mfa(d))),
+                                            ("kind", Json::from("function"))]);
+            let location = Json::object(vec![("physicalLocation", physical),
+                                             ("logicalLocations", Json::Array(vec![logical]))]);
+            Json::object(vec![("ruleId", Json::from(d.kind.id())),
+                              ("level", Json::from(d.severity.as_str())),
+                              ("message", Json::object(vec![("text", Json::string(&d.message))])),
+                              ("locations", Json::Array(vec![location])),
+                              ("properties", Json::object(vec![("types", types_to_json(d))]))])
+        })
+        .collect();
+    let run = Json::object(vec![("tool", Json::object(vec![("driver", driver)])),
+                                ("results", Json::Array(results))]);
+    Json::object(vec![("$schema", Json::from("https://json.schemastore.org/sarif-2.1.0.json")),
+                      ("version", Json::from("2.1.0")),
+                      ("runs", Json::Array(vec![run]))])
+}
+
+pub fn write_checkstyle<W: Write>(mut w: W, diagnostics: &[Diagnostic]) -> io::Result<()> {
+    let mut files = BTreeMap::new();
+    for d in diagnostics {
+        files.entry(&d.location.file).or_insert_with(Vec::new).push(d);
+    }
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(w, r#"<checkstyle version="4.3">"#)?;
+    for (file, diagnostics) in files {
+        writeln!(w, r#"  <file name="{}">"#, escape(file))?;
+        for d in diagnostics {
+            writeln!(w,
+                     r#"    <error line="{}" severity="{}" message="{}" source="diagnoser.{}"/>"#,
+                     d.location.line,
+                     d.severity,
+                     escape(&format!("{} ({})", d.message, mfa(d))),
+                     d.kind)?;
+        }
+        writeln!(w, "  </file>")?;
+    }
+    writeln!(w, "</checkstyle>")?;
+    Ok(())
+}
+
+pub fn write_github<W: Write>(mut w: W, diagnostics: &[Diagnostic]) -> io::Result<()> {
+    // NOTE: See "Workflow commands" of GitHub Actions for the escaping rules
+    let escape_data = |s: &str| s.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A");
+    let escape_property = |s: &str| escape_data(s).replace(':', "%3A").replace(',', "%2C");
+    for d in diagnostics {
+        let mut message = d.message.clone();
+        for (role, t) in &d.types {
+            message.push_str(&format!("\n{}: {}", role, t));
+        }
+        writeln!(w,
+                 "::{} file={},line={},title={}::{}",
+                 d.severity,
+                 escape_property(&d.location.file),
+                 d.location.line,
+                 escape_property(&format!("{} ({})", d.kind, mfa(d))),
+                 escape_data(&message))?;
+    }
+    Ok(())
+}
+
+fn mfa(d: &Diagnostic) -> String {
+    format!("{}:{}/{}", d.function.module, d.function.name, d.function.arity)
+}
+
+fn types_to_json(d: &Diagnostic) -> Json {
+    let types = d.types
+        .iter()
+        .map(|(role, t)| {
+            Json::object(vec![("role", Json::string(role)), ("type", Json::string(t))])
+        })
+        .collect();
+    Json::Array(types)
+}
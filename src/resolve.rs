@@ -0,0 +1,470 @@
+//! Expansion of built-in, user-defined and remote types into concrete types.
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fmt;
+use env::Env;
+use lattice;
+use module::Arity;
+use module::Local;
+use module::Spec;
+use module::SpecClause;
+use ty;
+use ty::Type;
+use ty::ListType;
+use ty::TypeClass;
+
+/// Expansions nested deeper than this are approximated by `any()`.
+///
+/// Recursive types are expanded into `ty::RecType`s, so this limits only the types which
+/// grow while they are expanded (e.g., `-type t(A) :: {A, t([A])}.`).
+const MAX_EXPANSION_DEPTH: usize = 8;
+
+/// An opaque type (e.g., `queue:queue/1`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OpaqueType {
+    /// The defining module
+    pub module: String,
+    pub name: String,
+    pub arity: Arity,
+}
+impl fmt::Display for OpaqueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}/{}", self.module, self.name, self.arity)
+    }
+}
+
+pub struct Resolver<'a> {
+    env: &'a Env,
+    module: &'a str,
+    expanding: RefCell<Vec<Expansion>>,
+    malformed_types: RefCell<BTreeSet<(String, Local)>>,
+}
+impl<'a> Resolver<'a> {
+    /// Makes a resolver for the types appearing in `module`.
+    pub fn new(env: &'a Env, module: &'a str) -> Self {
+        Resolver {
+            env,
+            module,
+            expanding: RefCell::new(Vec::new()),
+            malformed_types: RefCell::new(BTreeSet::new()),
+        }
+    }
+
+    /// Returns the user-defined types (and their modules) which were found malformed
+    /// by the resolutions so far.
+    ///
+    /// A recursive type is malformed if it never becomes productive
+    /// (e.g., `-type t() :: t().`); such types are resolved as `any()`.
+    pub fn malformed_types(&self) -> Vec<(String, Local)> {
+        self.malformed_types.borrow().iter().cloned().collect()
+    }
+    pub fn resolve(&self, t: &Type) -> Type {
+        self.expand(self.module, t, &HashMap::new(), 0)
+    }
+
+    /// Resolves the clauses of `spec` (the constraints of each clause are substituted).
+    pub fn resolve_spec(&self, spec: &Spec) -> Vec<ty::FunSpec> {
+        spec.clauses.iter().map(|c| self.resolve_spec_clause(c)).collect()
+    }
+    pub fn resolve_spec_clause(&self, clause: &SpecClause) -> ty::FunSpec {
+        let vars = clause.constraints
+            .iter()
+            .map(|c| (c.var.clone(), c.subtype.clone()))
+            .collect();
+        ty::FunSpec {
+            args: Some(clause.args.iter().map(|a| self.expand(self.module, a, &vars, 0)).collect()),
+            return_type: self.expand(self.module, &clause.return_type, &vars, 0),
+        }
+    }
+
+    /// Returns the opaque types of the arguments and the return value of `clause`
+    /// (`None` means that the type is not opaque).
+    ///
+    /// Aliases of opaque types (e.g., `-type t() :: queue:queue().`) are followed,
+    /// but the types containing opaque ones (e.g., `queue:queue() | undefined`) are not opaque.
+    pub fn opaque_spec_clause(&self,
+                              clause: &SpecClause)
+                              -> (Vec<Option<OpaqueType>>, Option<OpaqueType>) {
+        let vars = clause.constraints
+            .iter()
+            .map(|c| (c.var.clone(), c.subtype.clone()))
+            .collect();
+        let args = clause.args.iter().map(|a| self.find_opaque(self.module, a, &vars, 0)).collect();
+        (args, self.find_opaque(self.module, &clause.return_type, &vars, 0))
+    }
+
+    fn find_opaque(&self,
+                   module: &str,
+                   t: &Type,
+                   vars: &HashMap<String, Type>,
+                   depth: usize)
+                   -> Option<OpaqueType> {
+        if depth > MAX_EXPANSION_DEPTH {
+            return None;
+        }
+        let (module, instance) = match *t {
+            Type::Var(ref x) => {
+                return match x.value {
+                    Some(ref value) => self.find_opaque(module, value, vars, depth),
+                    None => {
+                        vars.get(&x.name)
+                            .and_then(|value| self.find_opaque(module, value, vars, depth + 1))
+                    }
+                };
+            }
+            Type::Local(ref x) => {
+                let key = Local::new(&x.name, x.args.len() as Arity);
+                let instance = self.env
+                    .modules
+                    .get(module)
+                    .and_then(|m| m.types.get(&key))
+                    .map(|c| c.make_instance(&x.args))
+                    .or_else(|| {
+                        self.env.cached_type(module, &key).map(|c| c.make_instance(&x.args))
+                    });
+                (module, instance)
+            }
+            Type::Remote(ref x) => {
+                let key = Local::new(&x.name, x.args.len() as Arity);
+                let instance = self.env
+                    .modules
+                    .get(&x.module)
+                    .and_then(|m| m.types.get(&key))
+                    .map(|c| c.make_instance(&x.args))
+                    .or_else(|| {
+                        self.env.cached_type(&x.module, &key).map(|c| c.make_instance(&x.args))
+                    });
+                (x.module.as_str(), instance)
+            }
+            Type::UserDefined(_) => (module, Some(t.clone())),
+            _ => return None,
+        };
+        match instance {
+            Some(Type::UserDefined(ref x)) if x.is_opaque => {
+                Some(OpaqueType {
+                    module: module.to_string(),
+                    name: x.name.clone(),
+                    arity: x.args.len() as Arity,
+                })
+            }
+            Some(Type::UserDefined(ref x)) => {
+                self.find_opaque(module, &x.body, &HashMap::new(), depth + 1)
+            }
+            Some(ref t) => self.find_opaque(module, t, &HashMap::new(), depth + 1),
+            None => None,
+        }
+    }
+
+    fn expand(&self, module: &str, t: &Type, vars: &HashMap<String, Type>, depth: usize) -> Type {
+        if depth > MAX_EXPANSION_DEPTH {
+            return lattice::any();
+        }
+        let expand = |t: &Type| self.expand(module, t, vars, depth);
+        match *t {
+            Type::Var(ref x) => {
+                if let Some(ref value) = x.value {
+                    expand(value)
+                } else if let Some(value) = vars.get(&x.name) {
+                    self.expand(module, value, vars, depth + 1)
+                } else if self.expanding.borrow().iter().any(|e| e.var == x.name) {
+                    // NOTE: A reference passed as an argument (e.g., `t` in `pair(a, t())`)
+                    t.clone()
+                } else {
+                    lattice::any()
+                }
+            }
+            Type::Local(ref x) => {
+                let args = x.args.iter().map(&expand).collect::<Vec<_>>();
+                let key = Local::new(&x.name, args.len() as Arity);
+                // NOTE: The specs of cached modules refer to their exported types (see `plt`)
+                let user_type = self.env
+                    .modules
+                    .get(module)
+                    .and_then(|m| m.types.get(&key))
+                    .map(|c| c.make_instance(&args))
+                    .or_else(|| self.env.cached_type(module, &key).map(|c| c.make_instance(&args)));
+                match user_type {
+                    Some(t) => self.expand_user_type(module, &x.name, args, &t, depth),
+                    None => builtin_type(&x.name, &args).unwrap_or_else(lattice::any),
+                }
+            }
+            Type::Remote(ref x) => {
+                let args = x.args.iter().map(&expand).collect::<Vec<_>>();
+                let key = Local::new(&x.name, args.len() as Arity);
+                let remote_type = self.env
+                    .modules
+                    .get(&x.module)
+                    .and_then(|m| m.types.get(&key))
+                    .map(|c| c.make_instance(&args))
+                    .or_else(|| {
+                        self.env.cached_type(&x.module, &key).map(|c| c.make_instance(&args))
+                    });
+                match remote_type {
+                    Some(t) => self.expand_user_type(&x.module, &x.name, args, &t, depth),
+                    None => lattice::any(),
+                }
+            }
+            Type::UserDefined(ref x) => self.expand(module, &x.body, vars, depth + 1),
+            Type::Union(ref x) => {
+                // NOTE: Unguarded references to the types being expanded are kept as they are
+                // (see `expand_user_type`)
+                let mut members = Vec::new();
+                let mut references = Vec::new();
+                for t in &x.types {
+                    flatten_references(expand(t), &mut members, &mut references);
+                }
+                let joined = lattice::join(&members);
+                if references.is_empty() {
+                    joined
+                } else {
+                    if !lattice::is_none(&joined) {
+                        references.insert(0, joined);
+                    }
+                    ty::union(&references)
+                }
+            }
+            Type::Tuple(ref x) => {
+                match x.elements {
+                    Some(ref es) => lattice::tuple(es.iter().map(&expand).collect()),
+                    None => t.clone(),
+                }
+            }
+            Type::List(ref x) => {
+                match **x {
+                    ListType::Proper(ref l) => {
+                        lattice::list(false, expand(&l.element), From::from(ty::NilType))
+                    }
+                    ListType::NonEmpty(ref l) => {
+                        lattice::list(true, expand(&l.element), From::from(ty::NilType))
+                    }
+                    ListType::MaybeImproper(ref l) => {
+                        let last = lattice::sup(&expand(&l.last), &From::from(ty::NilType));
+                        lattice::list(false, expand(&l.element), last)
+                    }
+                    ListType::NonEmptyImproper(ref l) => {
+                        lattice::list(true, expand(&l.element), expand(&l.last))
+                    }
+                }
+            }
+            Type::Map(ref x) => {
+                let pairs = x.pairs
+                    .iter()
+                    .map(|p| {
+                        ty::MapPair {
+                            is_assoc: p.is_assoc,
+                            key: expand(&p.key),
+                            value: expand(&p.value),
+                        }
+                    })
+                    .collect();
+                From::from(ty::MapType { pairs })
+            }
+            Type::Fun(ref x) => {
+                let clauses = x.clauses
+                    .iter()
+                    .map(|c| {
+                        ty::FunSpec {
+                            args: c.args.as_ref().map(|args| args.iter().map(&expand).collect()),
+                            return_type: expand(&c.return_type),
+                        }
+                    })
+                    .collect();
+                From::from(ty::FunType { clauses })
+            }
+            Type::Record(_) => From::from(ty::TupleType::any()),
+            _ => t.clone(),
+        }
+    }
+
+    // NOTE:
+    // A type which recurs while it is expanded is referred by a type variable,
+    // and tied into a recursive type (`Var :: Body`) when the expansion finishes.
+    // Unguarded references to the type itself (e.g., `t()` in `-type t() :: a | t().`) add
+    // nothing to it, so they are dropped; it is malformed if nothing remains.
+    fn expand_user_type(&self,
+                        module: &str,
+                        name: &str,
+                        args: Vec<Type>,
+                        instance: &Type,
+                        depth: usize)
+                        -> Type {
+        let arity = args.len() as Arity;
+        let key = (module.to_string(), name.to_string(), args);
+        let var = {
+            let mut expanding = self.expanding.borrow_mut();
+            if let Some(e) = expanding.iter_mut().find(|e| e.key == key) {
+                e.is_referred = true;
+                return From::from(ty::Var::new(&e.var));
+            }
+            let var = rec_var(name, &expanding);
+            expanding.push(Expansion {
+                key,
+                var: var.clone(),
+                is_referred: false,
+            });
+            var
+        };
+        let body = self.expand(module, instance, &HashMap::new(), depth + 1);
+        let expansion = self.expanding.borrow_mut().pop().expect("Never fails");
+        if !expansion.is_referred {
+            return body;
+        }
+        let rec = ty::RecType::new(&var, drop_unguarded_reference(&var, body));
+        if lattice::is_none(&rec.body) || !rec.is_productive() {
+            self.malformed_types.borrow_mut().insert((module.to_string(), Local::new(name, arity)));
+            return lattice::any();
+        }
+        // NOTE: The unfolding equals the body if all the references were unguarded
+        if rec.unfold() == rec.body {
+            return rec.body;
+        }
+        From::from(rec)
+    }
+}
+
+struct Expansion {
+    key: (String, String, Vec<Type>), // (module, name, args)
+    var: String,
+    is_referred: bool,
+}
+
+// NOTE: The variable is named after the type (e.g., `Tree` for `binary_tree()`)
+fn rec_var(name: &str, expanding: &[Expansion]) -> String {
+    let mut var = name.split('_')
+        .map(|w| {
+            let mut cs = w.chars();
+            cs.next().map_or_else(String::new, |c| c.to_uppercase().chain(cs).collect())
+        })
+        .collect::<String>();
+    if !var.starts_with(|c: char| c.is_ascii_uppercase()) {
+        var.insert(0, 'T');
+    }
+    if expanding.iter().any(|e| e.var == var) {
+        var = format!("{}{}", var, expanding.len());
+    }
+    var
+}
+
+fn is_reference(t: &Type) -> bool {
+    match *t {
+        Type::Var(ref x) => x.value.is_none(),
+        _ => false,
+    }
+}
+
+fn flatten_references(t: Type, members: &mut Vec<Type>, references: &mut Vec<Type>) {
+    match t {
+        Type::Union(x) if x.types.iter().any(is_reference) => {
+            for t in x.types {
+                flatten_references(t, members, references);
+            }
+        }
+        t => {
+            if is_reference(&t) {
+                references.push(t);
+            } else {
+                members.push(t);
+            }
+        }
+    }
+}
+
+fn drop_unguarded_reference(var: &str, t: Type) -> Type {
+    match t {
+        Type::Var(ref x) if x.value.is_none() && x.name == var => lattice::none(),
+        Type::Union(x) if x.types.iter().any(is_reference) => {
+            let types = x.types
+                .into_iter()
+                .map(|t| drop_unguarded_reference(var, t))
+                .filter(|t| !matches!(*t, Type::None(_)))
+                .collect::<Vec<_>>();
+            match types.len() {
+                0 => lattice::none(),
+                1 => types.into_iter().next().expect("Never fails"),
+                _ => From::from(ty::UnionType::new(types)),
+            }
+        }
+        t => t,
+    }
+}
+
+/// Returns the definition of the built-in type `name(args...)`.
+///
+/// The arguments are expected to be resolved already.
+///
+/// See: [Types and their Syntax](http://erlang.org/doc/reference_manual/typespec.html)
+pub fn builtin_type(name: &str, args: &[Type]) -> Option<Type> {
+    let nil = || From::from(ty::NilType);
+    let integer = |min: Option<i64>, max: Option<i64>| {
+        From::from(ty::IntegerType {
+            min: min.map(From::from),
+            max: max.map(From::from),
+        })
+    };
+    let bitstring = |bits: usize, align: usize| From::from(ty::BitstringType { bits, align });
+    let t = match (name, args.len()) {
+        ("any", 0) | ("term", 0) | ("dynamic", 0) => lattice::any(),
+        ("none", 0) | ("no_return", 0) => lattice::none(),
+        ("pid", 0) => From::from(ty::PidType),
+        ("port", 0) => From::from(ty::PortType),
+        ("reference", 0) => From::from(ty::ReferenceType),
+        ("nil", 0) => nil(),
+        ("atom", 0) | ("module", 0) | ("node", 0) => From::from(ty::AtomType::any()),
+        ("boolean", 0) => lattice::sup(&ty::atom("false"), &ty::atom("true")),
+        ("float", 0) => From::from(ty::FloatType),
+        ("integer", 0) => integer(None, None),
+        ("non_neg_integer", 0) => integer(Some(0), None),
+        ("pos_integer", 0) => integer(Some(1), None),
+        ("neg_integer", 0) => integer(None, Some(-1)),
+        ("byte", 0) | ("arity", 0) => integer(Some(0), Some(255)),
+        ("char", 0) => integer(Some(0), Some(0x10ffff)),
+        ("number", 0) => lattice::number(),
+        ("fun", 0) | ("function", 0) => From::from(ty::FunType::any()),
+        ("tuple", 0) => From::from(ty::TupleType::any()),
+        ("map", 0) => From::from(ty::MapType::any()),
+        ("list", 0) => lattice::list(false, lattice::any(), nil()),
+        ("list", 1) => lattice::list(false, args[0].clone(), nil()),
+        ("nonempty_list", 0) => lattice::list(true, lattice::any(), nil()),
+        ("nonempty_list", 1) => lattice::list(true, args[0].clone(), nil()),
+        ("maybe_improper_list", 0) => lattice::list(false, lattice::any(), lattice::any()),
+        ("maybe_improper_list", 2) => {
+            lattice::list(false, args[0].clone(), lattice::sup(&args[1], &nil()))
+        }
+        ("nonempty_maybe_improper_list", 0) => lattice::list(true, lattice::any(), lattice::any()),
+        ("nonempty_maybe_improper_list", 2) => {
+            lattice::list(true, args[0].clone(), lattice::sup(&args[1], &nil()))
+        }
+        ("nonempty_improper_list", 2) => lattice::list(true, args[0].clone(), args[1].clone()),
+        ("string", 0) => lattice::list(false, integer(Some(0), Some(0x10ffff)), nil()),
+        ("nonempty_string", 0) => lattice::list(true, integer(Some(0), Some(0x10ffff)), nil()),
+        ("binary", 0) => bitstring(0, 8),
+        ("nonempty_binary", 0) => bitstring(8, 8),
+        ("bitstring", 0) => bitstring(0, 1),
+        ("nonempty_bitstring", 0) => bitstring(1, 1),
+        ("mfa", 0) => {
+            let atom: Type = From::from(ty::AtomType::any());
+            lattice::tuple(vec![atom.clone(), atom, integer(Some(0), Some(255))])
+        }
+        ("identifier", 0) => {
+            lattice::join(&[From::from(ty::PidType),
+                            From::from(ty::PortType),
+                            From::from(ty::ReferenceType)])
+        }
+        ("timeout", 0) => lattice::sup(&ty::atom("infinity"), &integer(Some(0), None)),
+        ("iolist", 0) => iolist(),
+        ("iodata", 0) => lattice::sup(&iolist(), &bitstring(0, 8)),
+        _ => return None,
+    };
+    Some(t)
+}
+
+// NOTE: `iolist()` is recursive, so nested lists are approximated by `maybe_improper_list()`
+fn iolist() -> Type {
+    let binary: Type = From::from(ty::BitstringType::default().align(8));
+    let byte = From::from(ty::integer().min(0).max(255));
+    let nested = lattice::list(false, lattice::any(), lattice::any());
+    lattice::list(false,
+                  lattice::join(&[byte, binary.clone(), nested]),
+                  lattice::sup(&binary, &From::from(ty::NilType)))
+}
@@ -0,0 +1,154 @@
+//! Strongly connected components of directed graphs.
+//!
+//! See: [Tarjan's strongly connected components algorithm](https://en.wikipedia.org/wiki/Tarjan%27s_strongly_connected_components_algorithm)
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+/// Returns the strongly connected components of the graph.
+///
+/// The components are topologically sorted in the "callees first" order,
+/// i.e., if a node in `A` has an edge to a node in `B`, then `B` precedes `A`.
+/// Nodes in each component are sorted.
+///
+/// Edges to nodes which are not keys of `edges` are treated as edges to sink nodes.
+pub fn strongly_connected_components<T>(edges: &BTreeMap<T, BTreeSet<T>>) -> Vec<Vec<T>>
+    where T: Ord + Clone
+{
+    let mut nodes = BTreeSet::new();
+    for (from, tos) in edges {
+        nodes.insert(from);
+        nodes.extend(tos.iter());
+    }
+    let nodes = nodes.into_iter().collect::<Vec<_>>();
+    let indices = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect::<BTreeMap<_, _>>();
+    let successors = nodes.iter()
+        .map(|n| {
+            edges.get(*n)
+                .map(|tos| tos.iter().map(|t| indices[t]).collect())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<Vec<usize>>>();
+
+    let mut tarjan = Tarjan {
+        successors: &successors,
+        index: vec![None; nodes.len()],
+        lowlink: vec![0; nodes.len()],
+        on_stack: vec![false; nodes.len()],
+        stack: Vec::new(),
+        next_index: 0,
+        components: Vec::new(),
+    };
+    for v in 0..nodes.len() {
+        if tarjan.index[v].is_none() {
+            tarjan.visit(v);
+        }
+    }
+    tarjan.components
+        .into_iter()
+        .map(|c| {
+            let mut c = c.into_iter().map(|i| nodes[i].clone()).collect::<Vec<_>>();
+            c.sort();
+            c
+        })
+        .collect()
+}
+
+struct Tarjan<'a> {
+    successors: &'a [Vec<usize>],
+    index: Vec<Option<usize>>,
+    lowlink: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next_index: usize,
+    components: Vec<Vec<usize>>,
+}
+impl<'a> Tarjan<'a> {
+    // NOTE: Iterative version (call graphs of real code bases are too deep for recursion)
+    fn visit(&mut self, root: usize) {
+        let mut call_stack = vec![(root, 0)];
+        self.enter(root);
+        while let Some(&mut (v, ref mut i)) = call_stack.last_mut() {
+            if let Some(&w) = self.successors[v].get(*i) {
+                *i += 1;
+                match self.index[w] {
+                    None => {
+                        self.enter(w);
+                        call_stack.push((w, 0));
+                    }
+                    Some(w_index) if self.on_stack[w] => {
+                        self.lowlink[v] = self.lowlink[v].min(w_index);
+                    }
+                    Some(_) => {}
+                }
+                continue;
+            }
+            call_stack.pop();
+            if let Some(&(parent, _)) = call_stack.last() {
+                self.lowlink[parent] = self.lowlink[parent].min(self.lowlink[v]);
+            }
+            if Some(self.lowlink[v]) == self.index[v] {
+                let mut component = Vec::new();
+                loop {
+                    let w = self.stack.pop().unwrap();
+                    self.on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+    fn enter(&mut self, v: usize) {
+        self.index[v] = Some(self.next_index);
+        self.lowlink[v] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(v);
+        self.on_stack[v] = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(u32, &[u32])]) -> BTreeMap<u32, BTreeSet<u32>> {
+        edges.iter().map(|&(from, tos)| (from, tos.iter().cloned().collect())).collect()
+    }
+
+    #[test]
+    fn acyclic_graph() {
+        let g = graph(&[(1, &[2, 3]), (2, &[3]), (3, &[])]);
+        assert_eq!(strongly_connected_components(&g), vec![vec![3], vec![2], vec![1]]);
+    }
+
+    #[test]
+    fn cyclic_graph() {
+        // 1 -> 2 -> 3 -> 1, 3 -> 4 -> 5 -> 4, 6 -> 6
+        let g = graph(&[(1, &[2]), (2, &[3]), (3, &[1, 4]), (4, &[5]), (5, &[4]), (6, &[6, 1])]);
+        assert_eq!(strongly_connected_components(&g),
+                   vec![vec![4, 5], vec![1, 2, 3], vec![6]]);
+    }
+
+    #[test]
+    fn sink_nodes_are_included() {
+        let g = graph(&[(1, &[2]), (3, &[2, 4])]);
+        let components = strongly_connected_components(&g);
+        assert_eq!(components.len(), 4);
+        let position = |n| components.iter().position(|c| c == &vec![n]).unwrap();
+        assert!(position(2) < position(1));
+        assert!(position(2) < position(3));
+        assert!(position(4) < position(3));
+    }
+
+    #[test]
+    fn deep_graph_does_not_overflow() {
+        let n = 100_000;
+        let mut g = (0..n).map(|i| (i, BTreeSet::from([i + 1]))).collect::<BTreeMap<_, _>>();
+        g.insert(n, BTreeSet::from([0]));
+        let components = strongly_connected_components(&g);
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].len(), n as usize + 1);
+    }
+}
@@ -0,0 +1,708 @@
+//! Success typing inference.
+//!
+//! Functions are analyzed bottom-up over the strongly connected components of the call graph
+//! (see `call_graph::CallGraph::sccs`). The members of a component are analyzed repeatedly
+//! until their signatures reach a fixpoint, so mutually recursive functions are solved together.
+//!
+//! See: [Practical Type Inference Based on Success Typings](http://user.it.uu.se/~kostis/Papers/succ_types.pdf)
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use bif;
+use call_graph;
+use call_graph::CallGraph;
+use env::Env;
+use graph::ClauseId;
+use graph::Content;
+use graph::EdgeKind;
+use graph::Fun;
+use graph::Graph;
+use graph::NodeId;
+use intern;
+use intern::Signature;
+use intern::TypeId;
+use lattice;
+use module::Arity;
+use module::Module;
+use module::Remote;
+use resolve::Resolver;
+use ty;
+use ty::FunSpec;
+use ty::Type;
+
+/// Signatures are widened after this number of iterations over a component.
+const WIDENING_DELAY: usize = 3;
+
+/// Components which do not reach a fixpoint in this number of iterations get `any()` types.
+const MAX_ITERATIONS: usize = 20;
+
+/// Maximum number of propagation rounds over the nodes of a function graph.
+const MAX_ROUNDS: usize = 50;
+
+// NOTE: Types are interned during solving (see `intern`)
+pub struct Solver<'a> {
+    env: &'a Env,
+    _pin: intern::Pin, // The interned types of the solver are not in `intern::Roots`
+    signatures: BTreeMap<Remote, Signature>,
+    bif_signatures: RefCell<HashMap<Remote, Option<Signature>>>,
+    external_signatures: RefCell<HashMap<Remote, Vec<Signature>>>,
+}
+impl<'a> Solver<'a> {
+    pub fn new(env: &'a Env) -> Self {
+        Self::with_signatures(env, BTreeMap::new())
+    }
+
+    /// Makes a solver which uses the already inferred `signatures` (see `Solver::node_types`).
+    pub fn with_signatures(env: &'a Env, signatures: BTreeMap<Remote, FunSpec>) -> Self {
+        Solver {
+            env,
+            _pin: intern::pin(),
+            signatures: signatures.iter().map(|(f, s)| (f.clone(), Signature::new(s))).collect(),
+            bif_signatures: RefCell::new(HashMap::new()),
+            external_signatures: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Infers the signatures of the functions defined in the loaded modules.
+    pub fn solve(mut self) -> BTreeMap<Remote, FunSpec> {
+        let call_graph = CallGraph::new(self.env);
+        for component in call_graph.sccs() {
+            self.solve_component(&component);
+        }
+        self.fun_specs()
+    }
+
+    fn fun_specs(&self) -> BTreeMap<Remote, FunSpec> {
+        self.signatures.iter().map(|(f, s)| (f.clone(), s.to_fun_spec())).collect()
+    }
+
+    /// Re-infers the signatures affected by the change of `modules` (reloaded or removed).
+    ///
+    /// `self` should be made by `Solver::with_signatures` with the previous results.
+    /// Only the components which contain functions of `modules` or call functions whose
+    /// signatures have changed are solved again.
+    /// Returns the signatures and the functions which were solved again.
+    pub fn update(mut self,
+                  modules: &BTreeSet<String>)
+                  -> (BTreeMap<Remote, FunSpec>, BTreeSet<Remote>) {
+        let call_graph = CallGraph::new(self.env);
+        let previous = self.signatures
+            .iter()
+            .filter(|(f, _)| modules.contains(&f.module))
+            .map(|(f, s)| (f.clone(), s.clone()))
+            .collect::<BTreeMap<_, _>>();
+        self.signatures.retain(|f, _| !modules.contains(&f.module));
+
+        // NOTE: Removed functions are regarded as changed
+        let mut changed = previous.keys()
+            .filter(|f| !call_graph.callees.contains_key(*f))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        let mut solved = BTreeSet::new();
+        for component in call_graph.sccs() {
+            let dirty = component.iter().any(|f| {
+                modules.contains(&f.module) || !self.signatures.contains_key(f) ||
+                call_graph.callees[f].iter().any(|c| changed.contains(c)) ||
+                self.refers_types_of(f, modules)
+            });
+            if !dirty {
+                continue;
+            }
+            let old = component.iter()
+                .map(|f| self.signatures.get(f).or_else(|| previous.get(f)).cloned())
+                .collect::<Vec<_>>();
+            self.solve_component(&component);
+            for (f, old) in component.iter().zip(old) {
+                if old.is_none_or(|old| !is_same_signature(&old, &self.signatures[f])) {
+                    changed.insert(f.clone());
+                }
+                solved.insert(f.clone());
+            }
+        }
+        (self.fun_specs(), solved)
+    }
+
+    // NOTE: Remote types are not tracked precisely, so every spec of the clients is suspected
+    fn refers_types_of(&self, fun: &Remote, modules: &BTreeSet<String>) -> bool {
+        let module = &self.env.modules[&fun.module];
+        module.specs.contains_key(&fun.to_local()) &&
+        module.dependent_modules.iter().any(|m| modules.contains(m))
+    }
+
+    fn solve_component(&mut self, funs: &[Remote]) {
+        for f in funs {
+            // NOTE: `(any(), ...) -> none()` is the bottom of the iteration
+            let mut signature = any_signature(f.arity);
+            signature.return_type = TypeId::none();
+            self.signatures.insert(f.clone(), signature);
+        }
+        for i in 0..MAX_ITERATIONS {
+            let mut changed = false;
+            for f in funs {
+                let (mut signature, spec) = self.infer(f);
+                let old = &self.signatures[f];
+                if i >= WIDENING_DELAY {
+                    // NOTE: Widened types are narrowed by the spec again, so that e.g. a tree
+                    // cut off at a depth (see `lattice::widen`) is folded into the spec type
+                    signature = narrow_signature(&widen_signature(old, &signature), spec.as_ref());
+                }
+                if !is_same_signature(old, &signature) {
+                    self.signatures.insert(f.clone(), signature);
+                    changed = true;
+                }
+            }
+            if !changed {
+                return;
+            }
+        }
+        for f in funs {
+            self.signatures.insert(f.clone(), any_signature(f.arity));
+        }
+    }
+
+    // NOTE: The joined spec of `fun` is returned too
+    fn infer(&self, fun: &Remote) -> (Signature, Option<Signature>) {
+        let (solver, spec) = self.run_function(fun);
+        (narrow_signature(&solver.signature(), spec.as_ref()), spec)
+    }
+
+    /// Returns the types of the nodes in the graph of `fun` under the current signatures.
+    ///
+    /// `None` is returned if `fun` is not defined in the loaded modules.
+    pub fn node_types(&self, fun: &Remote) -> Option<HashMap<NodeId, Type>> {
+        let defined = self.env
+            .modules
+            .get(&fun.module)
+            .is_some_and(|m| m.functions.contains_key(&fun.to_local()));
+        if defined {
+            let types = self.run_function(fun).0.types;
+            Some(types.into_iter().map(|(n, t)| (n, t.to_type())).collect())
+        } else {
+            None
+        }
+    }
+
+    // NOTE: The joined spec of `fun` is returned too
+    fn run_function(&self, fun: &Remote) -> (FunctionSolver<'_, 'a>, Option<Signature>) {
+        let module = &self.env.modules[&fun.module];
+        let graph = &module.functions[&fun.to_local()].graph;
+        let spec = module.specs.get(&fun.to_local()).map(|s| {
+            let clauses = Resolver::new(self.env, &module.name).resolve_spec(s);
+            join_signatures(&clauses.iter().map(Signature::new).collect::<Vec<_>>(), fun.arity)
+        });
+        let params = spec.as_ref()
+            .and_then(|s| s.args.clone())
+            .unwrap_or_else(|| vec![TypeId::any(); fun.arity as usize]);
+
+        let mut solver = FunctionSolver::new(self, module, graph, params);
+        solver.run();
+        (solver, spec)
+    }
+
+    /// Returns the signature clauses of `fun`.
+    pub fn lookup(&self, fun: &Remote) -> Vec<FunSpec> {
+        self.lookup_signatures(fun).iter().map(Signature::to_fun_spec).collect()
+    }
+
+    // NOTE: The signatures of BIFs and of the functions not solved here are memoized
+    fn lookup_signatures(&self, fun: &Remote) -> Vec<Signature> {
+        let bif = self.bif_signatures
+            .borrow_mut()
+            .entry(fun.clone())
+            .or_insert_with(|| bif::signature(fun).as_ref().map(Signature::new))
+            .clone();
+        if let Some(s) = bif.or_else(|| self.signatures.get(fun).cloned()) {
+            return vec![s];
+        }
+        self.external_signatures
+            .borrow_mut()
+            .entry(fun.clone())
+            .or_insert_with(|| {
+                if let Some(s) = self.env.cached_signature(fun) {
+                    return vec![Signature::new(s)];
+                }
+                match self.env.remote_spec(fun) {
+                    Some(spec) => {
+                        let clauses = Resolver::new(self.env, &fun.module).resolve_spec(&spec);
+                        clauses.iter().map(Signature::new).collect()
+                    }
+                    None => vec![any_signature(fun.arity)],
+                }
+            })
+            .clone()
+    }
+}
+
+/// Type propagation over the graph of a function.
+struct FunctionSolver<'a, 'b: 'a> {
+    solver: &'a Solver<'b>,
+    module: &'a Module,
+    graph: &'a Graph,
+    fun: &'a Fun,
+    params: Vec<TypeId>,
+    order: Vec<NodeId>,
+    types: HashMap<NodeId, TypeId>,
+    consumables: HashMap<NodeId, TypeId>,
+    calls: HashMap<NodeId, NodeId>, // return value => call
+    destructured: HashMap<NodeId, Vec<(NodeId, usize)>>, // node => (pattern call, arg index)
+    match_inputs: HashMap<NodeId, Vec<NodeId>>,
+    return_inputs: HashMap<NodeId, Vec<NodeId>>,
+    body_clauses: HashMap<NodeId, Vec<ClauseId>>,
+}
+impl<'a, 'b: 'a> FunctionSolver<'a, 'b> {
+    fn new(solver: &'a Solver<'b>,
+           module: &'a Module,
+           graph: &'a Graph,
+           params: Vec<TypeId>)
+           -> Self {
+        let fun = graph.nodes
+            .values()
+            .filter_map(|n| match n.content {
+                Content::Fun(ref x) => Some(x),
+                _ => None,
+            })
+            .next()
+            .expect("No function node");
+        let mut this = FunctionSolver {
+            solver,
+            module,
+            graph,
+            fun,
+            params,
+            order: Vec::new(),
+            types: HashMap::new(),
+            consumables: HashMap::new(),
+            calls: HashMap::new(),
+            destructured: HashMap::new(),
+            match_inputs: HashMap::new(),
+            return_inputs: HashMap::new(),
+            body_clauses: HashMap::new(),
+        };
+        for node in graph.nodes.values() {
+            match node.content {
+                Content::Val(_) => this.order.push(node.id),
+                Content::LocalCall(ref x) => {
+                    this.calls.insert(x.return_value, node.id);
+                    if x.is_pattern {
+                        for (i, a) in x.args.iter().enumerate() {
+                            this.destructured.entry(*a).or_default().push((node.id, i));
+                        }
+                    }
+                }
+                Content::RemoteCall(ref x) => {
+                    this.calls.insert(x.return_value, node.id);
+                }
+                _ => {}
+            }
+        }
+        this.order.sort();
+        for edge in graph.edges.values() {
+            match edge.kind {
+                EdgeKind::Match => {
+                    this.match_inputs.entry(edge.consumer).or_default().push(edge.producer)
+                }
+                EdgeKind::Return => {
+                    this.return_inputs.entry(edge.consumer).or_default().push(edge.producer)
+                }
+                _ => {}
+            }
+        }
+        for c in &graph.clauses {
+            if let Some(body) = c.body {
+                this.body_clauses.entry(body).or_default().push(c.id);
+            }
+        }
+        this.init_consumables();
+        this
+    }
+
+    fn init_consumables(&mut self) {
+        let mut refinements = self.guard_refinements();
+        for (node, t) in self.call_refinements() {
+            let r = refinements.entry(node).or_insert_with(TypeId::any);
+            *r = intern::inf(*r, t);
+        }
+        // NOTE: The arguments of a pattern call precede it, so `order` is a topological order
+        for i in 0..self.order.len() {
+            let id = self.order[i];
+            let mut t = match self.graph.nodes[&id].content {
+                Content::Val(ref v) => v.consumable_type,
+                _ => unreachable!(),
+            };
+            if let Some(call) = self.calls.get(&id) {
+                t = intern::inf(t, self.pattern_type(*call));
+            }
+            if let Some(r) = refinements.get(&id) {
+                t = intern::inf(t, *r);
+            }
+            self.consumables.insert(id, t);
+        }
+    }
+
+    // Returns the structural type of the pattern call `call` (`any()` for expression calls)
+    fn pattern_type(&self, call: NodeId) -> TypeId {
+        let (fun, args) = match self.graph.nodes[&call].content {
+            Content::LocalCall(ref x) if x.is_pattern => (x.fun, &x.args),
+            _ => return TypeId::any(),
+        };
+        let arg = |i: usize| self.consumables[&args[i]];
+        match call_graph::literal_atom(self.graph, fun).as_deref() {
+            Some("__tuple") => intern::tuple(&(0..args.len()).map(arg).collect::<Vec<_>>()),
+            Some("__cons") => intern::cons(arg(0), arg(1)),
+            Some(name) if name.starts_with("__record_") => {
+                TypeId::new(From::from(ty::TupleType::any()))
+            }
+            _ => TypeId::any(),
+        }
+    }
+
+    // Type tests in guards narrow the variables bound in the clause (e.g., `is_atom(X)`)
+    fn guard_refinements(&self) -> HashMap<NodeId, TypeId> {
+        let mut refinements = HashMap::new();
+        for c in self.graph.clauses.iter().filter(|c| !c.guards.is_empty()) {
+            let mut conjs = Vec::new();
+            for conj in &c.guards {
+                let mut tests: HashMap<NodeId, TypeId> = HashMap::new();
+                let nodes = match self.graph.nodes[conj].content {
+                    Content::Conj(ref x) => &x.nodes,
+                    _ => unreachable!(),
+                };
+                for n in nodes {
+                    let test = self.calls.get(n).and_then(|call| self.type_test(*call));
+                    if let Some((var, t)) = test {
+                        if self.graph.nodes[&var].clause == Some(c.id) {
+                            let r = tests.entry(var).or_insert_with(TypeId::any);
+                            *r = intern::inf(*r, t);
+                        }
+                    }
+                }
+                conjs.push(tests);
+            }
+            // NOTE: A clause is selected if any of its guard sequences holds
+            for (var, t) in conjs[0].clone() {
+                let ts = conjs.iter().map(|c| c.get(&var).cloned()).collect::<Option<Vec<_>>>();
+                if let Some(ts) = ts {
+                    refinements.insert(var, intern::inf(t, intern::join(&ts)));
+                }
+            }
+        }
+        refinements
+    }
+    fn type_test(&self, call: NodeId) -> Option<(NodeId, TypeId)> {
+        match self.graph.nodes[&call].content {
+            Content::LocalCall(ref x) if x.args.len() == 1 => {
+                let name = call_graph::literal_atom(self.graph, x.fun)?;
+                bif::type_test(&name).map(|t| (x.args[0], TypeId::new(t)))
+            }
+            _ => None,
+        }
+    }
+
+    // Function clauses can succeed only if their unconditional calls do not fail,
+    // so the arguments of such calls are narrowed by the parameter types of the callees.
+    //
+    // TODO: Exclude calls in `try` bodies (their failures may be caught)
+    fn call_refinements(&self) -> Vec<(NodeId, TypeId)> {
+        let mut refinements = Vec::new();
+        for c in self.graph.clauses.iter().filter(|c| c.parent.is_none()) {
+            for &n in &c.nodes {
+                let content = &self.graph.nodes[&n].content;
+                let args = match *content {
+                    Content::LocalCall(ref x) if !x.is_pattern => &x.args,
+                    Content::RemoteCall(ref x) => &x.args,
+                    _ => continue,
+                };
+                let callee = match self.callee(content) {
+                    Some(callee) => callee,
+                    None => continue,
+                };
+                let signature =
+                    join_signatures(&self.solver.lookup_signatures(&callee), callee.arity);
+                let params = signature.args.unwrap_or_default();
+                for (a, p) in args.iter().zip(params) {
+                    if self.graph.nodes[a].clause == Some(c.id) {
+                        refinements.push((*a, p));
+                    }
+                }
+            }
+        }
+        refinements
+    }
+
+    fn run(&mut self) {
+        for _ in 0..MAX_ROUNDS {
+            let mut changed = false;
+            for i in 0..self.order.len() {
+                let id = self.order[i];
+                let t = self.evaluate(id);
+                if !intern::is_equivalent(self.type_of(id), t) {
+                    self.types.insert(id, t);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    fn type_of(&self, id: NodeId) -> TypeId {
+        self.types.get(&id).cloned().unwrap_or_else(TypeId::none)
+    }
+
+    fn evaluate(&self, id: NodeId) -> TypeId {
+        let produced = if let Some(inputs) = self.return_inputs.get(&id) {
+            let ts = inputs.iter()
+                .filter(|b| self.body_clauses[b].iter().any(|c| self.is_clause_alive(*c)))
+                .map(|b| self.type_of(*b))
+                .collect::<Vec<_>>();
+            intern::join(&ts)
+        } else if self.match_inputs.contains_key(&id) || self.destructured.contains_key(&id) {
+            let mut t = TypeId::any();
+            for p in self.match_inputs.get(&id).into_iter().flatten() {
+                t = intern::inf(t, self.type_of(*p));
+            }
+            for &(call, i) in self.destructured.get(&id).into_iter().flatten() {
+                t = intern::inf(t, self.destructure(call, i));
+            }
+            t
+        } else if let Some(call) = self.calls.get(&id) {
+            self.apply(*call)
+        } else if let Some(i) = self.param_index(id) {
+            self.params[i]
+        } else {
+            match self.graph.nodes[&id].content {
+                Content::Val(ref v) => v.producible_type,
+                _ => unreachable!(),
+            }
+        };
+        intern::inf(produced, self.consumables[&id])
+    }
+
+    fn param_index(&self, id: NodeId) -> Option<usize> {
+        self.fun.args.iter().position(|a| *a == id)
+    }
+
+    // NOTE: The same as `is_clause_alive` but over the interned types
+    fn is_clause_alive(&self, clause: ClauseId) -> bool {
+        let c = &self.graph.clauses[clause];
+        let true_ = TypeId::new(ty::atom("true"));
+        let holds = |conj: &NodeId| match self.graph.nodes[conj].content {
+            Content::Conj(ref x) => {
+                x.nodes.iter().all(|n| !intern::inf(self.type_of(*n), true_).is_none())
+            }
+            _ => unreachable!(),
+        };
+        c.patterns.iter().all(|p| !self.type_of(*p).is_none()) &&
+        (c.guards.is_empty() || c.guards.iter().any(holds))
+    }
+
+    fn destructure(&self, call: NodeId, index: usize) -> TypeId {
+        let (fun, arity, ret) = match self.graph.nodes[&call].content {
+            Content::LocalCall(ref x) => (x.fun, x.args.len(), x.return_value),
+            _ => unreachable!(),
+        };
+        let t = self.type_of(ret);
+        match call_graph::literal_atom(self.graph, fun).as_deref() {
+            Some("__tuple") => intern::tuple_element(t, arity, index),
+            Some("__cons") => if index == 0 { intern::head(t) } else { intern::tail(t) },
+            _ if t.is_none() => TypeId::none(),
+            _ => TypeId::any(),
+        }
+    }
+
+    fn apply(&self, call: NodeId) -> TypeId {
+        let content = &self.graph.nodes[&call].content;
+        let (fun, args) = match *content {
+            Content::LocalCall(ref x) if x.is_pattern => return TypeId::any(),
+            Content::LocalCall(ref x) => (x.fun, &x.args),
+            Content::RemoteCall(ref x) => (x.fun, &x.args),
+            _ => unreachable!(),
+        };
+        let args = args.iter().map(|a| self.type_of(*a)).collect::<Vec<_>>();
+        if args.iter().any(|a| a.is_none()) {
+            return TypeId::none();
+        }
+        if let Content::LocalCall(_) = *content {
+            match call_graph::literal_atom(self.graph, fun).as_deref() {
+                Some("__tuple") => return intern::tuple(&args),
+                Some("__cons") => return intern::cons(args[0], args[1]),
+                Some(name) if name.starts_with("__record_") => {
+                    return TypeId::new(From::from(ty::TupleType::any()))
+                }
+                Some(name) if name.starts_with("__field_") => return args[0],
+                _ => {}
+            }
+        }
+        let callee = match self.callee(content) {
+            Some(callee) => callee,
+            None => return TypeId::any(),
+        };
+        let ret = apply_signatures(&self.solver.lookup_signatures(&callee), &args);
+        let arg_types = args.iter().map(|a| a.get()).collect::<Vec<_>>();
+        let arg_types = arg_types.iter().map(|t| &**t).collect::<Vec<_>>();
+        match bif::apply(&callee, &arg_types) {
+            Some(t) if !ret.is_none() => intern::inf(TypeId::new(t), ret),
+            _ => ret,
+        }
+    }
+
+    fn callee(&self, content: &Content) -> Option<Remote> {
+        resolve_callee(self.module, self.graph, content)
+    }
+
+    fn signature(&self) -> Signature {
+        let fun = self.fun;
+        let clauses = self.graph.clauses.iter().filter(|c| c.parent.is_none()).collect::<Vec<_>>();
+        let succeeded = clauses.iter()
+            .filter(|c| {
+                let returns = c.body.is_some_and(|b| !self.type_of(b).is_none());
+                self.is_clause_alive(c.id) && returns
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        // NOTE: The argument types of functions which never return are kept for reference
+        let clauses = if succeeded.is_empty() { clauses } else { succeeded };
+        let args = (0..fun.args.len())
+            .map(|i| {
+                let ts = clauses.iter().map(|c| self.type_of(c.patterns[i])).collect::<Vec<_>>();
+                intern::join(&ts)
+            })
+            .collect();
+        Signature {
+            args: Some(args),
+            return_type: self.type_of(fun.return_value),
+        }
+    }
+}
+
+/// Returns `true` if the patterns of `clause` can match and some of its guards can hold
+/// under the node `types` (see `Solver::node_types`).
+pub fn is_clause_alive(graph: &Graph, types: &HashMap<NodeId, Type>, clause: ClauseId) -> bool {
+    let type_of = |id: &NodeId| types.get(id).cloned().unwrap_or_else(lattice::none);
+    let c = &graph.clauses[clause];
+    let true_ = ty::atom("true");
+    let holds = |conj: &NodeId| match graph.nodes[conj].content {
+        Content::Conj(ref x) => {
+            x.nodes.iter().all(|n| !lattice::is_none(&lattice::inf(&type_of(n), &true_)))
+        }
+        _ => unreachable!(),
+    };
+    c.patterns.iter().all(|p| !lattice::is_none(&type_of(p))) &&
+    (c.guards.is_empty() || c.guards.iter().any(holds))
+}
+
+/// Returns the function called by the call node `content` (see `call_graph::resolve_callee`).
+///
+/// Operators (`__op_X`) are regarded as calls to `erlang:X`.
+pub fn resolve_callee(module: &Module, graph: &Graph, content: &Content) -> Option<Remote> {
+    if let Content::LocalCall(ref x) = *content {
+        let name = call_graph::literal_atom(graph, x.fun);
+        if let Some(op) = name.as_deref().and_then(|n| n.strip_prefix("__op_")) {
+            return Some(Remote::new("erlang", op, x.args.len() as Arity));
+        }
+    }
+    call_graph::resolve_callee(module, graph, content)
+}
+
+/// Returns the join of the return types of the clauses of which the arguments can match.
+fn apply_signatures(clauses: &[Signature], args: &[TypeId]) -> TypeId {
+    let ts = clauses.iter()
+        .filter(|c| {
+            c.args.as_ref().is_none_or(|ps| {
+                ps.iter().zip(args).all(|(p, a)| !intern::inf(*p, *a).is_none())
+            })
+        })
+        .map(|c| c.return_type)
+        .collect::<Vec<_>>();
+    intern::join(&ts)
+}
+
+fn join_signatures(clauses: &[Signature], arity: Arity) -> Signature {
+    let args = (0..arity as usize)
+        .map(|i| {
+            let ts = clauses.iter()
+                .map(|c| c.args.as_ref().map_or_else(TypeId::any, |a| a[i]))
+                .collect::<Vec<_>>();
+            intern::join(&ts)
+        })
+        .collect();
+    let rets = clauses.iter().map(|c| c.return_type).collect::<Vec<_>>();
+    Signature {
+        args: Some(args),
+        return_type: intern::join(&rets),
+    }
+}
+
+fn any_signature(arity: Arity) -> Signature {
+    Signature {
+        args: Some(vec![TypeId::any(); arity as usize]),
+        return_type: TypeId::any(),
+    }
+}
+
+fn is_same_signature(a: &Signature, b: &Signature) -> bool {
+    let args = match (a.args.as_ref(), b.args.as_ref()) {
+        (Some(a), Some(b)) => a.iter().zip(b).all(|(a, b)| intern::is_equivalent(*a, *b)),
+        (None, None) => true,
+        _ => false,
+    };
+    args && intern::is_equivalent(a.return_type, b.return_type)
+}
+
+// NOTE: Types which have nothing in common with the spec are kept (see `Kind::SpecMismatch`)
+fn narrow_signature(signature: &Signature, spec: Option<&Signature>) -> Signature {
+    let spec = match spec {
+        Some(spec) => spec,
+        None => return signature.clone(),
+    };
+    let narrow = |t: TypeId, s: TypeId| {
+        let narrowed = intern::inf(t, s);
+        if narrowed.is_none() { t } else { narrowed }
+    };
+    let args = match (signature.args.as_ref(), spec.args.as_ref()) {
+        (Some(a), Some(s)) => Some(a.iter().zip(s).map(|(a, s)| narrow(*a, *s)).collect()),
+        (args, _) => args.cloned(),
+    };
+    Signature {
+        args,
+        return_type: narrow(signature.return_type, spec.return_type),
+    }
+}
+
+fn widen_signature(old: &Signature, new: &Signature) -> Signature {
+    let args = match (old.args.as_ref(), new.args.as_ref()) {
+        (Some(o), Some(n)) => Some(o.iter().zip(n).map(|(o, n)| intern::widen(*o, *n)).collect()),
+        _ => None,
+    };
+    Signature {
+        args,
+        return_type: intern::widen(old.return_type, new.return_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use module::Module;
+    use pretty::Aliases;
+    use pretty::Printer;
+    use super::*;
+
+    const RT_BEAM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/beam/testdata/rt.beam");
+
+    #[test]
+    fn widened_recursive_types_are_folded_by_specs() {
+        let mut env = Env::new();
+        env.add_module(Module::from_beam_file(RT_BEAM).unwrap());
+        let signatures = Solver::new(&env).solve();
+        let aliases = Aliases::new(&env);
+        let print = |name: &str, arity: Arity| {
+            let signature = &signatures[&Remote::new("rt", name, arity)];
+            Printer::new().print_fun_spec(&aliases.fold_fun_spec(signature, "rt"), 0)
+        };
+        assert_eq!(print("size", 1), "(tree()) -> non_neg_integer()");
+        assert_eq!(print("insert", 2),
+                   "(integer(), tree()) -> {'node', integer(), tree(), tree()}");
+    }
+}
@@ -0,0 +1,275 @@
+//! A minimal reader for Erlang terms written in text files (e.g., `.app` files).
+//!
+//! See: [file:consult/1](http://erlang.org/doc/man/file.html#consult-1)
+use std::fs;
+use std::path::Path;
+use std::str::Chars;
+use std::iter::Peekable;
+use module::Result;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Atom(String),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Binary(String),
+    Tuple(Vec<Term>),
+    List(Vec<Term>),
+}
+impl Term {
+    pub fn as_atom(&self) -> Option<&str> {
+        if let Term::Atom(ref x) = *self {
+            Some(x)
+        } else {
+            None
+        }
+    }
+    pub fn as_tuple(&self) -> Option<&[Term]> {
+        if let Term::Tuple(ref x) = *self {
+            Some(x)
+        } else {
+            None
+        }
+    }
+    pub fn as_list(&self) -> Option<&[Term]> {
+        if let Term::List(ref x) = *self {
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+/// Reads all the dot-terminated terms in `path`.
+pub fn consult<P: AsRef<Path>>(path: P) -> Result<Vec<Term>> {
+    let text = fs::read_to_string(path)?;
+    parse_terms(&text)
+}
+
+pub fn parse_terms(text: &str) -> Result<Vec<Term>> {
+    let mut parser = Parser { chars: text.chars().peekable() };
+    let mut terms = Vec::new();
+    loop {
+        parser.skip_whitespaces();
+        if parser.chars.peek().is_none() {
+            break;
+        }
+        terms.push(parser.parse_term()?);
+        parser.expect('.')?;
+    }
+    Ok(terms)
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+impl<'a> Parser<'a> {
+    fn skip_whitespaces(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c == '%' {
+                while self.chars.peek().is_some_and(|&c| c != '\n') {
+                    self.chars.next();
+                }
+            } else if c.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespaces();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("Expected {:?}, but got {:?}", expected, c).into()),
+            None => Err(format!("Expected {:?}, but reached EOF", expected).into()),
+        }
+    }
+    fn parse_term(&mut self) -> Result<Term> {
+        self.skip_whitespaces();
+        match self.chars.peek().cloned() {
+            Some('{') => {
+                self.chars.next();
+                Ok(Term::Tuple(self.parse_elements('}')?))
+            }
+            Some('[') => {
+                self.chars.next();
+                Ok(Term::List(self.parse_elements(']')?))
+            }
+            Some('<') => {
+                self.chars.next();
+                self.expect('<')?;
+                self.skip_whitespaces();
+                let value = if self.chars.peek() == Some(&'"') {
+                    self.chars.next();
+                    self.parse_quoted('"')?
+                } else {
+                    String::new()
+                };
+                self.expect('>')?;
+                self.expect('>')?;
+                Ok(Term::Binary(value))
+            }
+            Some('"') => {
+                self.chars.next();
+                Ok(Term::String(self.parse_quoted('"')?))
+            }
+            Some('\'') => {
+                self.chars.next();
+                Ok(Term::Atom(self.parse_quoted('\'')?))
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) if c.is_lowercase() => {
+                let mut name = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_alphanumeric() || c == '_' || c == '@' {
+                        name.push(c);
+                        self.chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                Ok(Term::Atom(name))
+            }
+            Some(c) => Err(format!("Unexpected character: {:?}", c).into()),
+            None => Err("Unexpected EOF".into()),
+        }
+    }
+    fn parse_elements(&mut self, close: char) -> Result<Vec<Term>> {
+        let mut elements = Vec::new();
+        self.skip_whitespaces();
+        if self.chars.peek() == Some(&close) {
+            self.chars.next();
+            return Ok(elements);
+        }
+        loop {
+            elements.push(self.parse_term()?);
+            self.skip_whitespaces();
+            match self.chars.next() {
+                Some(',') => {}
+                Some(c) if c == close => return Ok(elements),
+                Some(c) => return Err(format!("Unexpected character: {:?}", c).into()),
+                None => return Err("Unexpected EOF".into()),
+            }
+        }
+    }
+    fn parse_quoted(&mut self, quote: char) -> Result<String> {
+        let mut value = String::new();
+        loop {
+            match self.chars.next() {
+                Some('\\') => {
+                    match self.chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some(c) => value.push(c),
+                        None => return Err("Unexpected EOF".into()),
+                    }
+                }
+                Some(c) if c == quote => return Ok(value),
+                Some(c) => value.push(c),
+                None => return Err("Unexpected EOF".into()),
+            }
+        }
+    }
+    fn parse_number(&mut self) -> Result<Term> {
+        let mut text = String::new();
+        if self.chars.peek() == Some(&'-') {
+            text.push('-');
+            self.chars.next();
+        }
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_digit() || c == '_' {
+                self.chars.next();
+                if c != '_' {
+                    text.push(c);
+                }
+            } else if c == '-' && text.ends_with(['e', 'E']) {
+                text.push(c);
+                self.chars.next();
+            } else if c == '.' || c == 'e' || c == 'E' {
+                // NOTE: A dot followed by a non-digit character terminates the term
+                let mut lookahead = self.chars.clone();
+                lookahead.next();
+                if !lookahead.peek().is_some_and(|c| c.is_ascii_digit() || *c == '-') {
+                    break;
+                }
+                text.push(c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        if let Ok(v) = text.parse() {
+            Ok(Term::Integer(v))
+        } else {
+            text.parse().map(Term::Float).map_err(|_| format!("Invalid number: {}", text).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(name: &str) -> Term {
+        Term::Atom(name.to_string())
+    }
+
+    #[test]
+    fn app_file_is_parsed() {
+        let text = r#"
+            %% comment
+            {application, foo,
+             [{description, "Foo \"app\""},
+              {vsn, "1.0.0"},
+              {modules, [foo, foo_sup, 'Foo.Bar']},
+              {env, [{key, <<"value">>}, {empty, <<>>}]}]}.
+        "#;
+        let terms = parse_terms(text).unwrap();
+        assert_eq!(terms.len(), 1);
+        let app = terms[0].as_tuple().unwrap();
+        assert_eq!(app[0].as_atom(), Some("application"));
+        assert_eq!(app[1].as_atom(), Some("foo"));
+        let props = app[2].as_list().unwrap();
+        let prop = |key: &str, value: Term| Term::Tuple(vec![atom(key), value]);
+        assert_eq!(props[0], prop("description", Term::String("Foo \"app\"".to_string())));
+        let modules = vec![atom("foo"), atom("foo_sup"), atom("Foo.Bar")];
+        assert_eq!(props[2], prop("modules", Term::List(modules)));
+        let env = vec![prop("key", Term::Binary("value".to_string())),
+                       prop("empty", Term::Binary(String::new()))];
+        assert_eq!(props[3], prop("env", Term::List(env)));
+    }
+
+    #[test]
+    fn numbers_are_parsed() {
+        let terms = parse_terms("1. -20. 1_000. 1.5. 2.5e3. 1.5e-3. [1.0].").unwrap();
+        assert_eq!(terms,
+                   vec![Term::Integer(1),
+                        Term::Integer(-20),
+                        Term::Integer(1000),
+                        Term::Float(1.5),
+                        Term::Float(2500.0),
+                        Term::Float(0.0015),
+                        Term::List(vec![Term::Float(1.0)])]);
+    }
+
+    #[test]
+    fn multiple_terms_are_parsed() {
+        let terms = parse_terms("a. {b, c@host}. 'q\\'uoted'.").unwrap();
+        assert_eq!(terms,
+                   vec![atom("a"),
+                        Term::Tuple(vec![atom("b"), atom("c@host")]),
+                        atom("q'uoted")]);
+        assert!(parse_terms("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        assert!(parse_terms("{a, b}").is_err());
+        assert!(parse_terms("{a, b.").is_err());
+        assert!(parse_terms("[a b].").is_err());
+        assert!(parse_terms("\"unterminated.").is_err());
+        assert!(parse_terms("Var.").is_err());
+    }
+}
@@ -1,16 +1,22 @@
 //! See: [Types and Function Specifications](http://erlang.org/doc/reference_manual/typespec.html)
 #![allow(unused_variables)]
+use std::cmp;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Debug;
+use num::BigInt;
+use num::ToPrimitive;
+use num::bigint::Sign;
 
 pub trait ProtoType: Clone {}
 
-pub trait TypeClass: Debug {
+// NOTE: `Send + Sync` is required to load modules in parallel
+pub trait TypeClass: Debug + Send + Sync {
     fn make_instance(&self, args: &[Type]) -> Type;
 }
 impl<T> TypeClass for T
-    where T: ProtoType + Debug,
+    where T: ProtoType + Debug + Send + Sync,
           Type: From<T>
 {
     fn make_instance(&self, args: &[Type]) -> Type {
@@ -19,7 +25,7 @@ impl<T> TypeClass for T
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Type {
     Any(Box<AnyType>),
     None(Box<NoneType>),
@@ -38,6 +44,7 @@ pub enum Type {
     Tuple(Box<TupleType>),
     Union(Box<UnionType>),
     UserDefined(Box<UserDefinedType>),
+    Rec(Box<RecType>),
     Local(Box<LocalType>),
     Remote(Box<RemoteType>),
     Var(Box<Var>),
@@ -62,12 +69,49 @@ impl fmt::Display for Type {
             Type::Tuple(ref x) => write!(f, "{}", x),
             Type::Union(ref x) => write!(f, "{}", x),
             Type::UserDefined(ref x) => write!(f, "{}", x),
+            Type::Rec(ref x) => write!(f, "{}", x),
             Type::Local(ref x) => write!(f, "{}", x),
             Type::Remote(ref x) => write!(f, "{}", x),
             Type::Var(ref x) => write!(f, "{}", x),
         }
     }
 }
+impl PartialOrd for Type {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Type {
+    /// Compares types structurally.
+    ///
+    /// This is an arbitrary total order which is consistent with `Eq` (for sorting unions and
+    /// outputs deterministically), not a subtype order. Kinds are ordered like Erlang terms
+    /// (`number < atom < reference < fun < port < pid < tuple < map < nil < list <
+    /// bitstring`) between `none()` and the others, which are followed by `any()`. Singleton
+    /// atoms and integers are ordered as the values, but unlike Erlang terms, all the integer
+    /// types precede `float()`.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.kind_order().cmp(&other.kind_order()).then_with(|| {
+            match (self, other) {
+                (Type::Atom(a), Type::Atom(b)) => a.cmp(b),
+                (Type::Bitstring(a), Type::Bitstring(b)) => a.cmp(b),
+                (Type::Fun(a), Type::Fun(b)) => a.cmp(b),
+                (Type::Integer(a), Type::Integer(b)) => a.cmp(b),
+                (Type::List(a), Type::List(b)) => a.cmp(b),
+                (Type::Map(a), Type::Map(b)) => a.cmp(b),
+                (Type::Record(a), Type::Record(b)) => a.cmp(b),
+                (Type::Tuple(a), Type::Tuple(b)) => a.cmp(b),
+                (Type::Union(a), Type::Union(b)) => a.cmp(b),
+                (Type::UserDefined(a), Type::UserDefined(b)) => a.cmp(b),
+                (Type::Rec(a), Type::Rec(b)) => a.cmp(b),
+                (Type::Local(a), Type::Local(b)) => a.cmp(b),
+                (Type::Remote(a), Type::Remote(b)) => a.cmp(b),
+                (Type::Var(a), Type::Var(b)) => a.cmp(b),
+                _ => Ordering::Equal,
+            }
+        })
+    }
+}
 macro_rules! impl_from {
     ($to:ident :: $cons:ident ( $from:ty )) => {
         impl ::std::convert::From<$from> for $to {
@@ -94,13 +138,146 @@ impl_from!(Type::Record(RecordType));
 impl_from!(Type::Tuple(TupleType));
 impl_from!(Type::Union(UnionType));
 impl_from!(Type::UserDefined(UserDefinedType));
+impl_from!(Type::Rec(RecType));
 impl_from!(Type::Var(Var));
 impl_from!(Type::Local(LocalType));
 impl_from!(Type::Remote(RemoteType));
 impl ProtoType for Type {}
 impl Type {
-    pub fn bind(&self, bindings: HashMap<String, Type>) -> Type {
-        unimplemented!()
+    /// Replaces the type variables in `bindings` with the corresponding types.
+    pub fn bind(&self, bindings: &HashMap<String, Type>) -> Type {
+        let bind_all = |types: &[Type]| types.iter().map(|t| t.bind(bindings)).collect();
+        match *self {
+            Type::Var(ref x) => {
+                if let Some(ref value) = x.value {
+                    From::from(Var::with_value(&x.name, value.bind(bindings)))
+                } else if let Some(t) = bindings.get(&x.name) {
+                    t.clone()
+                } else {
+                    self.clone()
+                }
+            }
+            Type::Fun(ref x) => {
+                let clauses = x.clauses
+                    .iter()
+                    .map(|c| {
+                        FunSpec {
+                            args: c.args.as_ref().map(|args| bind_all(args)),
+                            return_type: c.return_type.bind(bindings),
+                        }
+                    })
+                    .collect();
+                From::from(FunType { clauses })
+            }
+            Type::List(ref x) => {
+                let list = match **x {
+                    ListType::Proper(ref l) => {
+                        ListType::Proper(ProperListType { element: l.element.bind(bindings) })
+                    }
+                    ListType::NonEmpty(ref l) => {
+                        ListType::NonEmpty(NonEmptyListType { element: l.element.bind(bindings) })
+                    }
+                    ListType::MaybeImproper(ref l) => {
+                        ListType::MaybeImproper(MaybeImproperListType {
+                            element: l.element.bind(bindings),
+                            last: l.last.bind(bindings),
+                        })
+                    }
+                    ListType::NonEmptyImproper(ref l) => {
+                        ListType::NonEmptyImproper(NonEmptyImproperListType {
+                            element: l.element.bind(bindings),
+                            last: l.last.bind(bindings),
+                        })
+                    }
+                };
+                From::from(list)
+            }
+            Type::Map(ref x) => {
+                let pairs = x.pairs
+                    .iter()
+                    .map(|p| {
+                        MapPair {
+                            is_assoc: p.is_assoc,
+                            key: p.key.bind(bindings),
+                            value: p.value.bind(bindings),
+                        }
+                    })
+                    .collect();
+                From::from(MapType { pairs })
+            }
+            Type::Record(ref x) => {
+                let fields = x.fields
+                    .iter()
+                    .map(|f| {
+                        RecordField {
+                            name: f.name.clone(),
+                            value: f.value.bind(bindings),
+                        }
+                    })
+                    .collect();
+                From::from(RecordType {
+                    name: x.name.clone(),
+                    fields,
+                })
+            }
+            Type::Tuple(ref x) => {
+                From::from(TupleType { elements: x.elements.as_ref().map(|es| bind_all(es)) })
+            }
+            Type::Union(ref x) => From::from(UnionType::new(bind_all(&x.types))),
+            Type::UserDefined(ref x) => {
+                From::from(UserDefinedType {
+                    is_opaque: x.is_opaque,
+                    name: x.name.clone(),
+                    args: bind_all(&x.args),
+                    body: x.body.bind(bindings),
+                })
+            }
+            Type::Rec(ref x) => {
+                // NOTE: The variable of `x` shadows the outer one
+                let mut bindings = bindings.clone();
+                bindings.remove(&x.var);
+                From::from(RecType::new(&x.var, x.body.bind(&bindings)))
+            }
+            Type::Local(ref x) => {
+                From::from(LocalType {
+                    name: x.name.clone(),
+                    args: bind_all(&x.args),
+                })
+            }
+            Type::Remote(ref x) => {
+                From::from(RemoteType {
+                    module: x.module.clone(),
+                    name: x.name.clone(),
+                    args: bind_all(&x.args),
+                })
+            }
+            _ => self.clone(),
+        }
+    }
+    fn kind_order(&self) -> u8 {
+        match *self {
+            Type::None(_) => 0,
+            Type::Integer(_) => 1,
+            Type::Float(_) => 2,
+            Type::Atom(_) => 3,
+            Type::Reference(_) => 4,
+            Type::Fun(_) => 5,
+            Type::Port(_) => 6,
+            Type::Pid(_) => 7,
+            Type::Tuple(_) => 8,
+            Type::Record(_) => 9,
+            Type::Map(_) => 10,
+            Type::Nil(_) => 11,
+            Type::List(_) => 12,
+            Type::Bitstring(_) => 13,
+            Type::Union(_) => 14,
+            Type::UserDefined(_) => 15,
+            Type::Rec(_) => 16,
+            Type::Local(_) => 17,
+            Type::Remote(_) => 18,
+            Type::Var(_) => 19,
+            Type::Any(_) => 20,
+        }
     }
     pub fn normalize(&self) -> Type {
         // TODO:
@@ -108,7 +285,7 @@ impl Type {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct AnyType;
 impl ProtoType for AnyType {}
 impl fmt::Display for AnyType {
@@ -117,7 +294,7 @@ impl fmt::Display for AnyType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct NoneType;
 impl ProtoType for NoneType {}
 impl fmt::Display for NoneType {
@@ -126,7 +303,7 @@ impl fmt::Display for NoneType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct PidType;
 impl ProtoType for PidType {}
 impl fmt::Display for PidType {
@@ -135,7 +312,7 @@ impl fmt::Display for PidType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct PortType;
 impl ProtoType for PortType {}
 impl fmt::Display for PortType {
@@ -144,7 +321,7 @@ impl fmt::Display for PortType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ReferenceType;
 impl ProtoType for ReferenceType {}
 impl fmt::Display for ReferenceType {
@@ -153,7 +330,7 @@ impl fmt::Display for ReferenceType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct NilType;
 impl ProtoType for NilType {}
 impl fmt::Display for NilType {
@@ -162,7 +339,7 @@ impl fmt::Display for NilType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Var {
     pub name: String,
     pub value: Option<Type>,
@@ -183,11 +360,15 @@ impl Var {
 }
 impl fmt::Display for Var {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.name)
+        if let Some(ref value) = self.value {
+            write!(f, "{} :: {}", self.name, value)
+        } else {
+            write!(f, "{}", self.name)
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct AtomType {
     pub value: Option<String>, // `None` means "any atoms"
 }
@@ -203,7 +384,7 @@ impl AtomType {
 impl fmt::Display for AtomType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if let Some(ref name) = self.value {
-            write!(f, "'{}'", name)
+            write!(f, "{}", quote_atom(name))
         } else {
             write!(f, "atom()")
         }
@@ -213,19 +394,23 @@ pub fn atom(name: &str) -> Type {
     Type::from(AtomType::new(name))
 }
 
+/// `<<_:BITS, _:_*ALIGN>>`
+///
+/// Like the abstract format of Erlang, `0` means the part is absent,
+/// so the default value is the empty binary (`<<>>`) and `bitstring()` is `<<_:_*1>>`.
 #[derive(Default)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct BitstringType {
-    pub bits: Option<usize>,
-    pub align: Option<usize>,
+    pub bits: usize,
+    pub align: usize,
 }
 impl BitstringType {
     pub fn bits(mut self, bits: usize) -> Self {
-        self.bits = Some(bits);
+        self.bits = bits;
         self
     }
     pub fn align(mut self, align: usize) -> Self {
-        self.align = Some(align);
+        self.align = align;
         self
     }
 }
@@ -233,15 +418,15 @@ impl ProtoType for BitstringType {}
 impl fmt::Display for BitstringType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match (self.bits, self.align) {
-            (None, None) => write!(f, "<<>>"),
-            (Some(bits), None) => write!(f, "<<_:{}>>", bits),
-            (None, Some(align)) => write!(f, "<<_:_*{}>>", align),
-            (Some(bits), Some(align)) => write!(f, "<<_:{}, _:_*{}>>", bits, align),
+            (0, 0) => write!(f, "<<>>"),
+            (bits, 0) => write!(f, "<<_:{}>>", bits),
+            (0, align) => write!(f, "<<_:_*{}>>", align),
+            (bits, align) => write!(f, "<<_:{}, _:_*{}>>", bits, align),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct FloatType;
 impl ProtoType for FloatType {}
 impl fmt::Display for FloatType {
@@ -250,7 +435,7 @@ impl fmt::Display for FloatType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct FunType {
     pub clauses: Vec<FunSpec>,
 }
@@ -263,54 +448,60 @@ impl FunType {
 impl fmt::Display for FunType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.clauses.is_empty() {
-            write!(f, "fun()")
-        } else {
-            assert_eq!(1, self.clauses.len());
-            let c = &self.clauses[0];
-            if let Some(ref args) = c.args {
-                try!(write!(f, "fun(("));
-                for (i, a) in args.iter().enumerate() {
-                    if i > 0 {
-                        try!(write!(f, ","));
-                    }
-                    try!(write!(f, "{}", a));
-                }
-                try!(write!(f, ") -> {})", c.return_type));
-                Ok(())
-            } else {
-                write!(f, "fun((...) -> {})", c.return_type)
+            return write!(f, "fun()");
+        }
+        // NOTE: As an extension, the clauses of an overloaded fun are separated by `;`
+        // (as in `-spec` attributes)
+        write!(f, "fun(")?;
+        for (i, c) in self.clauses.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
             }
+            write!(f, "{}", c)?;
         }
+        write!(f, ")")
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct FunSpec {
     pub args: Option<Vec<Type>>,
     pub return_type: Type,
 }
+impl fmt::Display for FunSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref args) = self.args {
+            write!(f, "(")?;
+            write_types(f, args)?;
+            write!(f, ") -> {}", self.return_type)
+        } else {
+            write!(f, "(...) -> {}", self.return_type)
+        }
+    }
+}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct IntegerType {
-    pub min: Option<i64>, // `None` means "infinity"
-    pub max: Option<i64>,
+    pub min: Option<BigInt>, // `None` means "infinity"
+    pub max: Option<BigInt>,
 }
 impl ProtoType for IntegerType {}
 impl IntegerType {
-    pub fn min(mut self, value: i64) -> Self {
-        self.min = Some(value);
+    pub fn min<T: Into<BigInt>>(mut self, value: T) -> Self {
+        self.min = Some(value.into());
         self
     }
-    pub fn max(mut self, value: i64) -> Self {
-        self.max = Some(value);
+    pub fn max<T: Into<BigInt>>(mut self, value: T) -> Self {
+        self.max = Some(value.into());
         self
     }
-    pub fn value(self, value: i64) -> Self {
-        self.min(value).max(value)
+    pub fn value<T: Into<BigInt>>(self, value: T) -> Self {
+        let value = value.into();
+        self.min(value.clone()).max(value)
     }
-    pub fn get_single_value(&self) -> Option<i64> {
+    pub fn get_single_value(&self) -> Option<BigInt> {
         if self.min == self.max {
-            self.min
+            self.min.clone()
         } else {
             None
         }
@@ -318,16 +509,164 @@ impl IntegerType {
     pub fn is_any(&self) -> bool {
         self.min.is_none() && self.max.is_none()
     }
+
+    // Range arithmetic: the results contain every value of the operations on the members
+
+    pub fn neg(&self) -> Self {
+        let (min, max) = self.bounds();
+        IntegerType::from_bounds(max.neg(), min.neg())
+    }
+    pub fn abs(&self) -> Self {
+        let (min, max) = self.bounds();
+        let zero = Bound::from(0);
+        if min >= zero {
+            self.clone()
+        } else if max <= zero {
+            self.neg()
+        } else {
+            IntegerType::from_bounds(zero, cmp::max(min.neg(), max))
+        }
+    }
+    pub fn add(&self, other: &Self) -> Self {
+        let ((a, b), (c, d)) = (self.bounds(), other.bounds());
+        IntegerType::from_bounds(a.add(&c), b.add(&d))
+    }
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+    pub fn mul(&self, other: &Self) -> Self {
+        IntegerType::from_corners(self, other, Bound::mul)
+    }
+    /// Returns `None` if `other` is `0` (the division always fails).
+    pub fn div(&self, other: &Self) -> Option<Self> {
+        // NOTE: Zero is excluded from the divisors, so the negative and positive ones are
+        // handled separately (truncation is monotonic in each part)
+        let positive = other.intersect(Bound::from(1), Bound::PosInf);
+        let negative = other.intersect(Bound::NegInf, Bound::from(-1));
+        let positive = positive.map(|p| IntegerType::from_corners(self, &p, Bound::div));
+        let negative = negative.map(|n| {
+            IntegerType::from_corners(self, &n.neg(), Bound::div).neg()
+        });
+        match (positive, negative) {
+            (Some(p), Some(n)) => Some(p.hull(&n)),
+            (p, n) => p.or(n),
+        }
+    }
+    /// Returns `None` if `other` is `0` (the division always fails).
+    pub fn rem(&self, other: &Self) -> Option<Self> {
+        let (a, b) = self.bounds();
+        let (c, d) = other.bounds();
+        if c == Bound::from(0) && d == Bound::from(0) {
+            return None;
+        }
+        // NOTE: `|X rem Y| < |Y|` and the result has the sign of `X`
+        let limit = cmp::max(c.neg(), d).add(&Bound::from(-1));
+        let zero = Bound::from(0);
+        let min = if a >= zero { zero.clone() } else { cmp::max(a, limit.neg()) };
+        let max = if b <= zero { zero } else { cmp::min(b, limit) };
+        Some(IntegerType::from_bounds(min, max))
+    }
+    pub fn band(&self, other: &Self) -> Self {
+        let ((a, b), (c, d)) = (self.bounds(), other.bounds());
+        let zero = Bound::from(0);
+        // NOTE: `X band Y` is not greater than the non-negative operands
+        match (a >= zero, c >= zero) {
+            (true, true) => IntegerType::from_bounds(zero, cmp::min(b, d)),
+            (true, false) => IntegerType::from_bounds(zero, b),
+            (false, true) => IntegerType::from_bounds(zero, d),
+            _ if b < zero && d < zero => IntegerType::from_bounds(Bound::NegInf, cmp::min(b, d)),
+            _ => integer(),
+        }
+    }
+    pub fn bor(&self, other: &Self) -> Self {
+        let ((a, b), (c, d)) = (self.bounds(), other.bounds());
+        let zero = Bound::from(0);
+        // NOTE: `X bor Y` is not less than the operands and keeps the sign bits
+        if a >= zero && c >= zero {
+            IntegerType::from_bounds(cmp::max(a, c), cmp::max(b, d).fill_bits())
+        } else if b < zero && d < zero {
+            IntegerType::from_bounds(cmp::max(a, c), Bound::from(-1))
+        } else if b < zero {
+            IntegerType::from_bounds(a, Bound::from(-1))
+        } else if d < zero {
+            IntegerType::from_bounds(c, Bound::from(-1))
+        } else {
+            integer()
+        }
+    }
+    pub fn bsl(&self, other: &Self) -> Self {
+        // NOTE: `X bsl -N` is `X bsr N`
+        let left = other.intersect(Bound::from(0), Bound::PosInf)
+            .map(|s| IntegerType::from_corners(self, &s, Bound::shift_left));
+        let right = other.intersect(Bound::NegInf, Bound::from(-1))
+            .map(|s| IntegerType::from_corners(self, &s.neg(), Bound::shift_right));
+        match (left, right) {
+            (Some(l), Some(r)) => l.hull(&r),
+            // NOTE: `other` is not empty, so at least one of the parts exists
+            // (`integer()` is only a fallback which is never used)
+            (l, r) => l.or(r).unwrap_or_else(integer),
+        }
+    }
+    pub fn bsr(&self, other: &Self) -> Self {
+        self.bsl(&other.neg())
+    }
+
+    fn bounds(&self) -> (Bound, Bound) {
+        (self.min.clone().map_or(Bound::NegInf, Bound::Finite),
+         self.max.clone().map_or(Bound::PosInf, Bound::Finite))
+    }
+    fn from_bounds(min: Bound, max: Bound) -> Self {
+        IntegerType {
+            min: min.finite(),
+            max: max.finite(),
+        }
+    }
+    // NOTE: `f` must be monotonic in each argument over the ranges
+    fn from_corners<F>(a: &Self, b: &Self, f: F) -> Self
+        where F: Fn(&Bound, &Bound) -> Bound
+    {
+        let ((a0, a1), (b0, b1)) = (a.bounds(), b.bounds());
+        let corners = [f(&a0, &b0), f(&a0, &b1), f(&a1, &b0), f(&a1, &b1)];
+        let min = corners.iter().min().expect("Never fails").clone();
+        let max = corners.iter().max().expect("Never fails").clone();
+        IntegerType::from_bounds(min, max)
+    }
+    fn intersect(&self, min: Bound, max: Bound) -> Option<Self> {
+        let (a, b) = self.bounds();
+        let (min, max) = (cmp::max(a, min), cmp::min(b, max));
+        if min <= max { Some(IntegerType::from_bounds(min, max)) } else { None }
+    }
+    fn hull(&self, other: &Self) -> Self {
+        let ((a, b), (c, d)) = (self.bounds(), other.bounds());
+        IntegerType::from_bounds(cmp::min(a, c), cmp::max(b, d))
+    }
 }
 impl fmt::Display for IntegerType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match (self.min, self.max) {
+        match (self.min.as_ref(), self.max.as_ref()) {
             (Some(min), Some(max)) if min != max => write!(f, "{}..{}", min, max),
             (Some(value), Some(_)) => write!(f, "{}", value),
+            // NOTE: Other half-open ranges can't be written, so they are shown as the least
+            // supertypes which can be (e.g., `5..` as `pos_integer()`)
+            (Some(min), None) if *min >= BigInt::from(1) => write!(f, "pos_integer()"),
+            (Some(min), None) if *min >= BigInt::from(0) => write!(f, "non_neg_integer()"),
+            (None, Some(max)) if *max <= BigInt::from(-1) => write!(f, "neg_integer()"),
             _ => write!(f, "integer()"),
         }
     }
 }
+impl PartialOrd for IntegerType {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for IntegerType {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // NOTE: `None` of `max` is the positive infinity
+        let max = |x: &IntegerType| (x.max.is_none(), x.max.clone());
+        self.min.cmp(&other.min).then_with(|| max(self).cmp(&max(other)))
+    }
+}
 pub fn integer() -> IntegerType {
     IntegerType {
         min: None,
@@ -335,7 +674,104 @@ pub fn integer() -> IntegerType {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Shifts by more bits than this are not evaluated (the results would be too large).
+pub const MAX_SHIFT: i64 = 1 << 16;
+
+/// A bound of the range arithmetic on `IntegerType`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Bound {
+    NegInf,
+    Finite(BigInt),
+    PosInf,
+}
+impl From<i64> for Bound {
+    fn from(x: i64) -> Self {
+        Bound::Finite(BigInt::from(x))
+    }
+}
+impl Bound {
+    fn finite(self) -> Option<BigInt> {
+        if let Bound::Finite(x) = self { Some(x) } else { None }
+    }
+    fn sign(&self) -> Sign {
+        match *self {
+            Bound::NegInf => Sign::Minus,
+            Bound::Finite(ref x) => x.sign(),
+            Bound::PosInf => Sign::Plus,
+        }
+    }
+    fn infinity(sign: Sign) -> Self {
+        match sign {
+            Sign::Minus => Bound::NegInf,
+            Sign::NoSign => Bound::from(0),
+            Sign::Plus => Bound::PosInf,
+        }
+    }
+    fn neg(&self) -> Self {
+        match *self {
+            Bound::NegInf => Bound::PosInf,
+            Bound::Finite(ref x) => Bound::Finite(-x),
+            Bound::PosInf => Bound::NegInf,
+        }
+    }
+    // NOTE: The infinities of the opposite signs are never added
+    fn add(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Bound::Finite(x), Bound::Finite(y)) => Bound::Finite(x + y),
+            (Bound::Finite(_), y) => y.clone(),
+            (x, _) => x.clone(),
+        }
+    }
+    fn mul(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Bound::Finite(x), Bound::Finite(y)) => Bound::Finite(x * y),
+            (x, y) => Bound::infinity(x.sign() * y.sign()),
+        }
+    }
+    // NOTE: `other` is positive and the quotient is truncated toward zero
+    fn div(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Bound::Finite(x), Bound::Finite(y)) => Bound::Finite(x / y),
+            (x, Bound::Finite(_)) => x.clone(),
+            (Bound::Finite(_), Bound::PosInf) => Bound::from(0),
+            // NOTE: `±inf / +inf` is indeterminate, but taking it as `0` is sound for
+            // `IntegerType::from_corners` because the least divisor is finite (i.e., the
+            // corners of the infinite dividend with it already reach the infinity)
+            (_, _) => Bound::from(0),
+        }
+    }
+    // NOTE: `bits` is non-negative
+    fn shift_left(&self, bits: &Self) -> Self {
+        match (self, bits.small_shift()) {
+            (Bound::Finite(x), Some(n)) => Bound::Finite(x << n),
+            (x, _) => Bound::infinity(x.sign()),
+        }
+    }
+    // NOTE: `bits` is non-negative and the result is rounded toward negative infinity
+    fn shift_right(&self, bits: &Self) -> Self {
+        match (self, bits.small_shift()) {
+            (Bound::Finite(x), Some(n)) => Bound::Finite(x >> n),
+            (x, Some(_)) => x.clone(),
+            (x, None) if x.sign() == Sign::Minus => Bound::from(-1),
+            (_, None) => Bound::from(0),
+        }
+    }
+    fn small_shift(&self) -> Option<usize> {
+        match *self {
+            Bound::Finite(ref x) => x.to_i64().filter(|x| *x <= MAX_SHIFT).map(|x| x as usize),
+            _ => None,
+        }
+    }
+    // Returns the least `2^N - 1` which is not less than `self`
+    fn fill_bits(&self) -> Self {
+        match *self {
+            Bound::Finite(ref x) => Bound::Finite((BigInt::from(1) << x.bits()) - 1),
+            ref x => x.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum ListType {
     Proper(ProperListType),
     MaybeImproper(MaybeImproperListType),
@@ -353,7 +789,7 @@ impl fmt::Display for ListType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ProperListType {
     pub element: Type,
 }
@@ -372,14 +808,32 @@ impl TypeClass for ProperListClass {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct MaybeImproperListType {
     pub element: Type,
     pub last: Type,
 }
+impl MaybeImproperListType {
+    /// Returns `true` if `last` contains `[]` (i.e., the list may be proper).
+    pub fn may_be_proper(&self) -> bool {
+        has_nil(&self.last)
+    }
+}
 impl fmt::Display for MaybeImproperListType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "maybe_improper_list({},{})", self.element, self.last)
+        // NOTE: A `maybe_improper_list(T, L)` may also end with `[]`
+        if self.may_be_proper() {
+            write!(f, "maybe_improper_list({},{})", self.element, self.last)
+        } else {
+            write!(f, "[]|nonempty_improper_list({},{})", self.element, self.last)
+        }
+    }
+}
+fn has_nil(t: &Type) -> bool {
+    match *t {
+        Type::Nil(_) => true,
+        Type::Union(ref x) => x.types.iter().any(has_nil),
+        _ => false,
     }
 }
 
@@ -395,7 +849,7 @@ impl TypeClass for MaybeImproperListClass {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct NonEmptyListType {
     pub element: Type,
 }
@@ -414,7 +868,7 @@ impl TypeClass for NonEmptyListClass {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct NonEmptyImproperListType {
     pub element: Type,
     pub last: Type,
@@ -437,7 +891,7 @@ impl TypeClass for NonEmptyImproperListClass {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct MapType {
     pub pairs: Vec<MapPair>,
 }
@@ -449,24 +903,34 @@ impl MapType {
 }
 impl fmt::Display for MapType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(write!(f, "#{{"));
+        if self.pairs.is_empty() {
+            return write!(f, "map()");
+        }
+        write!(f, "#{{")?;
         for (i, p) in self.pairs.iter().enumerate() {
             if i > 0 {
-                try!(write!(f, ","));
+                write!(f, ",")?;
             }
-            try!(write!(f, "{}=>{}", p.key, p.value));
+            write!(f, "{}{}{}", p.key, p.operator(), p.value)?;
         }
-        try!(write!(f, "}}"));
+        write!(f, "}}")?;
         Ok(())
     }
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct MapPair {
+    /// `true` for an optional association (`K => V`), `false` for a mandatory one (`K := V`)
+    pub is_assoc: bool,
     pub key: Type,
     pub value: Type,
 }
+impl MapPair {
+    pub fn operator(&self) -> &'static str {
+        if self.is_assoc { "=>" } else { ":=" }
+    }
+}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct RecordType {
     pub name: String,
     pub fields: Vec<RecordField>,
@@ -474,25 +938,28 @@ pub struct RecordType {
 impl ProtoType for RecordType {}
 impl fmt::Display for RecordType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(write!(f, "#{}{{", self.name));
+        write!(f, "#")?;
+        write!(f, "{}", atom_name(&self.name))?;
+        write!(f, "{{")?;
         for (i, p) in self.fields.iter().enumerate() {
             if i > 0 {
-                try!(write!(f, ","));
+                write!(f, ",")?;
             }
-            try!(write!(f, "{} :: {}", p.name, p.value));
+            write!(f, "{}", atom_name(&p.name))?;
+            write!(f, " :: {}", p.value)?;
         }
-        try!(write!(f, "}}"));
+        write!(f, "}}")?;
         Ok(())
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct RecordField {
     pub name: String,
     pub value: Type,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TupleType {
     pub elements: Option<Vec<Type>>,
 }
@@ -500,20 +967,27 @@ impl ProtoType for TupleType {}
 impl fmt::Display for TupleType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if let Some(ref elements) = self.elements {
-            try!(write!(f, "{{"));
-            for (i, e) in elements.iter().enumerate() {
-                if i > 0 {
-                    try!(write!(f, ","));
-                }
-                try!(write!(f, "{}", e));
-            }
-            try!(write!(f, "}}"));
-            Ok(())
+            write!(f, "{{")?;
+            write_types(f, elements)?;
+            write!(f, "}}")
         } else {
             write!(f, "tuple()")
         }
     }
 }
+impl PartialOrd for TupleType {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for TupleType {
+    /// `tuple()` comes first, and the others are ordered by their sizes and then by elements
+    /// (like Erlang terms).
+    fn cmp(&self, other: &Self) -> Ordering {
+        let size = |x: &TupleType| x.elements.as_ref().map(Vec::len);
+        size(self).cmp(&size(other)).then_with(|| self.elements.cmp(&other.elements))
+    }
+}
 impl TupleType {
     pub fn any() -> Self {
         TupleType { elements: None }
@@ -523,40 +997,111 @@ pub fn tuple3(t0: Type, t1: Type, t2: Type) -> TupleType {
     TupleType { elements: Some(vec![t0, t1, t2]) }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct UnionType {
     pub types: Vec<Type>,
 }
 impl ProtoType for UnionType {}
 impl fmt::Display for UnionType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.types.is_empty() {
+            return write!(f, "none()");
+        }
         for (i, t) in self.types.iter().enumerate() {
             if i > 0 {
-                try!(write!(f, "|"));
+                write!(f, "|")?;
+            }
+            match *t {
+                // NOTE: `::` binds more loosely than `|`
+                Type::Var(ref x) if x.value.is_some() => write!(f, "({})", t)?,
+                _ => write!(f, "{}", t)?,
             }
-            try!(write!(f, "{}", t));
         }
         Ok(())
     }
 }
 impl UnionType {
     pub fn new(types: Vec<Type>) -> Self {
-        UnionType { types: types }
+        UnionType { types }
     }
 }
+/// Makes the union of `types` (structurally equal types are included only once).
 pub fn union(types: &[Type]) -> Type {
-    From::from(UnionType::new(Vec::from(types)))
+    let mut members: Vec<Type> = Vec::with_capacity(types.len());
+    for t in types {
+        if !members.contains(t) {
+            members.push(t.clone());
+        }
+    }
+    From::from(UnionType::new(members))
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct UserDefinedType {
     pub is_opaque: bool,
     pub name: String,
+    pub args: Vec<Type>,
     pub body: Type,
 }
 impl fmt::Display for UserDefinedType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}()", self.name)
+        write!(f, "{}", atom_name(&self.name))?;
+        write!(f, "(")?;
+        write_types(f, &self.args)?;
+        write!(f, ")")
+    }
+}
+
+/// A recursive type `μVar.Body` (written as `(Var :: Body)`).
+///
+/// The (unbound) type variable `var` in `body` refers to the type itself,
+/// and the type is equivalent to its unfolding `Body[Var := Var :: Body]` (see `unfold`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RecType {
+    pub var: String,
+    pub body: Type,
+}
+impl RecType {
+    pub fn new(var: &str, body: Type) -> Self {
+        RecType {
+            var: var.to_string(),
+            body,
+        }
+    }
+
+    /// Replaces the references to the type in the body with the type itself.
+    pub fn unfold(&self) -> Type {
+        let mut bindings = HashMap::new();
+        bindings.insert(self.var.clone(), From::from(self.clone()));
+        self.body.bind(&bindings)
+    }
+
+    /// Returns `true` if every reference to the type in the body is guarded by a constructor
+    /// (e.g., `T :: 'leaf' | {'node', T, T}`), so that unfolding it makes progress.
+    ///
+    /// Non-productive types such as `T :: T | atom()` are malformed.
+    pub fn is_productive(&self) -> bool {
+        is_guarded(&self.var, &self.body)
+    }
+}
+impl fmt::Display for RecType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // NOTE: The parentheses distinguish it from the annotated type variables in unions
+        write!(f, "({} :: {})", self.var, self.body)
+    }
+}
+fn is_guarded(var: &str, t: &Type) -> bool {
+    match *t {
+        Type::Var(ref x) => {
+            match x.value {
+                Some(ref value) => is_guarded(var, value),
+                None => x.name != var,
+            }
+        }
+        Type::Union(ref x) => x.types.iter().all(|t| is_guarded(var, t)),
+        Type::UserDefined(ref x) => is_guarded(var, &x.body),
+        Type::Rec(ref x) => x.var == var || is_guarded(var, &x.body),
+        _ => true,
     }
 }
 
@@ -576,13 +1121,14 @@ impl TypeClass for UserDefinedClass {
         let ty = UserDefinedType {
             is_opaque: self.is_opaque,
             name: self.name.clone(),
-            body: self.body.bind(bindings),
+            args: Vec::from(args),
+            body: self.body.bind(&bindings),
         };
         From::from(ty)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct LocalType {
     pub name: String,
     pub args: Vec<Type>,
@@ -590,8 +1136,10 @@ pub struct LocalType {
 impl ProtoType for LocalType {}
 impl fmt::Display for LocalType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // TODO: Show arity
-        write!(f, "{}()", self.name)
+        write!(f, "{}", atom_name(&self.name))?;
+        write!(f, "(")?;
+        write_types(f, &self.args)?;
+        write!(f, ")")
     }
 }
 pub fn local(name: &str, args: &[Type]) -> Type {
@@ -613,7 +1161,7 @@ pub fn builtin2(name: &str, a0: Type, a1: Type) -> Type {
     local(name, &[a0, a1])
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct RemoteType {
     pub module: String,
     pub name: String,
@@ -621,8 +1169,12 @@ pub struct RemoteType {
 }
 impl fmt::Display for RemoteType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // TODO: Show arity
-        write!(f, "{}:{}()", self.module, self.name)
+        write!(f, "{}", atom_name(&self.module))?;
+        write!(f, ":")?;
+        write!(f, "{}", atom_name(&self.name))?;
+        write!(f, "(")?;
+        write_types(f, &self.args)?;
+        write!(f, ")")
     }
 }
 pub fn remote(module: &str, name: &str, args: &[Type]) -> Type {
@@ -632,3 +1184,205 @@ pub fn remote(module: &str, name: &str, args: &[Type]) -> Type {
         args: Vec::from(args),
     })
 }
+
+fn write_types(f: &mut fmt::Formatter, types: &[Type]) -> fmt::Result {
+    for (i, t) in types.iter().enumerate() {
+        if i > 0 {
+            write!(f, ",")?;
+        }
+        write!(f, "{}", t)?;
+    }
+    Ok(())
+}
+
+/// Returns `name` as written in types (quoted only if necessary, e.g., `foo` and `'Foo'`).
+pub fn atom_name(name: &str) -> String {
+    const RESERVED_WORDS: &[&str] = &["after", "and", "andalso", "band", "begin", "bnot", "bor",
+                                      "bsl", "bsr", "bxor", "case", "catch", "cond", "div",
+                                      "else", "end", "fun", "if", "let", "maybe", "not", "of",
+                                      "or", "orelse", "receive", "rem", "try", "when", "xor"];
+    let mut chars = name.chars();
+    let is_bare = chars.next().is_some_and(|c| c.is_ascii_lowercase()) &&
+                  chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@') &&
+                  !RESERVED_WORDS.contains(&name);
+    if is_bare { name.to_string() } else { quote_atom(name) }
+}
+
+fn quote_atom(name: &str) -> String {
+    let mut quoted = String::from("'");
+    for c in name.chars() {
+        match c {
+            '\\' | '\'' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hash;
+    use std::hash::Hasher;
+    use typespec::parse_type;
+    use super::*;
+
+    fn range(min: i64, max: i64) -> IntegerType {
+        integer().min(min).max(max)
+    }
+
+    #[test]
+    fn integer_ranges_are_displayed() {
+        assert_eq!(range(1, 3).to_string(), "1..3");
+        assert_eq!(range(5, 5).to_string(), "5");
+        assert_eq!(integer().min(0).to_string(), "non_neg_integer()");
+        assert_eq!(integer().min(1).to_string(), "pos_integer()");
+        assert_eq!(integer().max(-1).to_string(), "neg_integer()");
+        assert_eq!(integer().min(5).to_string(), "pos_integer()");
+        assert_eq!(integer().max(-5).to_string(), "neg_integer()");
+        assert_eq!(integer().min(-5).to_string(), "integer()");
+        assert_eq!(integer().max(0).to_string(), "integer()");
+        assert_eq!(integer().to_string(), "integer()");
+    }
+
+    #[test]
+    fn additive_arithmetic() {
+        assert_eq!(range(1, 3).add(&range(10, 20)), range(11, 23));
+        assert_eq!(range(1, 3).sub(&range(10, 20)), range(-19, -7));
+        assert_eq!(integer().min(0).add(&integer().min(1)), integer().min(1));
+        assert_eq!(integer().min(0).add(&integer().max(0)), integer());
+        assert_eq!(integer().min(0).neg(), integer().max(0));
+        assert_eq!(range(-5, 3).abs(), range(0, 5));
+        assert_eq!(range(-5, -2).abs(), range(2, 5));
+        assert_eq!(integer().max(-1).abs(), integer().min(1));
+    }
+
+    #[test]
+    fn multiplicative_arithmetic() {
+        assert_eq!(range(-2, 3).mul(&range(4, 5)), range(-10, 15));
+        assert_eq!(range(1, 3).mul(&integer().min(0)), integer().min(0));
+        assert_eq!(range(-2, 3).mul(&integer().min(0)), integer());
+
+        assert_eq!(range(10, 20).div(&range(0, 0)), None);
+        assert_eq!(range(10, 20).div(&range(-2, 5)), Some(range(-20, 20)));
+        assert_eq!(range(7, 7).div(&range(2, 2)), Some(range(3, 3)));
+        assert_eq!(range(-7, -7).div(&range(2, 2)), Some(range(-3, -3)));
+        assert_eq!(integer().min(0).div(&integer().min(1)), Some(integer().min(0)));
+        assert_eq!(integer().max(0).div(&integer().min(1)), Some(integer().max(0)));
+        assert_eq!(integer().div(&integer()), Some(integer()));
+
+        assert_eq!(range(10, 20).rem(&range(0, 0)), None);
+        assert_eq!(range(-7, 7).rem(&range(1, 3)), Some(range(-2, 2)));
+        assert_eq!(range(5, 9).rem(&range(1, 100)), Some(range(0, 9)));
+        assert_eq!(range(5, 9).rem(&integer()), Some(range(0, 9)));
+    }
+
+    #[test]
+    fn bitwise_arithmetic() {
+        assert_eq!(range(0, 12).band(&range(0, 5)), range(0, 5));
+        assert_eq!(integer().band(&range(0, 7)), range(0, 7));
+        assert_eq!(range(-3, -1).band(&range(-10, -2)), integer().max(-2));
+        assert_eq!(range(-3, 1).band(&range(-10, 2)), integer());
+
+        assert_eq!(range(1, 4).bor(&range(2, 8)), range(2, 15));
+        assert_eq!(range(-5, -1).bor(&range(0, 3)), range(-5, -1));
+        assert_eq!(range(-5, -2).bor(&range(-3, -1)), range(-3, -1));
+
+        assert_eq!(range(1, 3).bsl(&range(2, 4)), range(4, 48));
+        assert_eq!(range(1, 1).bsl(&range(-1, 1)), range(0, 2));
+        assert_eq!(range(1, 1).bsl(&integer().min(0)), integer().min(1));
+        assert_eq!(range(-8, 8).bsl(&integer().max(-1)), range(-4, 4));
+        assert_eq!(range(-8, 8).bsr(&range(1, 1)), range(-4, 4));
+        assert_eq!(range(5, 5).bsr(&range(1 << 20, 1 << 20)), range(0, 0));
+        assert_eq!(range(-5, -5).bsr(&range(1 << 20, 1 << 20)), range(-1, -1));
+    }
+
+    #[test]
+    fn big_integer_bounds() {
+        let max = range(i64::MAX, i64::MAX);
+        assert_eq!(max.add(&max).get_single_value(), Some(BigInt::from(i64::MAX) * 2));
+        let big: BigInt = BigInt::from(1) << 70;
+        assert_eq!(range(1, 1).bsl(&range(70, 70)), integer().value(big.clone()));
+        assert_eq!(integer().value(big.clone()).bsr(&range(70, 70)), range(1, 1));
+        assert_eq!(integer().value(big.clone()).to_string(), big.to_string());
+    }
+
+    // NOTE: Built-in types are written by constructors (`parse_type` leaves them as `LocalType`)
+    fn samples() -> Vec<Type> {
+        let mut types: Vec<Type> = vec![From::from(NoneType),
+                                        From::from(AnyType),
+                                        From::from(integer()),
+                                        From::from(FloatType),
+                                        From::from(AtomType::any()),
+                                        From::from(ReferenceType),
+                                        From::from(PortType),
+                                        From::from(PidType)];
+        types.extend(["0..5", "1", "-1", "a", "b", "fun()", "fun((a) -> b)", "tuple()", "{a}",
+                      "{a, b}", "{b, a}", "#{a => b}", "[]", "[a]", "<<>>", "a | b", "a | {b}",
+                      "t()", "m:t()", "X"]
+            .iter()
+            .map(|t| parse_type(t).unwrap()));
+        types
+    }
+
+    fn hash(t: &Type) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        t.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn ordering_is_consistent_with_equality() {
+        let types = samples();
+        for a in &types {
+            for b in &types {
+                assert_eq!(a.cmp(b) == Ordering::Equal, a == b, "{} <=> {}", a, b);
+                assert_eq!(a.cmp(b), b.cmp(a).reverse(), "{} <=> {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn hashes_agree_with_equality() {
+        let tuple = TupleType { elements: Some(vec![atom("a"), atom("b")]) };
+        let pairs: Vec<(Type, Type)> = vec![(parse_type("1").unwrap(), From::from(range(1, 1))),
+                                            (parse_type("{a, b}").unwrap(), From::from(tuple)),
+                                            (parse_type("0..255").unwrap(),
+                                             From::from(range(0, 255)))];
+        for (a, b) in pairs {
+            assert_eq!(a, b);
+            assert_eq!(hash(&a), hash(&b), "{}", a);
+        }
+        for t in samples() {
+            assert_eq!(hash(&t), hash(&t.clone()), "{}", t);
+        }
+    }
+
+    #[test]
+    fn sorting_is_deterministic() {
+        let mut sorted = samples();
+        sorted.sort();
+        let mut reversed = samples();
+        reversed.reverse();
+        reversed.sort();
+        let mut rotated = samples();
+        rotated.rotate_left(7);
+        rotated.sort();
+        assert_eq!(sorted, reversed);
+        assert_eq!(sorted, rotated);
+        assert_eq!(sorted.first().map(|t| t.to_string()), Some("none()".to_string()));
+        assert_eq!(sorted.last().map(|t| t.to_string()), Some("any()".to_string()));
+        let position = |text: &str| sorted.iter().position(|t| t.to_string() == text).unwrap();
+        assert!(position("-1") < position("0..5"));
+        assert!(position("integer()") < position("float()"));
+        assert!(position("float()") < position("'a'"));
+        assert!(position("'a'") < position("'b'"));
+        assert!(position("{'a'}") < position("{'a','b'}"));
+    }
+}
@@ -0,0 +1,738 @@
+//! Parser for the textual typespec syntax (e.g., `[{atom(), 0..255}] | #{binary() => term()}`).
+//!
+//! The results are the same as the ones converted from the abstract format by `ast::FromAst`
+//! (e.g., `[T]` is `list(T)` and user-defined types are left as `LocalType`).
+//! As an extension, `Var :: Body` whose `Body` refers to `Var` is parsed as a recursive type
+//! (`RecType`, printed as `(Var :: Body)`), and the clauses of an overloaded fun are separated
+//! by `;` (e.g., `fun((atom()) -> atom(); (integer()) -> integer())`).
+//!
+//! See: [Types and Function Specifications](http://erlang.org/doc/reference_manual/typespec.html)
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+use num::BigInt;
+use num::ToPrimitive;
+use ast;
+use module::Constraint;
+use module::Result;
+use module::Spec;
+use module::SpecClause;
+use ty;
+use ty::Type;
+
+/// Parses a type such as `[{atom(), 0..255}] | #{binary() => term()}`.
+pub fn parse_type(text: &str) -> Result<Type> {
+    let mut parser = Parser::new(text)?;
+    let t = parser.parse_top_type()?;
+    parser.expect_eof()?;
+    Ok(t)
+}
+
+/// Parses the clauses of a spec such as `(A, [A]) -> boolean() when A :: atom(); (_, _) -> true`.
+///
+/// A function name may precede each clause (as in `-spec` attributes).
+pub fn parse_spec(text: &str) -> Result<Spec> {
+    let mut parser = Parser::new(text)?;
+    let mut clauses = vec![parser.parse_spec_clause()?];
+    while parser.consume(&Token::Symbol(";")) {
+        clauses.push(parser.parse_spec_clause()?);
+    }
+    parser.expect_eof()?;
+    if clauses.iter().any(|c| c.args.len() != clauses[0].args.len()) {
+        return Err("The clauses of a spec must have the same arity".into());
+    }
+    Ok(Spec { clauses })
+}
+
+const SYMBOLS: &[&str] = &["...", "..", "::", ":=", "=>", "->", "<<", ">>", "#", "(", ")", "[",
+                           "]", "{", "}", ",", "|", ":", ";", "*", "+", "-"];
+const ADD_OPERATORS: &[&str] = &["+", "-", "bor", "bxor", "bsl", "bsr"];
+const MULT_OPERATORS: &[&str] = &["*", "div", "rem", "band"];
+const KEYWORDS: &[&str] = &["fun", "when", "bnot", "div", "rem", "band", "bor", "bxor", "bsl",
+                            "bsr"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Atom(String),
+    Var(String),
+    Integer(BigInt),
+    Keyword(&'static str),
+    Symbol(&'static str),
+}
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Atom(ref x) => write!(f, "'{}'", x),
+            Token::Var(ref x) => write!(f, "{}", x),
+            Token::Integer(ref x) => write!(f, "{}", x),
+            Token::Keyword(x) | Token::Symbol(x) => write!(f, "`{}`", x),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut chars = text.chars().peekable();
+    let mut tokens = Vec::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '%' {
+            while chars.peek().is_some_and(|&c| c != '\n') {
+                chars.next();
+            }
+        } else if c.is_ascii_digit() {
+            tokens.push(Token::Integer(tokenize_integer(&mut chars)?));
+        } else if c == '$' {
+            chars.next();
+            let c = match chars.next() {
+                Some('\\') => {
+                    match chars.next() {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('s') => ' ',
+                        Some('e') => '\x1b',
+                        Some('0') => '\0',
+                        Some(c) => c,
+                        None => return Err("Unexpected EOF".into()),
+                    }
+                }
+                Some(c) => c,
+                None => return Err("Unexpected EOF".into()),
+            };
+            tokens.push(Token::Integer(BigInt::from(u32::from(c))));
+        } else if c == '\'' {
+            chars.next();
+            let mut name = String::new();
+            loop {
+                match chars.next() {
+                    Some('\\') => name.extend(chars.next()),
+                    Some('\'') => break,
+                    Some(c) => name.push(c),
+                    None => return Err("Unexpected EOF".into()),
+                }
+            }
+            tokens.push(Token::Atom(name));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' || c == '@' {
+                    name.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            if c.is_uppercase() || c == '_' {
+                tokens.push(Token::Var(name));
+            } else if let Some(keyword) = KEYWORDS.iter().find(|k| **k == name) {
+                tokens.push(Token::Keyword(keyword));
+            } else {
+                tokens.push(Token::Atom(name));
+            }
+        } else {
+            let rest = chars.clone().take(3).collect::<String>();
+            let symbol = SYMBOLS.iter()
+                .find(|s| rest.starts_with(**s))
+                .ok_or_else(|| format!("Unexpected character: {:?}", c))?;
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+    Ok(tokens)
+}
+
+// NOTE: `Base#Digits` and `_` separators (e.g., `16#ff_ff`) are supported
+fn tokenize_integer(chars: &mut Peekable<Chars>) -> Result<BigInt> {
+    let digits = |chars: &mut Peekable<Chars>| {
+        let mut digits = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                chars.next();
+                if c != '_' {
+                    digits.push(c);
+                }
+            } else {
+                break;
+            }
+        }
+        digits
+    };
+    let mut text = digits(chars);
+    let mut radix = 10;
+    if chars.peek() == Some(&'#') {
+        chars.next();
+        radix = text.parse::<u32>()
+            .ok()
+            .filter(|r| (2..37).contains(r))
+            .ok_or_else(|| format!("Invalid radix: {}", text))?;
+        text = digits(chars);
+    }
+    let value = if text.chars().all(|c| c.is_digit(radix)) {
+        BigInt::parse_bytes(text.as_bytes(), radix)
+    } else {
+        None
+    };
+    value.ok_or_else(|| format!("Invalid integer: {}", text).into())
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+impl Parser {
+    fn new(text: &str) -> Result<Self> {
+        Ok(Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        })
+    }
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.position + n)
+    }
+    fn next(&mut self) -> Result<Token> {
+        let token = self.peek().cloned().ok_or("Unexpected EOF")?;
+        self.position += 1;
+        Ok(token)
+    }
+    fn consume(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, expected: &Token) -> Result<()> {
+        match self.peek() {
+            Some(t) if t == expected => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(t) => Err(format!("Expected {}, but got {}", expected, t).into()),
+            None => Err(format!("Expected {}, but reached EOF", expected).into()),
+        }
+    }
+    fn expect_symbol(&mut self, symbol: &'static str) -> Result<()> {
+        self.expect(&Token::Symbol(symbol))
+    }
+    fn expect_eof(&self) -> Result<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(t) => Err(format!("Unexpected token: {}", t).into()),
+        }
+    }
+    fn expect_atom(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Atom(name) => Ok(name),
+            t => Err(format!("Expected an atom, but got {}", t).into()),
+        }
+    }
+
+    fn parse_spec_clause(&mut self) -> Result<SpecClause> {
+        if let Some(&Token::Atom(_)) = self.peek() {
+            self.position += 1;
+        }
+        self.expect_symbol("(")?;
+        let args = self.parse_types(")")?;
+        self.expect_symbol("->")?;
+        let return_type = self.parse_top_type()?;
+        let mut constraints = Vec::new();
+        if self.consume(&Token::Keyword("when")) {
+            loop {
+                constraints.push(self.parse_constraint()?);
+                if !self.consume(&Token::Symbol(",")) {
+                    break;
+                }
+            }
+        }
+        Ok(SpecClause {
+            args,
+            return_type,
+            constraints,
+        })
+    }
+
+    // NOTE: The old `is_subtype(Var, Type)` form is also accepted
+    fn parse_constraint(&mut self) -> Result<Constraint> {
+        let is_old_form = self.peek() == Some(&Token::Atom("is_subtype".to_string()));
+        if is_old_form {
+            self.position += 1;
+            self.expect_symbol("(")?;
+        }
+        let var = match self.next()? {
+            Token::Var(ref name) if name != "_" => name.clone(),
+            t => return Err(format!("Expected a type variable, but got {}", t).into()),
+        };
+        self.expect_symbol(if is_old_form { "," } else { "::" })?;
+        let subtype = self.parse_top_type()?;
+        if is_old_form {
+            self.expect_symbol(")")?;
+        }
+        Ok(Constraint { var, subtype })
+    }
+
+    // NOTE: Precedence (loosest first): `::`, `|`, `..`, `+ - bor bxor bsl bsr`,
+    // `* div rem band`, unary `- + bnot`
+    fn parse_top_type(&mut self) -> Result<Type> {
+        let mut types = vec![self.parse_annotated_type()?];
+        while self.consume(&Token::Symbol("|")) {
+            types.push(self.parse_annotated_type()?);
+        }
+        Ok(if types.len() == 1 { types.remove(0) } else { ty::union(&types) })
+    }
+    fn parse_annotated_type(&mut self) -> Result<Type> {
+        if let (Some(Token::Var(name)), Some(&Token::Symbol("::"))) = (self.peek(),
+                                                                       self.peek_nth(1)) {
+            let name = name.clone();
+            self.position += 2;
+            let value = self.parse_top_type()?;
+            // NOTE: A variable referred in its own annotation is the recursive type
+            let rec = ty::RecType::new(&name, value);
+            if rec.unfold() != rec.body {
+                return Ok(From::from(rec));
+            }
+            return Ok(From::from(ty::Var::with_value(&name, rec.body)));
+        }
+        self.parse_range_type()
+    }
+    fn parse_range_type(&mut self) -> Result<Type> {
+        let low = self.parse_add_op_type()?;
+        if !self.consume(&Token::Symbol("..")) {
+            return Ok(low);
+        }
+        let high = self.parse_add_op_type()?;
+        let mut range = ty::integer();
+        if let Some(low) = integer_value(&low)? {
+            range = range.min(low);
+        }
+        if let Some(high) = integer_value(&high)? {
+            range = range.max(high);
+        }
+        Ok(From::from(range))
+    }
+    fn parse_add_op_type(&mut self) -> Result<Type> {
+        self.parse_binary_op_type(ADD_OPERATORS, Parser::parse_mult_op_type)
+    }
+    fn parse_mult_op_type(&mut self) -> Result<Type> {
+        self.parse_binary_op_type(MULT_OPERATORS, Parser::parse_unary_op_type)
+    }
+    fn parse_binary_op_type(&mut self,
+                            operators: &[&str],
+                            parse_operand: fn(&mut Parser) -> Result<Type>)
+                            -> Result<Type> {
+        let mut left = parse_operand(self)?;
+        loop {
+            let operator = match self.peek() {
+                Some(&Token::Symbol(x)) |
+                Some(&Token::Keyword(x)) if operators.contains(&x) => x,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            let right = parse_operand(self)?;
+            let (l, r) = (integer_value(&left)?, integer_value(&right)?);
+            let value = l.and_then(|l| r.and_then(|r| ast::integer_binary_op(operator, l, r)));
+            left = From::from(value.map_or_else(ty::integer, |v| ty::integer().value(v)));
+        }
+    }
+    fn parse_unary_op_type(&mut self) -> Result<Type> {
+        let operator = match self.peek() {
+            Some(&Token::Symbol(x)) |
+            Some(&Token::Keyword(x)) if x == "-" || x == "+" || x == "bnot" => x,
+            _ => return self.parse_primary_type(),
+        };
+        self.position += 1;
+        let operand = integer_value(&self.parse_unary_op_type()?)?;
+        let value = operand.and_then(|v| ast::integer_unary_op(operator, v));
+        Ok(From::from(value.map_or_else(ty::integer, |v| ty::integer().value(v))))
+    }
+    fn parse_primary_type(&mut self) -> Result<Type> {
+        match self.next()? {
+            Token::Var(name) => Ok(From::from(ty::Var::new(&name))),
+            Token::Integer(value) => Ok(From::from(ty::integer().value(value))),
+            Token::Atom(name) => {
+                if self.consume(&Token::Symbol(":")) {
+                    let type_name = self.expect_atom()?;
+                    self.expect_symbol("(")?;
+                    Ok(ty::remote(&name, &type_name, &self.parse_types(")")?))
+                } else if self.consume(&Token::Symbol("(")) {
+                    let args = self.parse_types(")")?;
+                    if name == "tuple" && args.is_empty() {
+                        Ok(From::from(ty::TupleType::any()))
+                    } else {
+                        Ok(ty::local(&name, &args))
+                    }
+                } else {
+                    Ok(ty::atom(&name))
+                }
+            }
+            Token::Keyword("fun") => self.parse_fun_type(),
+            Token::Symbol("(") => {
+                let t = self.parse_top_type()?;
+                self.expect_symbol(")")?;
+                Ok(t)
+            }
+            Token::Symbol("[") => {
+                if self.consume(&Token::Symbol("]")) {
+                    return Ok(From::from(ty::NilType));
+                }
+                let element = self.parse_top_type()?;
+                if self.consume(&Token::Symbol(",")) {
+                    self.expect_symbol("...")?;
+                    self.expect_symbol("]")?;
+                    Ok(ty::builtin1("nonempty_list", element))
+                } else {
+                    self.expect_symbol("]")?;
+                    Ok(ty::builtin1("list", element))
+                }
+            }
+            Token::Symbol("{") => {
+                Ok(From::from(ty::TupleType { elements: Some(self.parse_types("}")?) }))
+            }
+            Token::Symbol("#") => {
+                if self.consume(&Token::Symbol("{")) {
+                    self.parse_map_type()
+                } else {
+                    let name = self.expect_atom()?;
+                    self.expect_symbol("{")?;
+                    self.parse_record_type(name)
+                }
+            }
+            Token::Symbol("<<") => self.parse_bitstring_type(),
+            t => Err(format!("Unexpected token: {}", t).into()),
+        }
+    }
+
+    // NOTE: `fun()`, `fun((...) -> T)` and `fun((T1, ...) -> T; ...)`
+    fn parse_fun_type(&mut self) -> Result<Type> {
+        self.expect_symbol("(")?;
+        if self.consume(&Token::Symbol(")")) {
+            return Ok(From::from(ty::FunType::any()));
+        }
+        let mut clauses = Vec::new();
+        loop {
+            self.expect_symbol("(")?;
+            let args = if self.consume(&Token::Symbol("...")) {
+                self.expect_symbol(")")?;
+                None
+            } else {
+                Some(self.parse_types(")")?)
+            };
+            self.expect_symbol("->")?;
+            let return_type = self.parse_top_type()?;
+            clauses.push(ty::FunSpec { args, return_type });
+            if !self.consume(&Token::Symbol(";")) {
+                break;
+            }
+        }
+        self.expect_symbol(")")?;
+        Ok(From::from(ty::FunType { clauses }))
+    }
+
+    fn parse_map_type(&mut self) -> Result<Type> {
+        let mut pairs = Vec::new();
+        if !self.consume(&Token::Symbol("}")) {
+            loop {
+                let key = self.parse_top_type()?;
+                let is_assoc = self.consume(&Token::Symbol("=>"));
+                if !is_assoc {
+                    self.expect_symbol(":=")?;
+                }
+                let value = self.parse_top_type()?;
+                pairs.push(ty::MapPair { is_assoc, key, value });
+                if !self.consume(&Token::Symbol(",")) {
+                    self.expect_symbol("}")?;
+                    break;
+                }
+            }
+        }
+        Ok(From::from(ty::MapType { pairs }))
+    }
+
+    fn parse_record_type(&mut self, name: String) -> Result<Type> {
+        let mut fields = Vec::new();
+        if !self.consume(&Token::Symbol("}")) {
+            loop {
+                let field = self.expect_atom()?;
+                self.expect_symbol("::")?;
+                let value = self.parse_top_type()?;
+                fields.push(ty::RecordField { name: field, value });
+                if !self.consume(&Token::Symbol(",")) {
+                    self.expect_symbol("}")?;
+                    break;
+                }
+            }
+        }
+        Ok(From::from(ty::RecordType { name, fields }))
+    }
+
+    // NOTE: `<<>>`, `<<_:M>>`, `<<_:_*N>>` and `<<_:M, _:_*N>>` (`0` means "absent")
+    fn parse_bitstring_type(&mut self) -> Result<Type> {
+        let mut bitstring = ty::BitstringType::default();
+        if self.consume(&Token::Symbol(">>")) {
+            return Ok(From::from(bitstring));
+        }
+        let underscore = Token::Var("_".to_string());
+        self.expect(&underscore)?;
+        self.expect_symbol(":")?;
+        let is_unit_only = self.peek() == Some(&underscore);
+        if !is_unit_only {
+            bitstring.bits = self.parse_bitstring_size()?;
+            if self.consume(&Token::Symbol(",")) {
+                self.expect(&underscore)?;
+                self.expect_symbol(":")?;
+            } else {
+                self.expect_symbol(">>")?;
+                return Ok(From::from(bitstring));
+            }
+        }
+        self.expect(&underscore)?;
+        self.expect_symbol("*")?;
+        bitstring.align = self.parse_bitstring_size()?;
+        self.expect_symbol(">>")?;
+        Ok(From::from(bitstring))
+    }
+    fn parse_bitstring_size(&mut self) -> Result<usize> {
+        let size = self.parse_add_op_type()?;
+        integer_value(&size)?
+            .and_then(|n| n.to_usize())
+            .ok_or_else(|| format!("Invalid bitstring size: {}", size).into())
+    }
+
+    fn parse_types(&mut self, close: &'static str) -> Result<Vec<Type>> {
+        let mut types = Vec::new();
+        if self.consume(&Token::Symbol(close)) {
+            return Ok(types);
+        }
+        loop {
+            types.push(self.parse_top_type()?);
+            if !self.consume(&Token::Symbol(",")) {
+                self.expect_symbol(close)?;
+                return Ok(types);
+            }
+        }
+    }
+}
+
+/// Returns the value of a singleton integer type (`None` for `integer()`).
+fn integer_value(t: &Type) -> Result<Option<BigInt>> {
+    match *t {
+        Type::Integer(ref x) => Ok(x.get_single_value()),
+        _ => Err(format!("Expected an integer, but got {}", t).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use env::Env;
+    use lattice;
+    use pretty::Printer;
+    use resolve::Resolver;
+    use ty;
+    use ty::Type;
+    use super::*;
+
+    fn assert_round_trip(t: Type) {
+        let text = t.to_string();
+        assert_eq!(parse_type(&text).unwrap(), t, "{}", text);
+    }
+
+    // NOTE: Built-in types are parsed as `LocalType`s, so they are compared after resolution
+    fn assert_resolved_round_trip(t: Type) {
+        let env = Env::new();
+        let resolver = Resolver::new(&env, "m");
+        let text = t.to_string();
+        assert_eq!(resolver.resolve(&parse_type(&text).unwrap()), t, "{}", text);
+    }
+
+    fn tuple(elements: Vec<Type>) -> Type {
+        From::from(ty::TupleType { elements: Some(elements) })
+    }
+
+    #[test]
+    fn builtin_types_round_trip() {
+        assert_resolved_round_trip(lattice::any());
+        assert_resolved_round_trip(lattice::none());
+        assert_resolved_round_trip(From::from(ty::PidType));
+        assert_resolved_round_trip(From::from(ty::PortType));
+        assert_resolved_round_trip(From::from(ty::ReferenceType));
+        assert_resolved_round_trip(From::from(ty::FloatType));
+        assert_resolved_round_trip(From::from(ty::AtomType::any()));
+        assert_resolved_round_trip(From::from(ty::MapType::any()));
+        assert_resolved_round_trip(From::from(ty::integer()));
+        assert_resolved_round_trip(From::from(ty::integer().min(0)));
+        assert_resolved_round_trip(From::from(ty::integer().min(1)));
+        assert_resolved_round_trip(From::from(ty::integer().max(-1)));
+    }
+
+    #[test]
+    fn list_types_round_trip() {
+        let nil: Type = From::from(ty::NilType);
+        let atom = ty::atom("a");
+        assert_round_trip(nil.clone());
+        assert_resolved_round_trip(lattice::list(false, atom.clone(), nil.clone()));
+        assert_resolved_round_trip(lattice::list(true, atom.clone(), nil.clone()));
+        assert_resolved_round_trip(lattice::list(false, atom.clone(), ty::atom("b")));
+        assert_resolved_round_trip(lattice::list(true, atom, ty::atom("b")));
+    }
+
+    #[test]
+    fn atom_types_round_trip() {
+        assert_round_trip(ty::atom("foo"));
+        assert_round_trip(ty::atom("Foo"));
+        assert_round_trip(ty::atom("foo bar"));
+        assert_round_trip(ty::atom("fun"));
+        assert_round_trip(ty::atom("it's"));
+    }
+
+    #[test]
+    fn integer_types_round_trip() {
+        assert_round_trip(From::from(ty::integer().value(3)));
+        assert_round_trip(From::from(ty::integer().value(-3)));
+        assert_round_trip(From::from(ty::integer().min(1).max(10)));
+        assert_round_trip(From::from(ty::integer().min(-10).max(-1)));
+        let huge = BigInt::from(u64::MAX) * 4;
+        assert_round_trip(From::from(ty::integer().min(0).max(huge)));
+    }
+
+    #[test]
+    fn bitstring_types_round_trip() {
+        let empty = ty::BitstringType::default();
+        assert_eq!(empty.to_string(), "<<>>");
+        assert_round_trip(From::from(empty.clone()));
+        assert_round_trip(From::from(empty.clone().bits(3)));
+        assert_round_trip(From::from(empty.clone().align(8)));
+        assert_round_trip(From::from(empty.bits(3).align(5)));
+        assert_eq!(parse_type("<<_:0, _:_*0>>").unwrap(),
+                   From::from(ty::BitstringType::default()));
+    }
+
+    #[test]
+    fn fun_types_round_trip() {
+        let clause = |args| {
+            ty::FunSpec {
+                args,
+                return_type: ty::atom("ok"),
+            }
+        };
+        assert_round_trip(From::from(ty::FunType::any()));
+        assert_round_trip(From::from(ty::FunType { clauses: vec![clause(Some(vec![]))] }));
+        assert_round_trip(From::from(ty::FunType { clauses: vec![clause(None)] }));
+        let args = Some(vec![ty::atom("a"), ty::atom("b")]);
+        assert_round_trip(From::from(ty::FunType { clauses: vec![clause(args.clone())] }));
+        let fun = Type::from(ty::FunType { clauses: vec![clause(args), clause(None)] });
+        assert_eq!(fun.to_string(), "fun(('a','b') -> 'ok'; (...) -> 'ok')");
+        assert_eq!(Printer::new().width(20).print(&fun, 0),
+                   "fun(('a', 'b') -> 'ok';\n    (...) -> 'ok')");
+        assert_round_trip(fun);
+    }
+
+    #[test]
+    fn compound_types_round_trip() {
+        assert_round_trip(From::from(ty::TupleType::any()));
+        assert_round_trip(tuple(vec![]));
+        assert_round_trip(tuple(vec![ty::atom("a"), From::from(ty::integer().value(1))]));
+        assert_round_trip(From::from(ty::RecordType {
+            name: "rec".to_string(),
+            fields: vec![ty::RecordField {
+                             name: "field".to_string(),
+                             value: ty::atom("a"),
+                         }],
+        }));
+        assert_round_trip(ty::union(&[ty::atom("a"), tuple(vec![ty::atom("b")])]));
+    }
+
+    #[test]
+    fn map_types_keep_association_kinds() {
+        let pair = |is_assoc, key| {
+            ty::MapPair {
+                is_assoc,
+                key: ty::atom(key),
+                value: ty::atom("v"),
+            }
+        };
+        let map: Type = From::from(ty::MapType { pairs: vec![pair(true, "a"), pair(false, "b")] });
+        assert_eq!(map.to_string(), "#{'a'=>'v','b':='v'}");
+        assert_round_trip(map);
+        assert!(parse_type("#{a}").is_err());
+        assert!(parse_type("#{a = b}").is_err());
+    }
+
+    #[test]
+    fn reference_types_round_trip() {
+        assert_round_trip(ty::local("t", &[]));
+        assert_round_trip(ty::local("t", &[ty::atom("a"), From::from(ty::Var::new("T"))]));
+        assert_round_trip(ty::remote("m", "t", &[ty::atom("a")]));
+        assert_round_trip(ty::remote("Mod", "and", &[]));
+        let user_defined = ty::UserDefinedType {
+            is_opaque: false,
+            name: "t".to_string(),
+            args: vec![ty::atom("a")],
+            body: ty::atom("b"),
+        };
+        assert_eq!(parse_type(&user_defined.to_string()).unwrap(),
+                   ty::local("t", &[ty::atom("a")]));
+    }
+
+    #[test]
+    fn var_types_round_trip() {
+        assert_round_trip(From::from(ty::Var::new("T")));
+        assert_round_trip(From::from(ty::Var::new("_")));
+        assert_round_trip(From::from(ty::Var::with_value("T", ty::atom("a"))));
+        let annotated = From::from(ty::Var::with_value("T", ty::atom("a")));
+        assert_round_trip(ty::union(&[annotated, ty::atom("b")]));
+    }
+
+    #[test]
+    fn rec_types_round_trip() {
+        let var = || From::from(ty::Var::new("T"));
+        let body = ty::union(&[ty::atom("nil"), tuple(vec![ty::atom("a"), var()])]);
+        let rec: Type = From::from(ty::RecType::new("T", body));
+        assert_round_trip(rec.clone());
+        assert_round_trip(ty::union(&[rec.clone(), ty::atom("b")]));
+        assert_round_trip(tuple(vec![rec.clone(), rec.clone()]));
+        let nested = ty::RecType::new("U", tuple(vec![rec.clone(), From::from(ty::Var::new("U"))]));
+        assert_round_trip(From::from(nested));
+        for width in &[10, 80] {
+            let text = Printer::new().width(*width).print(&rec, 0);
+            assert_eq!(parse_type(&text).unwrap(), rec, "{}", text);
+        }
+    }
+
+    #[test]
+    fn operators_are_evaluated() {
+        assert_eq!(parse_type("1 + 2 * 3").unwrap(),
+                   From::from(ty::integer().value(7)));
+        assert_eq!(parse_type("-(1 bsl 3)..bnot 0").unwrap(),
+                   From::from(ty::integer().min(-8).max(-1)));
+        assert!(parse_type("a + 1").is_err());
+    }
+
+    #[test]
+    fn specs_are_parsed() {
+        let spec = parse_spec("f(A, [A]) -> boolean() when A :: atom(); (_, _) -> true").unwrap();
+        assert_eq!(spec.clauses.len(), 2);
+        assert_eq!(spec.clauses[0].args[0], From::from(ty::Var::new("A")));
+        assert_eq!(spec.clauses[0].constraints.len(), 1);
+        assert_eq!(spec.clauses[1].return_type, ty::atom("true"));
+        assert!(parse_spec("(a) -> b; (a, b) -> c").is_err());
+        assert!(parse_type("{a, b").is_err());
+        assert!(parse_type("a b").is_err());
+    }
+
+    #[test]
+    fn spec_attributes_quote_function_names() {
+        let spec = parse_spec("(a) -> ok; (b) -> error").unwrap();
+        let attribute = spec.to_attribute("Send");
+        assert_eq!(attribute, "-spec 'Send'('a') -> 'ok';\n      'Send'('b') -> 'error'.");
+        let text = attribute.trim_start_matches("-spec ").trim_end_matches('.');
+        assert_eq!(parse_spec(text).unwrap().to_attribute("Send"), attribute);
+        assert_eq!(spec.to_attribute("send"),
+                   "-spec send('a') -> 'ok';\n      send('b') -> 'error'.");
+    }
+}
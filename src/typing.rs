@@ -6,6 +6,7 @@ use erl_type;
 use erl_type::Type;
 use erl_type::TypeClass;
 use erl_type::FunSpec;
+use beam;
 use beam::Module;
 
 #[derive(Hash, PartialEq, Eq, Debug)]
@@ -19,14 +20,14 @@ impl TypeKey {
         TypeKey {
             module: None,
             name: name.to_string(),
-            arity: arity,
+            arity,
         }
     }
     pub fn remote(module: &str, name: &str, arity: u8) -> Self {
         TypeKey {
             module: Some(module.to_string()),
             name: name.to_string(),
-            arity: arity,
+            arity,
         }
     }
 }
@@ -43,19 +44,31 @@ pub struct Env {
     pub types: HashMap<TypeKey, Box<dyn TypeClass>>,
     pub specs: HashMap<SpecKey, FunSpec>, // TODO: => ftypes(?)
 }
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl Env {
     pub fn new() -> Self {
-        let types = HashMap::from_iter(built_in_types().into_iter());
+        let types = HashMap::from_iter(built_in_types());
         Env {
             modules: Vec::new(),
-            types: types,
+            types,
             specs: HashMap::new(),
         }
     }
     pub fn add_module(&mut self, module: Module) {
-        self.load_types(&module);
-        self.load_specs(&module);
-        self.modules.push(module);
+        self.add_modules(vec![module]);
+    }
+    /// Adds `modules` and updates the client modules of all the modules at once.
+    pub fn add_modules(&mut self, modules: Vec<Module>) {
+        for module in modules {
+            self.load_types(&module);
+            self.load_specs(&module);
+            self.modules.push(module);
+        }
+        beam::resolve_client_modules(&mut self.modules);
     }
     pub fn load_types(&mut self, module: &Module) {
         // TODO: Add Form::get_module()
@@ -146,7 +159,7 @@ fn ast_type_to_erl_type(ty: &ast::ty::Type) -> Type {
             } else {
                 panic!("{:?}", x.low);
             };
-            let high = if let erl_type::Type::Integer(high) = ast_type_to_erl_type(&x.high) {
+            if let erl_type::Type::Integer(high) = ast_type_to_erl_type(&x.high) {
                 if !high.is_any() {
                     range = range.max(high.get_single_value().unwrap());
                 }
@@ -169,11 +182,11 @@ fn ast_type_to_erl_type(ty: &ast::ty::Type) -> Type {
             })
         }
         ty::Type::BuiltIn(ref x) => {
-            From::from(erl_type::builtin(&x.name,
-                                         &x.args
-                                             .iter()
-                                             .map(ast_type_to_erl_type)
-                                             .collect::<Vec<_>>()))
+            erl_type::builtin(&x.name,
+                              &x.args
+                                  .iter()
+                                  .map(ast_type_to_erl_type)
+                                  .collect::<Vec<_>>())
         }
         ty::Type::Record(ref x) => {
             Type::from(erl_type::RecordType {
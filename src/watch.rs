@@ -0,0 +1,307 @@
+//! Incremental re-analysis of modified beam files.
+//!
+//! `BeamWatcher` polls the modification times of beam files (no OS-specific notification API
+//! is used), and `Analysis` reloads the modified modules and solves the affected components
+//! again (see `Solver::update`).
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use code_path;
+use diagnostic;
+use diagnostic::Diagnostic;
+use env::Env;
+use intern;
+use module::Remote;
+use module::Result;
+use solver::Solver;
+use ty::FunSpec;
+
+// NOTE: The size is compared too because modification times may be coarse
+type Stamp = (SystemTime, u64);
+
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// Modified or added beam files
+    pub modified: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct BeamWatcher {
+    dirs: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+    stamps: BTreeMap<PathBuf, Stamp>,
+    pending: BTreeMap<PathBuf, Stamp>,
+}
+impl BeamWatcher {
+    /// Makes a watcher of `files` and the beam files in the `ebin` directories under `dirs`
+    /// (see `code_path::find_beams_in_dir`).
+    ///
+    /// The files existing at this point are regarded as unchanged.
+    pub fn new(dirs: Vec<PathBuf>, files: Vec<PathBuf>) -> Result<Self> {
+        let mut watcher = BeamWatcher {
+            dirs,
+            files,
+            stamps: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        };
+        watcher.stamps = watcher.collect_stamps()?;
+        Ok(watcher)
+    }
+
+    /// Returns the changes since the last scan.
+    ///
+    /// A modification is reported when the file has not changed between two scans,
+    /// so that files being written by the compiler are not reported.
+    pub fn scan(&mut self) -> Result<Changes> {
+        let current = self.collect_stamps()?;
+        let mut changes = Changes::default();
+        for (path, stamp) in &current {
+            if self.stamps.get(path) == Some(stamp) {
+                self.pending.remove(path);
+            } else if self.pending.get(path) == Some(stamp) {
+                self.pending.remove(path);
+                self.stamps.insert(path.clone(), *stamp);
+                changes.modified.push(path.clone());
+            } else {
+                self.pending.insert(path.clone(), *stamp);
+            }
+        }
+        let removed = self.stamps
+            .keys()
+            .filter(|p| !current.contains_key(*p))
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in removed {
+            self.stamps.remove(&path);
+            changes.removed.push(path);
+        }
+        self.pending.retain(|p, _| current.contains_key(p));
+        Ok(changes)
+    }
+
+    /// Forgets the state of `path`, so that it is reported as modified again.
+    pub fn forget<P: AsRef<Path>>(&mut self, path: P) {
+        self.stamps.remove(path.as_ref());
+    }
+
+    fn collect_stamps(&self) -> Result<BTreeMap<PathBuf, Stamp>> {
+        let mut paths = self.files.clone();
+        for dir in &self.dirs {
+            paths.extend(code_path::find_beams_in_dir(dir)?);
+        }
+        let mut stamps = BTreeMap::new();
+        for path in paths {
+            // NOTE: Files may be removed while scanning
+            if let Ok(metadata) = fs::metadata(&path) {
+                stamps.insert(path, (metadata.modified()?, metadata.len()));
+            }
+        }
+        Ok(stamps)
+    }
+}
+
+#[derive(Debug)]
+pub struct Analysis {
+    pub env: Env,
+    pub signatures: BTreeMap<Remote, FunSpec>,
+
+    /// Module name => the findings in it (see `diagnostic::check`)
+    pub diagnostics: BTreeMap<String, Vec<Diagnostic>>,
+}
+impl Analysis {
+    pub fn new(env: Env) -> Self {
+        let signatures = Solver::new(&env).solve();
+        let mut analysis = Analysis {
+            env,
+            signatures,
+            diagnostics: BTreeMap::new(),
+        };
+        let modules = analysis.env.modules.keys().cloned().collect();
+        analysis.check(&modules);
+        analysis.collect_garbage();
+        analysis
+    }
+
+    /// Reloads the changed modules and analyzes the affected functions again.
+    ///
+    /// A modified beam file is ignored if another file of the same module is loaded
+    /// (i.e., it is shadowed). Beam files which can't be parsed are reported in `errors`
+    /// and the other changes are applied.
+    pub fn update(&mut self, changes: &Changes) -> Update {
+        let mut update = Update::default();
+        let mut modules = BTreeSet::new();
+        for beam_file in &changes.removed {
+            let name = self.module_of(beam_file);
+            if let Some(name) = name {
+                self.env.remove_module(&name);
+                modules.insert(name);
+            }
+        }
+        for beam_file in &changes.modified {
+            let name = beam_file.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+            let is_shadowed = self.env
+                .modules
+                .get(name)
+                .is_some_and(|m| m.beam_file.as_deref() != Some(beam_file.as_path()));
+            if is_shadowed {
+                continue;
+            }
+            match self.env.reload_beam_file(beam_file) {
+                Ok(name) => {
+                    modules.insert(name);
+                    update.reloaded.push(beam_file.clone());
+                }
+                Err(e) => update.errors.push((beam_file.clone(), e.to_string())),
+            }
+        }
+        if modules.is_empty() {
+            return update;
+        }
+
+        let solver = Solver::with_signatures(&self.env, mem::take(&mut self.signatures));
+        let (signatures, solved) = solver.update(&modules);
+        self.signatures = signatures;
+        modules.extend(solved.into_iter().map(|f| f.module));
+        self.check(&modules);
+        self.collect_garbage();
+        update.checked_modules = modules;
+        update
+    }
+
+    /// Returns all the findings sorted by location.
+    pub fn all_diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = self.diagnostics.values().flatten().cloned().collect::<Vec<_>>();
+        diagnostics.sort_by(|a, b| {
+            (&a.location, &a.function, a.kind).cmp(&(&b.location, &b.function, b.kind))
+        });
+        diagnostics
+    }
+
+    fn check(&mut self, modules: &BTreeSet<String>) {
+        for m in modules {
+            self.diagnostics.remove(m);
+        }
+        for d in diagnostic::check_modules(&self.env, &self.signatures, modules) {
+            self.diagnostics.entry(d.function.module.clone()).or_default().push(d);
+        }
+    }
+
+    // NOTE: Only the types in the graphs (kept by the modules) are referred between the updates,
+    // so the other interned types (e.g., the ones of the removed modules) are freed
+    fn collect_garbage(&self) {
+        intern::collect_garbage();
+    }
+
+    fn module_of(&self, beam_file: &Path) -> Option<String> {
+        self.env
+            .modules
+            .values()
+            .find(|m| m.beam_file.as_deref() == Some(beam_file))
+            .map(|m| m.name.clone())
+    }
+}
+
+/// Returns the findings which appeared in `new` and the ones which disappeared from `old`.
+///
+/// Findings are compared by their fingerprints, so moved ones are not reported.
+pub fn diff(old: &[Diagnostic], new: &[Diagnostic]) -> (Vec<Diagnostic>, Vec<Diagnostic>) {
+    // NOTE: Identical findings in a function are distinguished by their order
+    let keyed = |diagnostics: &[Diagnostic]| {
+        let mut counts = BTreeMap::new();
+        let mut keyed = BTreeMap::new();
+        for d in diagnostics {
+            let fingerprint = d.fingerprint();
+            let count = counts.entry(fingerprint).or_insert(0);
+            keyed.insert((fingerprint, *count), d.clone());
+            *count += 1;
+        }
+        keyed
+    };
+    let (old, new) = (keyed(old), keyed(new));
+    let appeared = new.iter().filter(|(k, _)| !old.contains_key(*k)).map(|(_, d)| d.clone());
+    let disappeared = old.iter().filter(|(k, _)| !new.contains_key(*k)).map(|(_, d)| d.clone());
+    (appeared.collect(), disappeared.collect())
+}
+
+#[derive(Debug, Default)]
+pub struct Update {
+    pub reloaded: Vec<PathBuf>,
+
+    /// The modules whose findings were updated (including removed ones)
+    pub checked_modules: BTreeSet<String>,
+
+    /// Beam files which could not be reloaded (and the reasons)
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::process;
+    use std::thread;
+    use std::time::Duration;
+    use module::Module;
+    use super::*;
+
+    const HELLO_BEAM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/beam/testdata/hello.beam");
+
+    fn analysis(beam_file: &Path) -> Analysis {
+        let mut env = Env::new();
+        env.add_module(Module::from_beam_file(beam_file).unwrap());
+        Analysis::new(env)
+    }
+
+    // NOTE: A collection is deferred while the other tests are solving (see `intern::Pin`)
+    fn collect() {
+        while !intern::collect_garbage() {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    // NOTE: `TypeId::get` panics if the type has been collected
+    fn check_types(analysis: &Analysis) {
+        let graphs = analysis.env.modules.values().flat_map(|m| m.functions.values());
+        for id in graphs.flat_map(|f| f.graph.type_ids()) {
+            id.get();
+        }
+    }
+
+    #[test]
+    fn collecting_an_analysis_keeps_the_types_of_the_others() {
+        let dir = ::std::env::temp_dir().join(format!("diagnoser-watch-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let beam_file = dir.join("hello.beam");
+        fs::copy(HELLO_BEAM, &beam_file).unwrap();
+
+        let first = analysis(Path::new(HELLO_BEAM));
+        let mut second = analysis(&beam_file);
+        collect();
+        check_types(&first);
+        check_types(&second);
+
+        drop(first);
+        collect();
+        check_types(&second);
+
+        let changes = Changes {
+            modified: vec![beam_file],
+            removed: Vec::new(),
+        };
+        let update = second.update(&changes);
+        assert!(update.errors.is_empty());
+        assert_eq!(update.checked_modules.into_iter().collect::<Vec<_>>(), ["hello"]);
+        check_types(&second);
+        assert_eq!(second.signatures[&Remote::new("hello", "world", 0)].to_string(),
+                   "() -> 'ok'");
+    }
+}
@@ -0,0 +1,29 @@
+//! Helpers for writing XML (used by the GraphML graphs and the Checkstyle reports).
+
+/// Escapes the special characters of XML.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(escape("fun((A) -> 'b' | \"c\") & <d>"),
+                   "fun((A) -&gt; &apos;b&apos; | &quot;c&quot;) &amp; &lt;d&gt;");
+        assert_eq!(escape("plain"), "plain");
+    }
+}